@@ -0,0 +1,96 @@
+use syn::{Attribute, Field, LitStr};
+
+/// Attributes of a field.
+#[derive(Default)]
+pub struct FieldOptions {
+  pub rename: Option<String>,
+  pub skip: bool,
+  pub default: bool,
+  pub unset_if_none: bool,
+}
+
+impl FieldOptions {
+  pub fn parse(field: &Field) -> syn::Result<Self> {
+    let mut options = FieldOptions::default();
+
+    for attr in cdrs_attrs(&field.attrs) {
+      attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("rename") {
+          let column: LitStr = meta.value()?.parse()?;
+          options.rename = Some(column.value());
+        } else if meta.path.is_ident("skip") {
+          options.skip = true;
+        } else if meta.path.is_ident("default") {
+          options.default = true;
+        } else if meta.path.is_ident("unset_if_none") {
+          options.unset_if_none = true;
+        } else {
+          return Err(meta.error("unsupported cdrs attribute"));
+        }
+
+        Ok(())
+      })?;
+    }
+
+    Ok(options)
+  }
+
+  /// Name of a column a field corresponds to.
+  pub fn column(&self, field: &Field) -> String {
+    self.rename.clone().unwrap_or_else(|| {
+      let ident = field
+        .ident
+        .as_ref()
+        .expect("fields of a struct with named fields have names")
+        .to_string();
+      ident.trim_start_matches("r#").to_string()
+    })
+  }
+}
+
+/// Kind of query values a struct is converted into.
+pub enum ValuesKind {
+  Named,
+  Simple,
+}
+
+/// Attributes of a struct.
+pub struct StructOptions {
+  pub values: ValuesKind,
+}
+
+impl StructOptions {
+  pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+    let mut options = StructOptions {
+      values: ValuesKind::Named,
+    };
+
+    for attr in cdrs_attrs(attrs) {
+      attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("values") {
+          let kind: LitStr = meta.value()?.parse()?;
+          options.values = match kind.value().as_str() {
+            "named" => ValuesKind::Named,
+            "simple" => ValuesKind::Simple,
+            _ => {
+              return Err(syn::Error::new_spanned(
+                kind,
+                "values should be either \"named\" or \"simple\"",
+              ))
+            }
+          };
+        } else {
+          return Err(meta.error("unsupported cdrs attribute"));
+        }
+
+        Ok(())
+      })?;
+    }
+
+    Ok(options)
+  }
+}
+
+fn cdrs_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+  attrs.iter().filter(|attr| attr.path().is_ident("cdrs"))
+}
@@ -0,0 +1,27 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+use crate::{named_fields, read_field};
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  let row = quote! { row };
+  let fields = named_fields(input, "FromRow")?
+    .iter()
+    .map(|field| read_field(field, &row))
+    .collect::<syn::Result<Vec<_>>>()?;
+
+  Ok(quote! {
+    impl #impl_generics ::cdrs_async::row::FromRow for #name #ty_generics #where_clause {
+      fn from_row(
+        row: &::cdrs_async::row::Row,
+      ) -> ::cdrs_async::row::Result<Self> {
+        Ok(#name {
+          #(#fields,)*
+        })
+      }
+    }
+  })
+}
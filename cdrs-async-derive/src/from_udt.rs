@@ -0,0 +1,29 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+use crate::{named_fields, read_field};
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  let udt = quote! { &udt };
+  let fields = named_fields(input, "FromUdt")?
+    .iter()
+    .map(|field| read_field(field, &udt))
+    .collect::<syn::Result<Vec<_>>>()?;
+
+  Ok(quote! {
+    impl #impl_generics ::cdrs_async::types::FromCql for #name #ty_generics #where_clause {
+      type Raw = ::cdrs_async::types::UDT;
+
+      fn from_raw(
+        udt: ::cdrs_async::types::UDT,
+      ) -> ::cdrs_async::row::Result<Self> {
+        Ok(#name {
+          #(#fields,)*
+        })
+      }
+    }
+  })
+}
@@ -0,0 +1,65 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, Field};
+
+use crate::{
+  attrs::{FieldOptions, StructOptions, ValuesKind},
+  field_value, is_option, named_fields,
+};
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+  let struct_options = StructOptions::parse(&input.attrs)?;
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+  let mut columns = vec![];
+  let mut values = vec![];
+  for field in named_fields(input, "IntoQueryValues")? {
+    let options = FieldOptions::parse(field)?;
+    if options.skip {
+      continue;
+    }
+
+    columns.push(options.column(field));
+    values.push(value(field, &options)?);
+  }
+
+  let query_values = match struct_options.values {
+    ValuesKind::Named => quote! {
+      let mut values = ::std::collections::HashMap::new();
+      #(values.insert(#columns.to_string(), #values);)*
+      ::cdrs_async::values::QueryValues::NamedValues(values)
+    },
+    ValuesKind::Simple => quote! {
+      ::cdrs_async::values::QueryValues::SimpleValues(vec![#(#values),*])
+    },
+  };
+
+  Ok(quote! {
+    impl #impl_generics ::std::convert::From<#name #ty_generics>
+      for ::cdrs_async::values::QueryValues #where_clause
+    {
+      fn from(value: #name #ty_generics) -> Self {
+        #query_values
+      }
+    }
+  })
+}
+
+fn value(field: &Field, options: &FieldOptions) -> syn::Result<TokenStream> {
+  let ident = &field.ident;
+
+  if !options.unset_if_none {
+    let value = field_value(field);
+    return Ok(quote! { ::cdrs_async::values::Value::from(#value) });
+  }
+
+  if !is_option(&field.ty) {
+    return Err(syn::Error::new_spanned(
+      field,
+      "unset_if_none can be applied only to fields of Option type",
+    ));
+  }
+
+  Ok(quote! { ::cdrs_async::values::unset_if_none(value.#ident) })
+}
@@ -0,0 +1,38 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+use crate::{attrs::FieldOptions, field_value, named_fields};
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+  let mut values = vec![];
+  for field in named_fields(input, "IntoUdt")? {
+    let options = FieldOptions::parse(field)?;
+    if options.skip {
+      continue;
+    }
+    if options.unset_if_none {
+      return Err(syn::Error::new_spanned(
+        field,
+        "fields of a UDT could not be unset",
+      ));
+    }
+
+    values.push(field_value(field));
+  }
+
+  Ok(quote! {
+    impl #impl_generics ::std::convert::From<#name #ty_generics>
+      for ::cdrs_async::values::Bytes #where_clause
+    {
+      fn from(value: #name #ty_generics) -> Self {
+        let mut bytes = ::std::vec::Vec::new();
+        #(::cdrs_async::types::push_field(&mut bytes, #values);)*
+        ::cdrs_async::values::Bytes::new(bytes)
+      }
+    }
+  })
+}
@@ -0,0 +1,159 @@
+//! Derive macros for `cdrs-async`.
+
+extern crate proc_macro;
+
+mod attrs;
+mod from_row;
+mod from_udt;
+mod into_query_values;
+mod into_udt;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+  parse_macro_input, punctuated::Punctuated, token::Comma, Data, DeriveInput, Field, Fields, Type,
+};
+
+use attrs::FieldOptions;
+
+/// Derives `FromRow` for a struct with named fields. Each field is read
+/// from a column with the same name. Field attributes:
+///
+/// * `#[cdrs(rename = "column")]` reads a field from a column with another name,
+/// * `#[cdrs(skip)]` does not read a field and sets it to a default value,
+/// * `#[cdrs(default)]` sets a field to a default value if a column
+///   is missing or null.
+///
+/// Fields could be of any type which implements `FromCql`. Fields
+/// of `Option` type are `None` if a column is null.
+#[proc_macro_derive(FromRow, attributes(cdrs))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+
+  from_row::derive(&input)
+    .unwrap_or_else(syn::Error::into_compile_error)
+    .into()
+}
+
+/// Derives conversion of a struct with named fields into `QueryValues`.
+/// By default a struct is converted into named values, each field
+/// is a value of a bind marker with the same name. Positional values
+/// in the order of fields are produced if a struct is marked
+/// with `#[cdrs(values = "simple")]`. Field attributes:
+///
+/// * `#[cdrs(rename = "column")]` binds a field to a marker with another name,
+/// * `#[cdrs(skip)]` leaves a field out,
+/// * `#[cdrs(unset_if_none)]` makes a value of `Option` type unset rather than
+///   null if it is `None`, so a column is left intact.
+///
+/// Fields of tuple types are bound as CQL tuples.
+#[proc_macro_derive(IntoQueryValues, attributes(cdrs))]
+pub fn derive_into_query_values(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+
+  into_query_values::derive(&input)
+    .unwrap_or_else(syn::Error::into_compile_error)
+    .into()
+}
+
+/// Derives `FromCql` for a struct which represents a user defined type,
+/// so it could be read from a column, a field of another UDT, an element
+/// of a tuple or a collection. Each field is read from a UDT field with
+/// the same name, field attributes are the same as the ones of `FromRow`.
+#[proc_macro_derive(FromUdt, attributes(cdrs))]
+pub fn derive_from_udt(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+
+  from_udt::derive(&input)
+    .unwrap_or_else(syn::Error::into_compile_error)
+    .into()
+}
+
+/// Derives serialization of a struct as a user defined type, so it could
+/// be bound as a value or be a part of another value. A UDT is serialized
+/// by positions of its fields, thus fields of a struct must be declared
+/// in the same order as in a UDT. `#[cdrs(skip)]` leaves a field out,
+/// `None` fields are null and fields of tuple types are CQL tuples.
+///
+/// A struct should also implement `Clone` and `Debug` to be an element
+/// of a collection.
+#[proc_macro_derive(IntoUdt, attributes(cdrs))]
+pub fn derive_into_udt(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+
+  into_udt::derive(&input)
+    .unwrap_or_else(syn::Error::into_compile_error)
+    .into()
+}
+
+/// Returns fields of a struct or an error if a type is not a struct
+/// with named fields.
+fn named_fields<'a>(
+  input: &'a DeriveInput,
+  derive: &str,
+) -> syn::Result<&'a Punctuated<Field, Comma>> {
+  match &input.data {
+    Data::Struct(data) => match &data.fields {
+      Fields::Named(fields) => Ok(&fields.named),
+      _ => Err(syn::Error::new_spanned(
+        input,
+        format!(
+          "{} can be derived only for structs with named fields",
+          derive
+        ),
+      )),
+    },
+    _ => Err(syn::Error::new_spanned(
+      input,
+      format!("{} can be derived only for structs", derive),
+    )),
+  }
+}
+
+/// Checks that a type is `Option<T>`. A type could be checked
+/// only by its name since macros do not see resolved types.
+fn is_option(ty: &Type) -> bool {
+  match ty {
+    Type::Path(type_path) if type_path.qself.is_none() => type_path
+      .path
+      .segments
+      .last()
+      .map(|segment| segment.ident == "Option")
+      .unwrap_or(false),
+    _ => false,
+  }
+}
+
+/// Returns an expression which reads a field from a row or a UDT.
+fn read_field(
+  field: &Field,
+  container: &proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+  let options = FieldOptions::parse(field)?;
+  let ident = &field.ident;
+
+  if options.skip {
+    return Ok(quote! { #ident: ::std::default::Default::default() });
+  }
+
+  let column = options.column(field);
+  let value = if options.default {
+    quote! { ::cdrs_async::types::get_or_default(#container, #column)? }
+  } else {
+    quote! { ::cdrs_async::types::get_required(#container, #column)? }
+  };
+
+  Ok(quote! { #ident: #value })
+}
+
+/// Returns an expression which takes a field of `value` so it could
+/// be converted into `Value`. Tuples are wrapped to be serialized
+/// as CQL tuples.
+fn field_value(field: &Field) -> proc_macro2::TokenStream {
+  let ident = &field.ident;
+
+  match field.ty {
+    Type::Tuple(_) => quote! { ::cdrs_async::types::CqlTuple(value.#ident) },
+    _ => quote! { value.#ident },
+  }
+}
@@ -2,8 +2,6 @@ extern crate async_std;
 extern crate async_trait;
 extern crate cdrs_async;
 
-use std::pin::Pin;
-
 use async_std::task;
 use cdrs_async::{authenticators::NoneAuthenticator, query::QueryExecutor, Compression, Session};
 
@@ -18,16 +16,14 @@ const CREATE_KS_QUERY: &'static str = r#"
 fn main() {
   task::block_on(async {
     let authenticator_strategy = NoneAuthenticator {};
-    let mut session = Session::connect(
+    let session = Session::connect(
       "127.0.0.1:9042",
       Compression::None,
       authenticator_strategy.into(),
     )
     .await
     .expect("session connect");
-    let pinned_session = Pin::new(&mut session);
-
-    let r = pinned_session.query(CREATE_KS_QUERY).await;
+    let r = session.query(CREATE_KS_QUERY).await;
     println!("Result {:?}", r);
   });
 }
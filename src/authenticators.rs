@@ -1,57 +1,177 @@
-use cassandra_proto::types::CBytes;
+use std::{fmt, future::Future, sync::Arc};
 
-/// Generic CDRS authenticator structure.
+use async_trait::async_trait;
+
+use crate::error;
+
+/// SASL authenticator which performs a handshake of a single connection.
+/// A server may send any number of challenges before it accepts
+/// or rejects credentials, so multi-step mechanisms could be implemented
+/// by keeping a state of a handshake in an authenticator.
+#[async_trait]
+pub trait SaslAuthenticator: Send + Sync {
+  /// Returns a class of an authenticator a server should use, e.g.
+  /// `org.apache.cassandra.auth.PasswordAuthenticator`. `None` means that
+  /// a server is expected to have authentication disabled.
+  fn cassandra_name(&self) -> Option<&str>;
+
+  /// Returns a token which is sent to a server first.
+  async fn initial_response(&mut self) -> error::Result<Vec<u8>>;
+
+  /// Returns a response to a challenge a server sent.
+  async fn evaluate_challenge(&mut self, challenge: Option<Vec<u8>>) -> error::Result<Vec<u8>>;
+
+  /// Called when a server accepted authentication. A server may send
+  /// a final token, e.g. to prove its identity.
+  async fn on_success(&mut self, _token: Option<Vec<u8>>) -> error::Result<()> {
+    Ok(())
+  }
+}
+
+type SaslFactory = dyn Fn() -> Box<dyn SaslAuthenticator> + Send + Sync;
+
+/// Generic CDRS authenticator structure. It creates a new SASL authenticator
+/// for each connection, so handshakes of different connections do not share
+/// a state. Cloning an authenticator is cheap.
+#[derive(Clone)]
 pub struct Authenticator {
-  cassandra_name: Option<String>,
-  auth_token: CBytes,
+  factory: Arc<SaslFactory>,
 }
 
 impl Authenticator {
-  /// Returns authentication token wich will be used
-  /// by a DB server for session authorisation.
-  pub fn get_auth_token(&self) -> CBytes {
-    self.auth_token.clone()
+  /// Creates an authenticator which calls a given function each time
+  /// a connection is opened.
+  pub fn new<F, A>(factory: F) -> Self
+  where
+    F: Fn() -> A + Send + Sync + 'static,
+    A: SaslAuthenticator + 'static,
+  {
+    Authenticator {
+      factory: Arc::new(move || Box::new(factory())),
+    }
+  }
+
+  /// Returns a SASL authenticator for a new connection.
+  pub fn sasl(&self) -> Box<dyn SaslAuthenticator> {
+    (self.factory)()
+  }
+}
+
+impl fmt::Debug for Authenticator {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("Authenticator")
+      .field("cassandra_name", &self.sasl().cassandra_name())
+      .finish()
+  }
+}
+
+/// Each connection is authenticated by a clone of a given authenticator.
+impl<A: SaslAuthenticator + Clone + 'static> From<A> for Authenticator {
+  fn from(authenticator: A) -> Self {
+    Authenticator::new(move || authenticator.clone())
+  }
+}
+
+/// Username and password of a user.
+#[derive(Clone, PartialEq)]
+pub struct Credentials {
+  pub username: String,
+  pub password: String,
+}
+
+impl Credentials {
+  pub fn new<S: ToString>(username: S, password: S) -> Self {
+    Credentials {
+      username: username.to_string(),
+      password: password.to_string(),
+    }
+  }
+}
+
+impl fmt::Debug for Credentials {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("Credentials")
+      .field("username", &self.username)
+      .field("password", &"***")
+      .finish()
+  }
+}
+
+/// Source of credentials which is asked for them each time a connection
+/// is authenticated, so rotated passwords or tokens are picked up by new
+/// connections. It is implemented by async functions which return credentials.
+#[async_trait]
+pub trait CredentialsProvider: Send + Sync {
+  async fn credentials(&self) -> error::Result<Credentials>;
+}
+
+/// Credentials which never change.
+#[async_trait]
+impl CredentialsProvider for Credentials {
+  async fn credentials(&self) -> error::Result<Credentials> {
+    Ok(self.clone())
   }
+}
 
-  /// Returns authentication method name. CDRS will use it
-  /// to match with a method requested by a DB server.
-  pub fn get_cassandra_name(&self) -> Option<String> {
-    self.cassandra_name.clone()
+#[async_trait]
+impl<F, R> CredentialsProvider for F
+where
+  F: Fn() -> R + Send + Sync,
+  R: Future<Output = error::Result<Credentials>> + Send,
+{
+  async fn credentials(&self) -> error::Result<Credentials> {
+    self().await
   }
 }
 
 /// Password authenticator. CDRS will use it if a DB server requested
 /// `org.apache.cassandra.auth.PasswordAuthenticator` authentication.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct PasswordAuthenticator {
-  username: String,
-  password: String,
+  credentials: Arc<dyn CredentialsProvider>,
 }
 
 impl PasswordAuthenticator {
   pub fn new<S: ToString>(username: S, password: S) -> PasswordAuthenticator {
+    PasswordAuthenticator::with_provider(Credentials::new(username, password))
+  }
+
+  /// Creates an authenticator which asks a given provider for credentials
+  /// each time a connection is opened.
+  pub fn with_provider<P: CredentialsProvider + 'static>(provider: P) -> PasswordAuthenticator {
     PasswordAuthenticator {
-      username: username.to_string(),
-      password: password.to_string(),
+      credentials: Arc::new(provider),
     }
   }
 }
 
-impl Into<Authenticator> for PasswordAuthenticator {
-  fn into(self) -> Authenticator {
-    let auth_token = {
-      let mut v = vec![0];
-      v.extend_from_slice(self.username.as_bytes());
-      v.push(0);
-      v.extend_from_slice(self.password.as_bytes());
+impl fmt::Debug for PasswordAuthenticator {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("PasswordAuthenticator").finish()
+  }
+}
 
-      CBytes::new(v)
-    };
+#[async_trait]
+impl SaslAuthenticator for PasswordAuthenticator {
+  fn cassandra_name(&self) -> Option<&str> {
+    Some("org.apache.cassandra.auth.PasswordAuthenticator")
+  }
 
-    Authenticator {
-      cassandra_name: Some("org.apache.cassandra.auth.PasswordAuthenticator".into()),
-      auth_token,
-    }
+  async fn initial_response(&mut self) -> error::Result<Vec<u8>> {
+    let credentials = self.credentials.credentials().await?;
+
+    let mut token = vec![0];
+    token.extend_from_slice(credentials.username.as_bytes());
+    token.push(0);
+    token.extend_from_slice(credentials.password.as_bytes());
+
+    Ok(token)
+  }
+
+  async fn evaluate_challenge(&mut self, _challenge: Option<Vec<u8>>) -> error::Result<Vec<u8>> {
+    Err(error::Error::Auth(
+      "Password authentication does not expect challenges".to_string(),
+    ))
   }
 }
 
@@ -60,18 +180,30 @@ impl Into<Authenticator> for PasswordAuthenticator {
 #[derive(Debug, Clone)]
 pub struct NoneAuthenticator;
 
-impl Into<Authenticator> for NoneAuthenticator {
-  fn into(self) -> Authenticator {
-    Authenticator {
-      cassandra_name: None,
-      auth_token: CBytes::new(vec![0]),
-    }
+#[async_trait]
+impl SaslAuthenticator for NoneAuthenticator {
+  fn cassandra_name(&self) -> Option<&str> {
+    None
+  }
+
+  async fn initial_response(&mut self) -> error::Result<Vec<u8>> {
+    Err(error::Error::Auth(
+      "Authentication is not configured".to_string(),
+    ))
+  }
+
+  async fn evaluate_challenge(&mut self, _challenge: Option<Vec<u8>>) -> error::Result<Vec<u8>> {
+    Err(error::Error::Auth(
+      "Authentication is not configured".to_string(),
+    ))
   }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use futures::executor::block_on;
+  use std::sync::atomic::{AtomicUsize, Ordering};
 
   #[test]
   fn test_password_authenticator_trait_impl() {
@@ -88,27 +220,59 @@ mod tests {
   fn test_password_authenticator_get_cassandra_name() {
     let auth: Authenticator = PasswordAuthenticator::new("foo", "bar").into();
     assert_eq!(
-      auth.get_cassandra_name(),
-      Some("org.apache.cassandra.auth.PasswordAuthenticator".into())
+      auth.sasl().cassandra_name(),
+      Some("org.apache.cassandra.auth.PasswordAuthenticator")
     );
   }
 
   #[test]
-  fn test_password_authenticator_get_auth_token() {
+  fn test_password_authenticator_initial_response() {
     let auth: Authenticator = PasswordAuthenticator::new("foo", "bar").into();
     let mut expected_token = vec![0];
     expected_token.extend_from_slice("foo".as_bytes());
     expected_token.push(0);
     expected_token.extend_from_slice("bar".as_bytes());
 
-    assert_eq!(auth.get_auth_token().into_plain().unwrap(), expected_token);
+    let mut sasl = auth.sasl();
+    assert_eq!(block_on(sasl.initial_response()).unwrap(), expected_token);
+    assert!(block_on(sasl.evaluate_challenge(None)).is_err());
+  }
+
+  #[test]
+  fn test_password_authenticator_with_provider() {
+    let rotations = Arc::new(AtomicUsize::new(0));
+    let provider = {
+      let rotations = rotations.clone();
+      move || {
+        let rotation = rotations.fetch_add(1, Ordering::SeqCst);
+        async move { Ok(Credentials::new("foo".to_string(), rotation.to_string())) }
+      }
+    };
+    let auth: Authenticator = PasswordAuthenticator::with_provider(provider).into();
+
+    assert_eq!(
+      block_on(auth.sasl().initial_response()).unwrap(),
+      vec![0, b'f', b'o', b'o', 0, b'0']
+    );
+    assert_eq!(
+      block_on(auth.sasl().initial_response()).unwrap(),
+      vec![0, b'f', b'o', b'o', 0, b'1']
+    );
+    assert_eq!(rotations.load(Ordering::SeqCst), 2);
+  }
+
+  #[test]
+  fn test_password_authenticator_provider_error() {
+    let provider = || async { Err("vault is unavailable".into()) };
+    let mut sasl = PasswordAuthenticator::with_provider(provider);
+
+    assert!(block_on(sasl.initial_response()).is_err());
   }
 
   #[test]
   fn test_authenticator_none_get_cassandra_name() {
     let auth: Authenticator = (NoneAuthenticator {}).into();
-    assert_eq!(auth.get_cassandra_name(), None);
-    assert_eq!(auth.get_auth_token().into_plain().unwrap(), vec![0]);
+    assert_eq!(auth.sasl().cassandra_name(), None);
   }
 
   fn authenticator_tester(_authenticator: Box<Authenticator>) {}
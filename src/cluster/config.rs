@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use super::topology::{AddressTranslator, IdentityTranslator};
+use crate::{
+  load_balancing::{LoadBalancingPolicy, RoundRobin},
+  retry::{DefaultRetryPolicy, RetryPolicy},
+};
+
+const DEFAULT_CORE_CONNECTIONS: usize = 1;
+const DEFAULT_MAX_CONNECTIONS: usize = 8;
+const DEFAULT_MAX_REQUESTS_PER_CONNECTION: usize = 1_024;
+
+/// Configuration of a connection pool which is kept for each cluster node.
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+  core_connections: usize,
+  max_connections: usize,
+  max_requests_per_connection: usize,
+}
+
+impl Default for PoolConfig {
+  fn default() -> Self {
+    PoolConfig {
+      core_connections: DEFAULT_CORE_CONNECTIONS,
+      max_connections: DEFAULT_MAX_CONNECTIONS,
+      max_requests_per_connection: DEFAULT_MAX_REQUESTS_PER_CONNECTION,
+    }
+  }
+}
+
+impl PoolConfig {
+  /// Creates a pool configuration with default values:
+  /// * 1 core connection,
+  /// * 8 connections at most,
+  /// * 1024 in-flight requests per connection before a new one is opened.
+  pub fn new() -> Self {
+    Default::default()
+  }
+
+  /// Sets a number of connections which are opened upfront and kept open.
+  pub fn core_connections(mut self, core_connections: usize) -> Self {
+    self.core_connections = core_connections.max(1);
+    self.max_connections = self.max_connections.max(self.core_connections);
+
+    self
+  }
+
+  /// Sets a maximal number of connections per node.
+  pub fn max_connections(mut self, max_connections: usize) -> Self {
+    self.max_connections = max_connections.max(1);
+    self.core_connections = self.core_connections.min(self.max_connections);
+
+    self
+  }
+
+  /// Sets a number of in-flight requests after which a connection
+  /// is considered busy and a pool tries to open a new one.
+  pub fn max_requests_per_connection(mut self, max_requests: usize) -> Self {
+    self.max_requests_per_connection = max_requests.max(1);
+
+    self
+  }
+
+  pub fn get_core_connections(&self) -> usize {
+    self.core_connections
+  }
+
+  pub fn get_max_connections(&self) -> usize {
+    self.max_connections
+  }
+
+  pub fn get_max_requests_per_connection(&self) -> usize {
+    self.max_requests_per_connection
+  }
+}
+
+/// Cluster configuration which contains addresses of nodes
+/// and describes how connections to them should be established.
+pub struct ClusterConfig<M, LB = RoundRobin> {
+  pub(super) contact_points: Vec<String>,
+  pub(super) connection_manager: M,
+  pub(super) pool: PoolConfig,
+  pub(super) load_balancing: LB,
+  pub(super) peer_discovery: bool,
+  pub(super) address_translator: Arc<dyn AddressTranslator>,
+  pub(super) retry_policy: Arc<dyn RetryPolicy>,
+}
+
+impl<M> ClusterConfig<M> {
+  /// Creates a new cluster configuration with default pool settings,
+  /// round-robin load balancing, default retry policy and enabled
+  /// peer discovery.
+  pub fn new<A: ToString>(contact_points: Vec<A>, connection_manager: M) -> Self {
+    ClusterConfig {
+      contact_points: contact_points.iter().map(ToString::to_string).collect(),
+      connection_manager,
+      pool: PoolConfig::default(),
+      load_balancing: RoundRobin::new(),
+      peer_discovery: true,
+      address_translator: Arc::new(IdentityTranslator),
+      retry_policy: Arc::new(DefaultRetryPolicy::new()),
+    }
+  }
+}
+
+impl<M, LB: LoadBalancingPolicy> ClusterConfig<M, LB> {
+  /// Sets a configuration of per node connection pools.
+  pub fn pool(mut self, pool: PoolConfig) -> Self {
+    self.pool = pool;
+
+    self
+  }
+
+  /// Sets a policy which decides what nodes serve requests.
+  pub fn load_balancing<L: LoadBalancingPolicy>(self, load_balancing: L) -> ClusterConfig<M, L> {
+    ClusterConfig {
+      contact_points: self.contact_points,
+      connection_manager: self.connection_manager,
+      pool: self.pool,
+      load_balancing,
+      peer_discovery: self.peer_discovery,
+      address_translator: self.address_translator,
+      retry_policy: self.retry_policy,
+    }
+  }
+
+  /// Enables or disables connecting to nodes which are found
+  /// in `system.peers` of contact points.
+  pub fn peer_discovery(mut self, peer_discovery: bool) -> Self {
+    self.peer_discovery = peer_discovery;
+
+    self
+  }
+
+  /// Sets a policy which decides whether failed requests are retried.
+  pub fn retry_policy<R: RetryPolicy>(mut self, retry_policy: R) -> Self {
+    self.retry_policy = Arc::new(retry_policy);
+
+    self
+  }
+
+  /// Sets a translator of addresses which discovered peers advertise.
+  pub fn address_translator<T: AddressTranslator>(mut self, address_translator: T) -> Self {
+    self.address_translator = Arc::new(address_translator);
+
+    self
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_pool_config_default() {
+    let config = PoolConfig::new();
+    assert_eq!(config.get_core_connections(), DEFAULT_CORE_CONNECTIONS);
+    assert_eq!(config.get_max_connections(), DEFAULT_MAX_CONNECTIONS);
+    assert_eq!(
+      config.get_max_requests_per_connection(),
+      DEFAULT_MAX_REQUESTS_PER_CONNECTION
+    );
+  }
+
+  #[test]
+  fn test_pool_config_core_raises_max() {
+    let config = PoolConfig::new().max_connections(2).core_connections(4);
+    assert_eq!(config.get_core_connections(), 4);
+    assert_eq!(config.get_max_connections(), 4);
+  }
+
+  #[test]
+  fn test_pool_config_max_lowers_core() {
+    let config = PoolConfig::new().core_connections(4).max_connections(2);
+    assert_eq!(config.get_core_connections(), 2);
+    assert_eq!(config.get_max_connections(), 2);
+  }
+
+  #[test]
+  fn test_pool_config_at_least_one_connection() {
+    let config = PoolConfig::new().core_connections(0).max_connections(0);
+    assert_eq!(config.get_core_connections(), 1);
+    assert_eq!(config.get_max_connections(), 1);
+  }
+}
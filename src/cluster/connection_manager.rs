@@ -0,0 +1,104 @@
+use async_tls::TlsConnector;
+use async_trait::async_trait;
+
+use crate::{
+  authenticators::Authenticator, compressor::Compression, error, session::Session,
+  session_config::SessionConfig, transport::CDRSTransport, TransportTcp, TransportTls,
+};
+
+/// Connection manager opens new sessions to cluster nodes. It is used by
+/// a cluster session to fill connection pools and to replace dead connections.
+#[async_trait]
+pub trait ConnectionManager: Send + Sync + 'static {
+  /// Transport which is used by sessions the manager opens.
+  type Transport: CDRSTransport + 'static;
+
+  /// Opens a new session to a node with a given address.
+  async fn connect(&self, addr: &str) -> error::Result<Session<Self::Transport>>;
+}
+
+/// Connection manager which opens TCP sessions.
+#[derive(Debug, Clone)]
+pub struct TcpConnectionManager {
+  compression: Compression,
+  authenticator: Authenticator,
+  config: SessionConfig,
+}
+
+impl TcpConnectionManager {
+  pub fn new(compression: Compression, authenticator: Authenticator) -> Self {
+    TcpConnectionManager {
+      compression,
+      authenticator,
+      config: SessionConfig::default(),
+    }
+  }
+
+  /// Sets a configuration of sessions the manager opens.
+  pub fn session_config(mut self, config: SessionConfig) -> Self {
+    self.config = config;
+
+    self
+  }
+}
+
+#[async_trait]
+impl ConnectionManager for TcpConnectionManager {
+  type Transport = TransportTcp;
+
+  async fn connect(&self, addr: &str) -> error::Result<Session<TransportTcp>> {
+    Session::connect_with_config(
+      addr,
+      self.compression,
+      self.authenticator.clone(),
+      self.config.clone(),
+    )
+    .await
+  }
+}
+
+/// Connection manager which opens TLS sessions.
+#[derive(Clone)]
+pub struct TlsConnectionManager {
+  connector: TlsConnector,
+  compression: Compression,
+  authenticator: Authenticator,
+  config: SessionConfig,
+}
+
+impl TlsConnectionManager {
+  pub fn new(
+    connector: TlsConnector,
+    compression: Compression,
+    authenticator: Authenticator,
+  ) -> Self {
+    TlsConnectionManager {
+      connector,
+      compression,
+      authenticator,
+      config: SessionConfig::default(),
+    }
+  }
+
+  /// Sets a configuration of sessions the manager opens.
+  pub fn session_config(mut self, config: SessionConfig) -> Self {
+    self.config = config;
+
+    self
+  }
+}
+
+#[async_trait]
+impl ConnectionManager for TlsConnectionManager {
+  type Transport = TransportTls;
+
+  async fn connect(&self, addr: &str) -> error::Result<Session<TransportTls>> {
+    Session::connect_tls_with_config(
+      (addr, self.connector.clone()),
+      self.compression,
+      self.authenticator.clone(),
+      self.config.clone(),
+    )
+    .await
+  }
+}
@@ -0,0 +1,19 @@
+//! Cluster module contains a session which is connected to several nodes
+//! of a cluster and keeps a pool of connections for each of them.
+
+mod config;
+mod connection_manager;
+mod node;
+mod options;
+mod pool;
+mod session;
+mod token_map;
+mod topology;
+
+pub use config::{ClusterConfig, PoolConfig};
+pub use connection_manager::{ConnectionManager, TcpConnectionManager, TlsConnectionManager};
+pub use node::Node;
+pub use options::RequestOptions;
+pub use session::{ClusterSession, SessionView};
+pub use token_map::{ReplicationStrategy, TokenMap};
+pub use topology::{AddressTranslator, IdentityTranslator};
@@ -0,0 +1,154 @@
+use std::{
+  net::{IpAddr, SocketAddr},
+  sync::atomic::{AtomicBool, Ordering},
+};
+
+use cassandra_proto::types::{list::List, rows::Row, AsRustType, IntoRustByName};
+use log::warn;
+use uuid::Uuid;
+
+use crate::{
+  error,
+  token::{PartitionToken, Partitioner},
+};
+
+/// Cluster node as it is seen by load balancing policies.
+#[derive(Debug)]
+pub struct Node {
+  addr: String,
+  host_id: Option<Uuid>,
+  datacenter: Option<String>,
+  rack: Option<String>,
+  tokens: Vec<PartitionToken>,
+  release_version: Option<String>,
+  rpc_address: Option<SocketAddr>,
+  is_up: AtomicBool,
+}
+
+impl Node {
+  pub fn new<A: ToString, T: Into<PartitionToken>>(
+    addr: A,
+    datacenter: Option<String>,
+    rack: Option<String>,
+    tokens: Vec<T>,
+  ) -> Self {
+    Node {
+      addr: addr.to_string(),
+      host_id: None,
+      datacenter,
+      rack,
+      tokens: tokens.into_iter().map(Into::into).collect(),
+      release_version: None,
+      rpc_address: None,
+      is_up: AtomicBool::new(true),
+    }
+  }
+
+  /// Creates a node which location in a cluster is not known.
+  pub fn unknown<A: ToString>(addr: A) -> Self {
+    Node::new(addr, None, None, Vec::<PartitionToken>::new())
+  }
+
+  /// Creates a node from a row of `system.local`, `system.peers`
+  /// or `system.peers_v2` table. Tokens are parsed as a partitioner
+  /// of a cluster stores them, ones which cannot be parsed are skipped.
+  /// A node owns no tokens if a partitioner is not known.
+  pub(crate) fn from_row<A: ToString>(
+    addr: A,
+    rpc_address: Option<SocketAddr>,
+    row: &Row,
+    partitioner: Option<Partitioner>,
+  ) -> error::Result<Self> {
+    let addr = addr.to_string();
+    let tokens: Option<List> = row.get_by_name("tokens")?;
+    let tokens: Vec<String> = match tokens {
+      Some(tokens) => tokens.as_rust_type()?.unwrap_or_default(),
+      None => vec![],
+    };
+    let parse = |token: &String| {
+      let partitioner = partitioner?;
+      partitioner
+        .parse_token(token)
+        .map_err(|err| {
+          warn!(
+            "CDRS cluster: node {} owns a token which is skipped: {:?}",
+            addr, err
+          )
+        })
+        .ok()
+    };
+
+    Ok(Node {
+      tokens: tokens.iter().filter_map(parse).collect(),
+      addr,
+      host_id: row.get_by_name("host_id")?,
+      datacenter: row.get_by_name("data_center")?,
+      rack: row.get_by_name("rack")?,
+      release_version: row.get_by_name("release_version")?,
+      rpc_address,
+      is_up: AtomicBool::new(true),
+    })
+  }
+
+  /// Address which is used to connect to the node.
+  pub fn addr(&self) -> &str {
+    &self.addr
+  }
+
+  /// Unique identifier of the node.
+  pub fn host_id(&self) -> Option<Uuid> {
+    self.host_id
+  }
+
+  /// Data center the node belongs to.
+  pub fn datacenter(&self) -> Option<&str> {
+    self.datacenter.as_deref()
+  }
+
+  /// Rack the node belongs to.
+  pub fn rack(&self) -> Option<&str> {
+    self.rack.as_deref()
+  }
+
+  /// Tokens which are owned by the node.
+  pub fn tokens(&self) -> &[PartitionToken] {
+    &self.tokens
+  }
+
+  /// Cassandra version the node runs.
+  pub fn release_version(&self) -> Option<&str> {
+    self.release_version.as_deref()
+  }
+
+  /// Address of a native protocol server as the node advertises it,
+  /// i.e. before it is translated by `AddressTranslator`.
+  pub fn rpc_address(&self) -> Option<SocketAddr> {
+    self.rpc_address
+  }
+
+  /// Checks that the node was not reported down by a cluster.
+  pub fn is_up(&self) -> bool {
+    self.is_up.load(Ordering::Relaxed)
+  }
+
+  pub(crate) fn set_up(&self, is_up: bool) {
+    self.is_up.store(is_up, Ordering::Relaxed);
+  }
+
+  /// Checks that an address which a server event refers to is the node's one.
+  pub(crate) fn has_address(&self, addr: SocketAddr) -> bool {
+    self.rpc_address == Some(addr) || self.addr.parse() == Ok(addr)
+  }
+}
+
+/// Returns an address peer clients should connect to. Nodes which listen
+/// on all interfaces advertise `0.0.0.0`, so a peer address is used then.
+pub(crate) fn rpc_address(row: &Row, rpc_column: &str, port: u16) -> error::Result<SocketAddr> {
+  let rpc: Option<IpAddr> = row.get_by_name(rpc_column)?;
+  let ip = match rpc {
+    Some(ip) if !ip.is_unspecified() => ip,
+    _ => row.get_r_by_name("peer")?,
+  };
+
+  Ok(SocketAddr::new(ip, port))
+}
@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use crate::load_balancing::RoutingInfo;
+
+/// Options of requests which are sent via a session view.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestOptions {
+  routing: RoutingInfo,
+  is_idempotent: bool,
+  timeout: Option<Duration>,
+}
+
+impl RequestOptions {
+  /// Creates options of non-idempotent requests without routing information.
+  pub fn new() -> Self {
+    Default::default()
+  }
+
+  /// Sets a routing information which is passed to a load balancing policy.
+  pub fn routing(mut self, routing: RoutingInfo) -> Self {
+    self.routing = routing;
+
+    self
+  }
+
+  /// Marks requests as idempotent ones, i.e. ones which could be applied
+  /// several times with the same effect, so they are safe to retry.
+  pub fn idempotent(mut self, is_idempotent: bool) -> Self {
+    self.is_idempotent = is_idempotent;
+
+    self
+  }
+
+  /// Sets a time to wait for a response to each attempt of a request
+  /// instead of a request timeout of a session configuration.
+  pub fn timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = Some(timeout);
+
+    self
+  }
+
+  pub fn get_routing(&self) -> &RoutingInfo {
+    &self.routing
+  }
+
+  pub fn is_idempotent(&self) -> bool {
+    self.is_idempotent
+  }
+
+  pub fn get_timeout(&self) -> Option<Duration> {
+    self.timeout
+  }
+}
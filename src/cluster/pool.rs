@@ -0,0 +1,143 @@
+use std::sync::{
+  atomic::{AtomicUsize, Ordering},
+  Arc, Mutex, MutexGuard,
+};
+
+use log::warn;
+
+use super::{config::PoolConfig, connection_manager::ConnectionManager};
+use crate::{
+  error, prepared_cache::PreparedCache, retry::FallthroughRetryPolicy, session::Session,
+};
+
+/// Pool of connections to a single cluster node.
+pub(crate) struct NodePool<M: ConnectionManager> {
+  addr: String,
+  config: PoolConfig,
+  connection_manager: Arc<M>,
+  sessions: Mutex<Vec<Session<M::Transport>>>,
+  opening: AtomicUsize,
+  prepared_cache: Arc<PreparedCache>,
+}
+
+impl<M: ConnectionManager> NodePool<M> {
+  /// Creates a pool which sessions share a given cache of prepared queries.
+  pub fn new(
+    addr: String,
+    config: PoolConfig,
+    connection_manager: Arc<M>,
+    prepared_cache: Arc<PreparedCache>,
+  ) -> Self {
+    NodePool {
+      addr,
+      config,
+      connection_manager,
+      sessions: Mutex::new(vec![]),
+      opening: AtomicUsize::new(0),
+      prepared_cache,
+    }
+  }
+
+  /// Address of a node the pool is connected to.
+  pub fn addr(&self) -> &str {
+    &self.addr
+  }
+
+  /// Opens core connections. It fails only if none of them could be opened.
+  pub async fn init(&self) -> error::Result<()> {
+    let mut last_error = None;
+    for _ in 0..self.config.get_core_connections() {
+      if let Err(err) = self.open().await {
+        warn!("CDRS pool: cannot connect to {}: {:?}", self.addr, err);
+        last_error = Some(err);
+      }
+    }
+
+    match last_error {
+      Some(err) if self.size() == 0 => Err(err),
+      _ => Ok(()),
+    }
+  }
+
+  /// Returns a number of alive connections.
+  pub fn size(&self) -> usize {
+    let mut sessions = self.sessions();
+    sessions.retain(Session::is_alive);
+    sessions.len()
+  }
+
+  /// Returns a session which should serve a next request. Dead connections
+  /// are removed and replaced, and if all connections are busy a new one
+  /// is opened unless the pool has reached its maximal size.
+  pub async fn acquire(&self) -> error::Result<Session<M::Transport>> {
+    let (least_busy, should_open) = {
+      let mut sessions = self.sessions();
+      sessions.retain(Session::is_alive);
+
+      let least_busy = sessions
+        .iter()
+        .min_by_key(|session| session.in_flight())
+        .cloned();
+      let is_busy = least_busy
+        .as_ref()
+        .map(|session| session.in_flight() >= self.config.get_max_requests_per_connection())
+        .unwrap_or(true);
+
+      let size = sessions.len() + self.opening.load(Ordering::SeqCst);
+      let should_open = size < self.config.get_core_connections()
+        || (is_busy && size < self.config.get_max_connections());
+
+      (least_busy, should_open)
+    };
+
+    match least_busy {
+      Some(session) if !should_open => Ok(session),
+      Some(session) => self.open().await.or_else(|err| {
+        warn!("CDRS pool: cannot connect to {}: {:?}", self.addr, err);
+        Ok(session)
+      }),
+      None => self.open().await,
+    }
+  }
+
+  async fn open(&self) -> error::Result<Session<M::Transport>> {
+    let session = {
+      let _opening = OpeningGuard::new(&self.opening);
+      self.connection_manager.connect(&self.addr).await?
+    };
+    // a query which a node does not know is prepared again from the cache
+    // a cluster session retries requests itself, possibly on other nodes,
+    // and waits for schema agreement of nodes which are not down
+    let session = session
+      .with_prepared_cache(self.prepared_cache.clone())
+      .with_retry_policy(Arc::new(FallthroughRetryPolicy::new()))
+      .without_schema_agreement_wait();
+    self.sessions().push(session.clone());
+
+    Ok(session)
+  }
+
+  fn sessions(&self) -> MutexGuard<'_, Vec<Session<M::Transport>>> {
+    self
+      .sessions
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+}
+
+/// Counts connections which are being opened, the counter is decremented
+/// even if opening future is dropped before completion.
+struct OpeningGuard<'a>(&'a AtomicUsize);
+
+impl<'a> OpeningGuard<'a> {
+  fn new(opening: &'a AtomicUsize) -> Self {
+    opening.fetch_add(1, Ordering::SeqCst);
+    OpeningGuard(opening)
+  }
+}
+
+impl<'a> Drop for OpeningGuard<'a> {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::SeqCst);
+  }
+}
@@ -0,0 +1,921 @@
+use std::{
+  collections::HashMap,
+  future::Future,
+  net::SocketAddr,
+  sync::{Arc, RwLock, Weak},
+  time::Duration,
+};
+
+use async_std::task;
+use async_trait::async_trait;
+use cassandra_proto::{
+  consistency::Consistency,
+  frame::Frame,
+  query::{QueryBatch, QueryParams},
+};
+use futures::{channel::mpsc, future::join_all, stream::StreamExt};
+use log::{debug, info, warn};
+use uuid::Uuid;
+
+use super::{
+  config::{ClusterConfig, PoolConfig},
+  connection_manager::ConnectionManager,
+  node::Node,
+  options::RequestOptions,
+  pool::NodePool,
+  token_map::{ReplicationStrategy, TokenMap},
+  topology::{fetch_local, fetch_peers, fetch_replication, port_of, AddressTranslator},
+};
+use crate::{
+  error,
+  events::{
+    EventStream, EventType, ServerEvent, StatusChangeType, TopologyChange, TopologyChangeType,
+  },
+  load_balancing::{LoadBalancingPolicy, RoundRobin, RoutingInfo},
+  prepared_cache::PreparedCache,
+  query::{
+    BatchExecutor, ExecExecutor, PrepareExecutor, PreparedQuery, PreparedStatement, QueryExecutor,
+    QueryOptions,
+  },
+  retry::{self, RequestInfo, RetryDecision, RetryPolicy},
+  schema_agreement::{is_agreement, is_schema_change, wait_for_agreement},
+  session::Session,
+  utils::clone_query_params,
+};
+
+/// Time to wait before registering for events again if no node is reachable.
+const REGISTER_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Session which is connected to several cluster nodes. It keeps a pool
+/// of connections for each node and dispatches requests across them
+/// as a load balancing policy decides.
+///
+/// Cloning a cluster session is cheap, all clones share the same pools.
+pub struct ClusterSession<M: ConnectionManager, LB: LoadBalancingPolicy = RoundRobin> {
+  shared: Arc<Shared<M>>,
+  load_balancing: Arc<LB>,
+  retry_policy: Arc<dyn RetryPolicy>,
+}
+
+impl<M: ConnectionManager, LB: LoadBalancingPolicy> Clone for ClusterSession<M, LB> {
+  fn clone(&self) -> Self {
+    ClusterSession {
+      shared: self.shared.clone(),
+      load_balancing: self.load_balancing.clone(),
+      retry_policy: self.retry_policy.clone(),
+    }
+  }
+}
+
+impl<M: ConnectionManager, LB: LoadBalancingPolicy> ClusterSession<M, LB> {
+  /// Creates a new cluster session and opens core connections to each
+  /// of contact points. It fails only if none of the nodes is reachable.
+  /// If peer discovery is enabled, nodes which contact points know about
+  /// are connected as well.
+  pub async fn connect(config: ClusterConfig<M, LB>) -> error::Result<Self> {
+    let ClusterConfig {
+      contact_points,
+      connection_manager,
+      pool: pool_config,
+      load_balancing,
+      peer_discovery,
+      address_translator,
+      retry_policy,
+    } = config;
+    let shared = Shared {
+      topology: RwLock::default(),
+      connection_manager: Arc::new(connection_manager),
+      pool_config,
+      address_translator,
+      prepared_cache: Arc::new(PreparedCache::new()),
+    };
+
+    let mut pools: Vec<NodePool<M>> = contact_points
+      .into_iter()
+      .map(|addr| shared.new_pool(addr))
+      .collect();
+
+    let results = join_all(pools.iter().map(init_node)).await;
+    if !results.iter().any(Result::is_ok) {
+      return Err(
+        results
+          .into_iter()
+          .filter_map(Result::err)
+          .last()
+          .unwrap_or_else(|| "No contact points were provided".into()),
+      );
+    }
+
+    let mut nodes: Vec<Node> = results
+      .into_iter()
+      .zip(pools.iter())
+      .map(|(result, pool)| result.unwrap_or_else(|_| Node::unknown(pool.addr())))
+      .collect();
+
+    if peer_discovery {
+      let mut peer_pools = vec![];
+      for peer in Self::discover_peers(&pools, shared.address_translator.as_ref()).await {
+        if let Some(node) = nodes.iter_mut().find(|node| node.addr() == peer.addr()) {
+          if node.host_id().is_none() {
+            *node = peer;
+          }
+          continue;
+        }
+        if peer.host_id().is_some() && nodes.iter().any(|node| node.host_id() == peer.host_id()) {
+          continue;
+        }
+
+        peer_pools.push(shared.new_pool(peer.addr().to_string()));
+        nodes.push(peer);
+      }
+
+      // unreachable peers are kept, their pools try to connect on demand
+      join_all(peer_pools.iter().map(NodePool::init)).await;
+      pools.extend(peer_pools);
+    }
+
+    shared.set_topology(Topology::new(
+      nodes.into_iter().map(Arc::new).collect(),
+      pools
+        .into_iter()
+        .map(|pool| (pool.addr().to_string(), Arc::new(pool)))
+        .collect(),
+    ));
+    let session = ClusterSession {
+      shared: Arc::new(shared),
+      load_balancing: Arc::new(load_balancing),
+      retry_policy,
+    };
+
+    match session.register(&EventType::ALL).await {
+      Ok(events) => {
+        task::spawn(handle_events(Arc::downgrade(&session.shared), events));
+      }
+      Err(err) => warn!("CDRS cluster: cannot register for events: {:?}", err),
+    }
+
+    Ok(session)
+  }
+
+  /// Reads peers from a first reachable contact point.
+  async fn discover_peers(pools: &[NodePool<M>], translator: &dyn AddressTranslator) -> Vec<Node> {
+    for pool in pools.iter().filter(|pool| pool.size() > 0) {
+      let default_port = port_of(pool.addr());
+      let peers = match pool.acquire().await {
+        Ok(session) => fetch_peers(&session, default_port, translator).await,
+        Err(err) => Err(err),
+      };
+
+      match peers {
+        Ok(peers) => return peers,
+        Err(err) => warn!(
+          "CDRS cluster: cannot read peers of {}: {:?}",
+          pool.addr(),
+          err
+        ),
+      }
+    }
+
+    vec![]
+  }
+
+  /// Returns a number of alive connections to a node with a given address.
+  pub fn pool_size(&self, addr: &str) -> Option<usize> {
+    self
+      .shared
+      .topology()
+      .pools
+      .get(addr)
+      .map(|pool| pool.size())
+  }
+
+  /// Returns nodes which the session knows about. Nodes which join
+  /// or leave a cluster later are added or removed when a server reports them.
+  pub fn nodes(&self) -> Vec<Arc<Node>> {
+    self.shared.topology().nodes.clone()
+  }
+
+  /// Returns a ring of tokens which nodes the session knows about own.
+  /// It is rebuilt only when a topology of a cluster changes.
+  pub fn token_map(&self) -> Arc<TokenMap> {
+    self.shared.topology().token_map.clone()
+  }
+
+  /// Reads replication settings of a keyspace, so replicas of its
+  /// partitions could be found by `TokenMap::replicas`.
+  pub async fn replication_strategy(&self, keyspace: &str) -> error::Result<ReplicationStrategy> {
+    let session = self.acquire(&RoutingInfo::default()).await?;
+
+    fetch_replication(&session, keyspace).await
+  }
+
+  /// Checks that all nodes which are not reported down use the same
+  /// schema version.
+  pub async fn check_schema_agreement(&self) -> error::Result<bool> {
+    let versions = self
+      .acquire(&RoutingInfo::default())
+      .await?
+      .schema_versions()
+      .await?;
+
+    Ok(is_agreement(&versions, |host_id| self.is_live(host_id)))
+  }
+
+  /// Waits until nodes which are not reported down agree on a schema version,
+  /// but no longer than a maximum time sessions of nodes are configured with.
+  /// Returns whether agreement was reached.
+  pub async fn await_schema_agreement(&self) -> error::Result<bool> {
+    let max_wait = self
+      .acquire(&RoutingInfo::default())
+      .await?
+      .max_schema_agreement_wait();
+
+    wait_for_agreement(max_wait, || self.check_schema_agreement()).await
+  }
+
+  /// Waits until nodes which are not reported down agree on a schema version
+  /// after a schema change a node made. A schema change itself succeeded,
+  /// so it does not fail if agreement is not reached.
+  async fn wait_for_schema_change(&self, session: &Session<M::Transport>) {
+    let max_wait = session.max_schema_agreement_wait();
+    if max_wait == Duration::from_secs(0) {
+      return;
+    }
+
+    let agreement = wait_for_agreement(max_wait, || async move {
+      let versions = session.schema_versions().await?;
+      Ok(is_agreement(&versions, |host_id| self.is_live(host_id)))
+    })
+    .await;
+    match agreement {
+      Ok(true) => {}
+      Ok(false) => warn!(
+        "CDRS cluster: schema agreement was not reached within {:?}",
+        max_wait
+      ),
+      Err(err) => warn!("CDRS cluster: cannot check schema agreement: {:?}", err),
+    }
+  }
+
+  /// Checks that a node with a given host id was not reported down.
+  /// Nodes the session does not know about are considered live.
+  fn is_live(&self, host_id: Option<Uuid>) -> bool {
+    !self
+      .shared
+      .topology()
+      .nodes
+      .iter()
+      .any(|node| host_id.is_some() && node.host_id() == host_id && !node.is_up())
+  }
+
+  /// Registers for server events of given types via a connection to one
+  /// of nodes. When that connection is closed, events are registered for
+  /// via another node, so the stream ends only when the session is dropped.
+  /// Events which were sent while registering again are missed.
+  pub async fn register(&self, event_types: &[EventType]) -> error::Result<EventStream> {
+    let events = self.register_node(event_types).await?;
+    let (sender, receiver) = mpsc::unbounded();
+    task::spawn(forward_events(
+      Arc::downgrade(&self.shared),
+      self.load_balancing.clone(),
+      self.retry_policy.clone(),
+      event_types.to_vec(),
+      events,
+      sender,
+    ));
+
+    Ok(EventStream::new(event_types.to_vec(), receiver))
+  }
+
+  /// Registers for server events via a connection to one of nodes.
+  /// The stream ends when that connection is closed.
+  async fn register_node(&self, event_types: &[EventType]) -> error::Result<EventStream> {
+    self
+      .acquire(&RoutingInfo::default())
+      .await?
+      .register_connection(event_types)
+      .await
+  }
+
+  /// Returns a view of the session which passes a given routing information
+  /// to a load balancing policy for each request, so e.g. token aware
+  /// policy can send requests directly to a node that owns a partition.
+  pub fn with_routing(&self, routing: RoutingInfo) -> SessionView<M, LB> {
+    self.with_options(RequestOptions::new().routing(routing))
+  }
+
+  /// Returns a view of the session which sends all requests with given options.
+  pub fn with_options(&self, options: RequestOptions) -> SessionView<M, LB> {
+    SessionView {
+      session: self.clone(),
+      options,
+    }
+  }
+
+  /// Returns nodes in the order they should be tried to serve a request.
+  /// Nodes which are reported down are tried only after all other ones.
+  fn query_plan(&self, routing: &RoutingInfo) -> Vec<Arc<Node>> {
+    let topology = self.shared.topology();
+    let mut plan = self
+      .load_balancing
+      .query_plan(routing, &topology.nodes, &topology.token_map);
+    plan.sort_by_key(|node| !node.is_up());
+    plan
+  }
+
+  /// Returns a session of a first node in a query plan which is reachable.
+  async fn acquire(&self, routing: &RoutingInfo) -> error::Result<Session<M::Transport>> {
+    let mut last_error = None;
+
+    for node in self.query_plan(routing) {
+      match self.acquire_node(&node).await {
+        Ok(session) => return Ok(session),
+        Err(err) => last_error = Some(err),
+      }
+    }
+
+    Err(last_error.unwrap_or_else(|| "No nodes are available".into()))
+  }
+
+  async fn acquire_node(&self, node: &Node) -> error::Result<Session<M::Transport>> {
+    let pool = self.shared.topology().pools.get(node.addr()).cloned();
+    match pool {
+      Some(pool) => pool.acquire().await,
+      None => Err(format!("No connection pool for {}", node.addr()).into()),
+    }
+  }
+
+  /// Sends a request to nodes of a query plan until one of them responds.
+  /// A retry policy decides whether a request which failed should be sent
+  /// again and with what consistency. Nodes which cannot be connected are
+  /// skipped without consulting the policy, since requests were not sent.
+  async fn send<F, R>(
+    &self,
+    options: &RequestOptions,
+    consistency: Consistency,
+    request: F,
+  ) -> error::Result<Frame>
+  where
+    F: Fn(Session<M::Transport>, Consistency) -> R + Send + Sync,
+    R: Future<Output = error::Result<Frame>> + Send,
+  {
+    let mut plan = self.query_plan(options.get_routing()).into_iter();
+    let mut node = plan.next();
+    let mut consistency = consistency;
+    let mut retry_count = 0;
+    let mut last_error = None;
+
+    while let Some(current) = node.take() {
+      let session = match self.acquire_node(&current).await {
+        Ok(session) => session,
+        Err(err) => {
+          last_error = Some(err);
+          node = plan.next();
+          continue;
+        }
+      };
+
+      let session = match options.get_timeout() {
+        Some(timeout) => session.with_request_timeout(Some(timeout)),
+        None => session,
+      };
+
+      let err = match request(session.clone(), consistency).await {
+        Ok(frame) => {
+          if is_schema_change(&frame) {
+            self.wait_for_schema_change(&session).await;
+          }
+          return Ok(frame);
+        }
+        Err(err) => err,
+      };
+
+      let info = RequestInfo {
+        consistency,
+        is_idempotent: options.is_idempotent(),
+        retry_count,
+      };
+      match retry::decide(self.retry_policy.as_ref(), &info, &err) {
+        RetryDecision::RetrySameNode(retry_consistency) => {
+          consistency = retry_consistency;
+          node = Some(current);
+        }
+        RetryDecision::RetryNextNode(retry_consistency) => {
+          consistency = retry_consistency;
+          node = plan.next();
+        }
+        RetryDecision::Rethrow => return Err(err),
+      }
+
+      debug!(
+        "CDRS cluster: retrying a request failed with {:?}, attempt {}",
+        err,
+        retry_count + 1
+      );
+      retry_count += 1;
+      last_error = Some(err);
+    }
+
+    Err(last_error.unwrap_or_else(|| "No nodes are available".into()))
+  }
+
+  async fn send_query(
+    &self,
+    options: &RequestOptions,
+    query: String,
+    query_params: QueryParams,
+    query_options: QueryOptions,
+  ) -> error::Result<Frame> {
+    let consistency = query_params.consistency;
+    self
+      .send(options, consistency, |session, consistency| {
+        let query = query.clone();
+        let mut query_params = clone_query_params(&query_params);
+        query_params.consistency = consistency;
+
+        async move {
+          session
+            .query_with_options(query, query_params, query_options)
+            .await
+        }
+      })
+      .await
+  }
+
+  async fn send_exec(
+    &self,
+    options: &RequestOptions,
+    prepared: &PreparedQuery,
+    query_params: QueryParams,
+    query_options: QueryOptions,
+  ) -> error::Result<Frame> {
+    let consistency = query_params.consistency;
+    self
+      .send(options, consistency, |session, consistency| {
+        let prepared = prepared.clone();
+        let mut query_params = clone_query_params(&query_params);
+        query_params.consistency = consistency;
+
+        async move {
+          session
+            .exec_with_options(&prepared, query_params, query_options)
+            .await
+        }
+      })
+      .await
+  }
+
+  async fn send_exec_statement(
+    &self,
+    options: &RequestOptions,
+    statement: &PreparedStatement,
+    query_params: QueryParams,
+    query_options: QueryOptions,
+  ) -> error::Result<Frame> {
+    let query_params = statement.bind_params(query_params)?;
+    let consistency = query_params.consistency;
+    self
+      .send(options, consistency, |session, consistency| {
+        let statement = statement.clone();
+        let mut query_params = clone_query_params(&query_params);
+        query_params.consistency = consistency;
+
+        async move {
+          session
+            .exec_statement_with_options(&statement, query_params, query_options)
+            .await
+        }
+      })
+      .await
+  }
+
+  async fn send_batch(
+    &self,
+    options: &RequestOptions,
+    batch: QueryBatch,
+    query_options: QueryOptions,
+  ) -> error::Result<Frame> {
+    let consistency = batch.consistency;
+    self
+      .send(options, consistency, |session, consistency| {
+        let mut batch = batch.clone();
+        batch.consistency = consistency;
+
+        async move { session.batch_with_options(batch, query_options).await }
+      })
+      .await
+  }
+}
+
+/// Opens core connections of a pool and reads a location of its node.
+async fn init_node<M: ConnectionManager>(pool: &NodePool<M>) -> error::Result<Node> {
+  pool.init().await?;
+  let session = pool.acquire().await?;
+
+  match fetch_local(pool.addr(), &session).await {
+    Ok(node) => Ok(node),
+    Err(err) => {
+      warn!(
+        "CDRS cluster: cannot read info of {}: {:?}",
+        pool.addr(),
+        err
+      );
+      Ok(Node::unknown(pool.addr()))
+    }
+  }
+}
+
+/// Nodes a session knows about, pools of connections to them and a ring
+/// of tokens they own. A topology is replaced as a whole when a node joins
+/// a cluster, so each request uses a consistent snapshot of it.
+struct Topology<M: ConnectionManager> {
+  nodes: Vec<Arc<Node>>,
+  pools: HashMap<String, Arc<NodePool<M>>>,
+  token_map: Arc<TokenMap>,
+}
+
+impl<M: ConnectionManager> Topology<M> {
+  fn new(nodes: Vec<Arc<Node>>, pools: HashMap<String, Arc<NodePool<M>>>) -> Self {
+    Topology {
+      token_map: Arc::new(TokenMap::new(&nodes)),
+      nodes,
+      pools,
+    }
+  }
+}
+
+impl<M: ConnectionManager> Default for Topology<M> {
+  fn default() -> Self {
+    Topology::new(vec![], HashMap::new())
+  }
+}
+
+/// State which clones of a session share with a task handling server events.
+/// Prepared queries of all nodes are cached together, so a node which does
+/// not know a query, e.g. because it joined a cluster after the query had
+/// been prepared, prepares it again from the cache.
+struct Shared<M: ConnectionManager> {
+  topology: RwLock<Arc<Topology<M>>>,
+  connection_manager: Arc<M>,
+  pool_config: PoolConfig,
+  address_translator: Arc<dyn AddressTranslator>,
+  prepared_cache: Arc<PreparedCache>,
+}
+
+impl<M: ConnectionManager> Shared<M> {
+  fn topology(&self) -> Arc<Topology<M>> {
+    self
+      .topology
+      .read()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+      .clone()
+  }
+
+  fn set_topology(&self, topology: Topology<M>) {
+    *self
+      .topology
+      .write()
+      .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(topology);
+  }
+
+  fn new_pool(&self, addr: String) -> NodePool<M> {
+    NodePool::new(
+      addr,
+      self.pool_config.clone(),
+      self.connection_manager.clone(),
+      self.prepared_cache.clone(),
+    )
+  }
+
+  /// Connects to a node which joined a cluster and adds it to the topology.
+  /// A node may not accept connections yet when it is reported, then its pool
+  /// tries to connect on demand like pools of unreachable peers do.
+  async fn add_node(&self, rpc_address: SocketAddr) {
+    let addr = self.address_translator.translate(rpc_address).to_string();
+    let is_known = |topology: &Topology<M>| {
+      topology.pools.contains_key(&addr)
+        || topology
+          .nodes
+          .iter()
+          .any(|node| node.has_address(rpc_address))
+    };
+    if is_known(&self.topology()) {
+      return;
+    }
+
+    let pool = self.new_pool(addr.clone());
+    let node = match init_node(&pool).await {
+      Ok(node) => node,
+      Err(err) => {
+        warn!(
+          "CDRS cluster: cannot connect to new node {}: {:?}",
+          addr, err
+        );
+        Node::unknown(&addr)
+      }
+    };
+
+    let mut topology = self
+      .topology
+      .write()
+      .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if is_known(&topology) {
+      return;
+    }
+
+    info!("CDRS cluster: node {} joined the cluster", addr);
+    let mut nodes = topology.nodes.clone();
+    nodes.push(Arc::new(node));
+    let mut pools = topology.pools.clone();
+    pools.insert(addr, Arc::new(pool));
+    *topology = Arc::new(Topology::new(nodes, pools));
+  }
+
+  /// Removes a node which left a cluster and closes its pool,
+  /// so it does not own tokens any more.
+  fn remove_node(&self, rpc_address: SocketAddr) {
+    let addr = self.address_translator.translate(rpc_address).to_string();
+    let mut topology = self
+      .topology
+      .write()
+      .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let (removed, nodes): (Vec<_>, Vec<_>) = topology
+      .nodes
+      .iter()
+      .cloned()
+      .partition(|node| node.addr() == addr || node.has_address(rpc_address));
+    if removed.is_empty() {
+      return;
+    }
+
+    let mut pools = topology.pools.clone();
+    for node in removed {
+      info!("CDRS cluster: node {} left the cluster", node.addr());
+      pools.remove(node.addr());
+    }
+    *topology = Arc::new(Topology::new(nodes, pools));
+  }
+}
+
+/// Sends events of a node to a stream. When a connection to the node is
+/// closed, events are registered for via another node. It stops as soon as
+/// the stream or all clones of a session are dropped.
+async fn forward_events<M: ConnectionManager, LB: LoadBalancingPolicy>(
+  shared: Weak<Shared<M>>,
+  load_balancing: Arc<LB>,
+  retry_policy: Arc<dyn RetryPolicy>,
+  event_types: Vec<EventType>,
+  mut events: EventStream,
+  sender: mpsc::UnboundedSender<ServerEvent>,
+) {
+  loop {
+    while let Some(event) = events.next().await {
+      if sender.unbounded_send(event).is_err() {
+        return;
+      }
+    }
+
+    warn!("CDRS cluster: event connection was closed, registering for events again");
+    events = loop {
+      let session = match shared.upgrade() {
+        Some(shared) if !sender.is_closed() => ClusterSession {
+          shared,
+          load_balancing: load_balancing.clone(),
+          retry_policy: retry_policy.clone(),
+        },
+        _ => return,
+      };
+      match session.register_node(&event_types).await {
+        Ok(events) => break events,
+        Err(err) => warn!("CDRS cluster: cannot register for events: {:?}", err),
+      }
+
+      drop(session);
+      task::sleep(REGISTER_RETRY_DELAY).await;
+    };
+  }
+}
+
+/// Keeps the topology and prepared queries of a session up to date according
+/// to server events. It stops once all clones of the session are dropped.
+async fn handle_events<M: ConnectionManager>(shared: Weak<Shared<M>>, mut events: EventStream) {
+  while let Some(event) = events.next().await {
+    let shared = match shared.upgrade() {
+      Some(shared) => shared,
+      None => return,
+    };
+    let set_up = |addr, is_up| {
+      for node in shared
+        .topology()
+        .nodes
+        .iter()
+        .filter(|node| node.has_address(addr))
+      {
+        info!(
+          "CDRS cluster: node {} is {}",
+          node.addr(),
+          if is_up { "up" } else { "down" }
+        );
+        node.set_up(is_up);
+      }
+    };
+
+    match event {
+      ServerEvent::StatusChange(change) => {
+        set_up(change.addr, change.change_type == StatusChangeType::Up)
+      }
+      ServerEvent::TopologyChange(TopologyChange {
+        change_type: TopologyChangeType::RemovedNode,
+        addr,
+      }) => shared.remove_node(addr),
+      ServerEvent::TopologyChange(TopologyChange {
+        change_type: TopologyChangeType::NewNode,
+        addr,
+      }) => shared.add_node(addr).await,
+      ServerEvent::SchemaChange(change) => {
+        debug!("CDRS cluster: schema changed: {:?}", change);
+        shared.prepared_cache.invalidate(&change);
+      }
+    }
+  }
+}
+
+/// Cluster session view which sends all requests with the same options.
+/// It is created by `ClusterSession::with_options`.
+pub struct SessionView<M: ConnectionManager, LB: LoadBalancingPolicy = RoundRobin> {
+  session: ClusterSession<M, LB>,
+  options: RequestOptions,
+}
+
+impl<M: ConnectionManager, LB: LoadBalancingPolicy> SessionView<M, LB> {
+  pub fn options(&self) -> &RequestOptions {
+    &self.options
+  }
+
+  /// Marks requests of the view as idempotent ones.
+  pub fn idempotent(mut self, is_idempotent: bool) -> Self {
+    self.options = self.options.idempotent(is_idempotent);
+
+    self
+  }
+
+  /// Sets a time to wait for a response to each attempt of requests of the view.
+  pub fn timeout(mut self, timeout: Duration) -> Self {
+    self.options = self.options.timeout(timeout);
+
+    self
+  }
+}
+
+#[async_trait]
+impl<M: ConnectionManager, LB: LoadBalancingPolicy> QueryExecutor for ClusterSession<M, LB> {
+  async fn query_with_options<Q: ToString + Send>(
+    &self,
+    query: Q,
+    query_params: QueryParams,
+    options: QueryOptions,
+  ) -> error::Result<Frame> {
+    self
+      .send_query(
+        &RequestOptions::default(),
+        query.to_string(),
+        query_params,
+        options,
+      )
+      .await
+  }
+}
+
+#[async_trait]
+impl<M: ConnectionManager, LB: LoadBalancingPolicy> PrepareExecutor for ClusterSession<M, LB> {
+  /// Prepares a query on a first reachable node. Other nodes prepare it
+  /// when they are asked to execute it for the first time.
+  async fn prepare_statement_tw<Q: ToString + Send>(
+    &self,
+    query: Q,
+    with_tracing: bool,
+    with_warnings: bool,
+  ) -> error::Result<PreparedStatement> {
+    self
+      .acquire(&RoutingInfo::default())
+      .await?
+      .prepare_statement_tw(query, with_tracing, with_warnings)
+      .await
+  }
+}
+
+#[async_trait]
+impl<M: ConnectionManager, LB: LoadBalancingPolicy> ExecExecutor for ClusterSession<M, LB> {
+  async fn exec_with_options(
+    &self,
+    prepared: &PreparedQuery,
+    query_parameters: QueryParams,
+    options: QueryOptions,
+  ) -> error::Result<Frame> {
+    self
+      .send_exec(
+        &RequestOptions::default(),
+        prepared,
+        query_parameters,
+        options,
+      )
+      .await
+  }
+
+  async fn exec_statement_with_options(
+    &self,
+    statement: &PreparedStatement,
+    query_parameters: QueryParams,
+    options: QueryOptions,
+  ) -> error::Result<Frame> {
+    self
+      .send_exec_statement(
+        &RequestOptions::default(),
+        statement,
+        query_parameters,
+        options,
+      )
+      .await
+  }
+}
+
+#[async_trait]
+impl<M: ConnectionManager, LB: LoadBalancingPolicy> BatchExecutor for ClusterSession<M, LB> {
+  async fn batch_with_options(
+    &self,
+    batch: QueryBatch,
+    options: QueryOptions,
+  ) -> error::Result<Frame> {
+    self
+      .send_batch(&RequestOptions::default(), batch, options)
+      .await
+  }
+}
+
+#[async_trait]
+impl<M: ConnectionManager, LB: LoadBalancingPolicy> QueryExecutor for SessionView<M, LB> {
+  async fn query_with_options<Q: ToString + Send>(
+    &self,
+    query: Q,
+    query_params: QueryParams,
+    options: QueryOptions,
+  ) -> error::Result<Frame> {
+    self
+      .session
+      .send_query(&self.options, query.to_string(), query_params, options)
+      .await
+  }
+}
+
+#[async_trait]
+impl<M: ConnectionManager, LB: LoadBalancingPolicy> PrepareExecutor for SessionView<M, LB> {
+  async fn prepare_statement_tw<Q: ToString + Send>(
+    &self,
+    query: Q,
+    with_tracing: bool,
+    with_warnings: bool,
+  ) -> error::Result<PreparedStatement> {
+    self
+      .session
+      .prepare_statement_tw(query, with_tracing, with_warnings)
+      .await
+  }
+}
+
+#[async_trait]
+impl<M: ConnectionManager, LB: LoadBalancingPolicy> ExecExecutor for SessionView<M, LB> {
+  async fn exec_with_options(
+    &self,
+    prepared: &PreparedQuery,
+    query_parameters: QueryParams,
+    options: QueryOptions,
+  ) -> error::Result<Frame> {
+    self
+      .session
+      .send_exec(&self.options, prepared, query_parameters, options)
+      .await
+  }
+
+  async fn exec_statement_with_options(
+    &self,
+    statement: &PreparedStatement,
+    query_parameters: QueryParams,
+    options: QueryOptions,
+  ) -> error::Result<Frame> {
+    self
+      .session
+      .send_exec_statement(&self.options, statement, query_parameters, options)
+      .await
+  }
+}
+
+#[async_trait]
+impl<M: ConnectionManager, LB: LoadBalancingPolicy> BatchExecutor for SessionView<M, LB> {
+  async fn batch_with_options(
+    &self,
+    batch: QueryBatch,
+    options: QueryOptions,
+  ) -> error::Result<Frame> {
+    self.session.send_batch(&self.options, batch, options).await
+  }
+}
@@ -0,0 +1,503 @@
+use std::{
+  collections::{HashMap, HashSet},
+  sync::Arc,
+};
+
+use super::node::Node;
+use crate::{
+  error,
+  token::{PartitionToken, Partitioner, TokenRange},
+};
+
+/// Replication strategy of a keyspace which defines how many replicas
+/// of each partition a cluster keeps and which nodes they are placed on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplicationStrategy {
+  /// Replicas are placed on the next nodes of the ring regardless
+  /// of their data centers and racks.
+  Simple { replication_factor: usize },
+  /// Replicas are placed in each data center separately, a data center
+  /// which is not listed keeps no replicas. Nodes of different racks
+  /// are preferred within a data center.
+  NetworkTopology {
+    replication_factors: HashMap<String, usize>,
+  },
+  /// Replicas are placed in a way the driver is not aware of,
+  /// e.g. by `LocalStrategy` or a custom strategy. Only a node
+  /// which owns a token is considered a replica.
+  Other { class: String },
+}
+
+impl ReplicationStrategy {
+  /// Parses `replication` options of a keyspace as they are stored
+  /// in `system_schema.keyspaces`, e.g.
+  /// `{'class': 'SimpleStrategy', 'replication_factor': '3'}`.
+  pub fn from_options(options: &HashMap<String, String>) -> error::Result<Self> {
+    let class = options
+      .get("class")
+      .ok_or("Replication options do not contain a class")?;
+
+    // a class could be given either by a full or a short name
+    match class.rsplit('.').next().unwrap_or_default() {
+      "SimpleStrategy" => {
+        let factor = options
+          .get("replication_factor")
+          .ok_or("SimpleStrategy requires replication_factor")?;
+
+        Ok(ReplicationStrategy::Simple {
+          replication_factor: parse_replication_factor(factor)?,
+        })
+      }
+      "NetworkTopologyStrategy" => Ok(ReplicationStrategy::NetworkTopology {
+        replication_factors: options
+          .iter()
+          .filter(|(dc, _)| dc.as_str() != "class")
+          .map(|(dc, factor)| Ok((dc.clone(), parse_replication_factor(factor)?)))
+          .collect::<error::Result<_>>()?,
+      }),
+      _ => Ok(ReplicationStrategy::Other {
+        class: class.clone(),
+      }),
+    }
+  }
+}
+
+/// Parses a replication factor. Cassandra 4 allows to specify a number
+/// of transient replicas as `total/transient`, all of them are replicas.
+fn parse_replication_factor(factor: &str) -> error::Result<usize> {
+  factor
+    .split('/')
+    .next()
+    .unwrap_or_default()
+    .trim()
+    .parse()
+    .map_err(|_| format!("Invalid replication factor {:?}", factor).into())
+}
+
+/// Ring of tokens which cluster nodes own. It answers which nodes are
+/// replicas of a partition with a given token.
+#[derive(Debug, Clone, Default)]
+pub struct TokenMap {
+  ring: Vec<(PartitionToken, Arc<Node>)>,
+  partitioner: Partitioner,
+  datacenters: HashMap<String, Datacenter>,
+}
+
+/// Numbers of nodes and racks of a data center which own tokens.
+#[derive(Debug, Clone, Default)]
+struct Datacenter {
+  nodes: usize,
+  racks: usize,
+}
+
+impl TokenMap {
+  /// Builds a ring of tokens which given nodes own, i.e. of ones
+  /// which are read from `tokens` column of `system.local`
+  /// and `system.peers`.
+  pub fn new(nodes: &[Arc<Node>]) -> Self {
+    let mut ring: Vec<(PartitionToken, Arc<Node>)> = nodes
+      .iter()
+      .flat_map(|node| {
+        node
+          .tokens()
+          .iter()
+          .map(move |token| (token.clone(), node.clone()))
+      })
+      .collect();
+    ring.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let partitioner = ring
+      .first()
+      .map(|(token, _)| token.partitioner())
+      .unwrap_or_default();
+
+    let mut racks: HashMap<&str, HashSet<&str>> = HashMap::new();
+    let mut datacenters: HashMap<String, Datacenter> = HashMap::new();
+    for node in nodes.iter().filter(|node| !node.tokens().is_empty()) {
+      if let Some(dc) = node.datacenter() {
+        datacenters.entry(dc.to_string()).or_default().nodes += 1;
+        racks.entry(dc).or_default().extend(node.rack());
+      }
+    }
+    for (dc, racks) in racks {
+      if let Some(datacenter) = datacenters.get_mut(dc) {
+        datacenter.racks = racks.len();
+      }
+    }
+
+    TokenMap {
+      ring,
+      partitioner,
+      datacenters,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.ring.is_empty()
+  }
+
+  /// Partitioner which tokens of the ring are computed by.
+  pub fn partitioner(&self) -> Partitioner {
+    self.partitioner
+  }
+
+  /// Returns tokens of the ring in ascending order with their owners.
+  pub fn ring(&self) -> &[(PartitionToken, Arc<Node>)] {
+    &self.ring
+  }
+
+  /// Returns ranges of tokens with nodes which own them, i.e. the first
+  /// replicas of partitions which tokens fall into these ranges.
+  /// Token ranges are ones of Murmur3 partitioner, so there are none
+  /// if a cluster uses another one.
+  pub fn ranges(&self) -> Vec<(TokenRange, Arc<Node>)> {
+    let ring: Vec<_> = self
+      .ring
+      .iter()
+      .filter_map(|(token, node)| match token {
+        PartitionToken::Murmur3(token) => Some((*token, node)),
+        _ => None,
+      })
+      .collect();
+    let last = match ring.last() {
+      Some((token, _)) => *token,
+      None => return vec![],
+    };
+
+    ring
+      .into_iter()
+      .scan(last, |start, (end, node)| {
+        let range = TokenRange::new(*start, end);
+        *start = end;
+        Some((range, node.clone()))
+      })
+      .collect()
+  }
+
+  /// Returns a node which owns a given token, i.e. a node with the smallest
+  /// token which is greater than or equal to a given one. The ring wraps
+  /// around, so the node with the smallest token owns the rest.
+  pub fn owner<T: Into<PartitionToken>>(&self, token: T) -> Option<&Arc<Node>> {
+    self.walk(&token.into()).next()
+  }
+
+  /// Returns replicas of a partition with a given token in the order
+  /// they are met walking the ring from the token, so the owner of the token
+  /// is the first one for strategies which keep any replica in its data center.
+  pub fn replicas<T: Into<PartitionToken>>(
+    &self,
+    token: T,
+    strategy: &ReplicationStrategy,
+  ) -> Vec<Arc<Node>> {
+    let token = token.into();
+    match strategy {
+      ReplicationStrategy::Simple { replication_factor } => {
+        let mut replicas: Vec<Arc<Node>> = vec![];
+        for node in self.walk(&token) {
+          if replicas.len() == *replication_factor {
+            break;
+          }
+          if !contains(&replicas, node) {
+            replicas.push(node.clone());
+          }
+        }
+        replicas
+      }
+      ReplicationStrategy::NetworkTopology {
+        replication_factors,
+      } => self.network_topology_replicas(&token, replication_factors),
+      ReplicationStrategy::Other { .. } => self.walk(&token).next().cloned().into_iter().collect(),
+    }
+  }
+
+  /// Places replicas as `NetworkTopologyStrategy` does: within each data center
+  /// nodes of racks which hold no replica yet are taken first, nodes of other
+  /// racks are taken in the ring order once each rack holds a replica.
+  fn network_topology_replicas(
+    &self,
+    token: &PartitionToken,
+    replication_factors: &HashMap<String, usize>,
+  ) -> Vec<Arc<Node>> {
+    let mut dcs: HashMap<&str, DcReplicas> = replication_factors
+      .iter()
+      .filter_map(|(dc, factor)| {
+        let datacenter = self.datacenters.get(dc)?;
+        // a data center could not keep more replicas than it has nodes
+        let factor = (*factor).min(datacenter.nodes);
+        Some((dc.as_str(), DcReplicas::new(factor, datacenter.racks)))
+      })
+      .collect();
+
+    let mut result = vec![];
+    for node in self.walk(token) {
+      if dcs.values().all(DcReplicas::is_complete) {
+        break;
+      }
+      let replicas = match node.datacenter().and_then(|dc| dcs.get_mut(dc)) {
+        Some(replicas) if !replicas.is_complete() => replicas,
+        _ => continue,
+      };
+      if contains(&replicas.nodes, node) {
+        continue;
+      }
+
+      match node.rack() {
+        Some(rack) if replicas.racks.len() < replicas.rack_count => {
+          if replicas.racks.contains(rack) {
+            if !contains(&replicas.skipped, node) {
+              replicas.skipped.push(node.clone());
+            }
+            continue;
+          }
+
+          replicas.racks.insert(rack);
+          replicas.add(node, &mut result);
+          // all racks hold a replica, so skipped nodes are the next ones
+          if replicas.racks.len() == replicas.rack_count {
+            for skipped in std::mem::take(&mut replicas.skipped) {
+              if replicas.is_complete() {
+                break;
+              }
+              replicas.add(&skipped, &mut result);
+            }
+          }
+        }
+        _ => replicas.add(node, &mut result),
+      }
+    }
+
+    result
+  }
+
+  /// Iterates over owners of tokens starting from the owner of a given one
+  /// and wrapping around the ring. Nodes with several tokens are returned
+  /// as many times as tokens they own.
+  fn walk(&self, token: &PartitionToken) -> impl Iterator<Item = &Arc<Node>> {
+    let start = self.ring.partition_point(|(t, _)| t < token);
+
+    self.ring[start..]
+      .iter()
+      .chain(self.ring[..start].iter())
+      .map(|(_, node)| node)
+  }
+}
+
+/// Replicas of one data center which are being placed.
+struct DcReplicas<'a> {
+  factor: usize,
+  rack_count: usize,
+  nodes: Vec<Arc<Node>>,
+  racks: HashSet<&'a str>,
+  skipped: Vec<Arc<Node>>,
+}
+
+impl<'a> DcReplicas<'a> {
+  fn new(factor: usize, rack_count: usize) -> Self {
+    DcReplicas {
+      factor,
+      rack_count,
+      nodes: vec![],
+      racks: HashSet::new(),
+      skipped: vec![],
+    }
+  }
+
+  fn is_complete(&self) -> bool {
+    self.nodes.len() >= self.factor
+  }
+
+  fn add(&mut self, node: &Arc<Node>, result: &mut Vec<Arc<Node>>) {
+    self.nodes.push(node.clone());
+    result.push(node.clone());
+  }
+}
+
+fn contains(nodes: &[Arc<Node>], node: &Arc<Node>) -> bool {
+  nodes.iter().any(|n| Arc::ptr_eq(n, node))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::token::Token;
+
+  fn node(addr: &str, dc: &str, rack: &str, tokens: &[i64]) -> Arc<Node> {
+    Arc::new(Node::new(
+      addr,
+      Some(dc.to_string()),
+      Some(rack.to_string()),
+      tokens.iter().cloned().map(Token).collect(),
+    ))
+  }
+
+  fn addrs(nodes: &[Arc<Node>]) -> Vec<&str> {
+    nodes.iter().map(|node| node.addr()).collect()
+  }
+
+  fn options(options: &[(&str, &str)]) -> HashMap<String, String> {
+    options
+      .iter()
+      .map(|(key, value)| (key.to_string(), value.to_string()))
+      .collect()
+  }
+
+  fn nts(factors: &[(&str, usize)]) -> ReplicationStrategy {
+    ReplicationStrategy::NetworkTopology {
+      replication_factors: factors
+        .iter()
+        .map(|(dc, factor)| (dc.to_string(), *factor))
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn test_replication_strategy_from_options() {
+    assert_eq!(
+      ReplicationStrategy::from_options(&options(&[
+        ("class", "org.apache.cassandra.locator.SimpleStrategy"),
+        ("replication_factor", "3"),
+      ]))
+      .unwrap(),
+      ReplicationStrategy::Simple {
+        replication_factor: 3
+      }
+    );
+    assert_eq!(
+      ReplicationStrategy::from_options(&options(&[
+        ("class", "NetworkTopologyStrategy"),
+        ("dc1", "3"),
+        ("dc2", "2/1"),
+      ]))
+      .unwrap(),
+      nts(&[("dc1", 3), ("dc2", 2)])
+    );
+    assert_eq!(
+      ReplicationStrategy::from_options(&options(&[(
+        "class",
+        "org.apache.cassandra.locator.LocalStrategy"
+      )]))
+      .unwrap(),
+      ReplicationStrategy::Other {
+        class: "org.apache.cassandra.locator.LocalStrategy".into()
+      }
+    );
+    assert!(ReplicationStrategy::from_options(&options(&[("class", "SimpleStrategy")])).is_err());
+    assert!(ReplicationStrategy::from_options(&options(&[
+      ("class", "SimpleStrategy"),
+      ("replication_factor", "three"),
+    ]))
+    .is_err());
+  }
+
+  #[test]
+  fn test_owner_and_ranges() {
+    let map = TokenMap::new(&[
+      node("a", "dc1", "r1", &[-100, 0]),
+      node("b", "dc1", "r1", &[-50, 50]),
+    ]);
+
+    assert_eq!(map.owner(Token(-60)).unwrap().addr(), "b");
+    assert_eq!(map.owner(Token(0)).unwrap().addr(), "a");
+    assert_eq!(map.owner(Token(51)).unwrap().addr(), "a");
+    assert!(TokenMap::new(&[]).owner(Token(0)).is_none());
+
+    let ranges: Vec<_> = map
+      .ranges()
+      .into_iter()
+      .map(|(range, node)| (range.start.0, range.end.0, node.addr().to_string()))
+      .collect();
+    assert_eq!(
+      ranges,
+      vec![
+        (50, -100, "a".to_string()),
+        (-100, -50, "b".to_string()),
+        (-50, 0, "a".to_string()),
+        (0, 50, "b".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_simple_strategy_skips_nodes_already_chosen() {
+    let map = TokenMap::new(&[
+      node("a", "dc1", "r1", &[0, 10]),
+      node("b", "dc1", "r1", &[20]),
+      node("c", "dc2", "r1", &[30]),
+    ]);
+    let simple = |replication_factor| ReplicationStrategy::Simple { replication_factor };
+
+    assert_eq!(addrs(&map.replicas(Token(5), &simple(2))), vec!["a", "b"]);
+    assert_eq!(
+      addrs(&map.replicas(Token(25), &simple(3))),
+      vec!["c", "a", "b"]
+    );
+    assert_eq!(
+      addrs(&map.replicas(Token(25), &simple(5))),
+      vec!["c", "a", "b"]
+    );
+  }
+
+  #[test]
+  fn test_network_topology_strategy_per_dc() {
+    let map = TokenMap::new(&[
+      node("a", "dc1", "r1", &[0]),
+      node("b", "dc2", "r1", &[10]),
+      node("c", "dc1", "r1", &[20]),
+      node("d", "dc2", "r1", &[30]),
+      node("e", "dc1", "r1", &[40]),
+    ]);
+
+    assert_eq!(
+      addrs(&map.replicas(Token(5), &nts(&[("dc1", 2), ("dc2", 1)]))),
+      vec!["b", "c", "e"]
+    );
+    assert_eq!(
+      addrs(&map.replicas(Token(5), &nts(&[("dc2", 5)]))),
+      vec!["b", "d"]
+    );
+    assert!(map.replicas(Token(5), &nts(&[("dc3", 1)])).is_empty());
+  }
+
+  #[test]
+  fn test_network_topology_strategy_prefers_other_racks() {
+    let map = TokenMap::new(&[
+      node("a", "dc1", "r1", &[0]),
+      node("b", "dc1", "r1", &[10]),
+      node("c", "dc1", "r1", &[20]),
+      node("d", "dc1", "r2", &[30]),
+    ]);
+
+    assert_eq!(
+      addrs(&map.replicas(Token(0), &nts(&[("dc1", 2)]))),
+      vec!["a", "d"]
+    );
+    // skipped nodes are taken in the ring order once all racks hold a replica
+    assert_eq!(
+      addrs(&map.replicas(Token(0), &nts(&[("dc1", 3)]))),
+      vec!["a", "d", "b"]
+    );
+  }
+
+  #[test]
+  fn test_other_strategy_uses_owner() {
+    let map = TokenMap::new(&[node("a", "dc1", "r1", &[0]), node("b", "dc1", "r1", &[10])]);
+    let strategy = ReplicationStrategy::Other {
+      class: "LocalStrategy".into(),
+    };
+
+    assert_eq!(addrs(&map.replicas(Token(5), &strategy)), vec!["b"]);
+  }
+
+  #[test]
+  fn test_random_partitioner_ring() {
+    let random = |token: u128| PartitionToken::Random(token);
+    let map = TokenMap::new(&[
+      Arc::new(Node::new("a", None, None, vec![random(1 << 100)])),
+      Arc::new(Node::new("b", None, None, vec![random(1 << 120)])),
+    ]);
+
+    assert_eq!(map.partitioner(), Partitioner::Random);
+    assert_eq!(map.owner(random(1 << 110)).unwrap().addr(), "b");
+    assert_eq!(map.owner(random(u128::MAX)).unwrap().addr(), "a");
+    assert!(map.ranges().is_empty());
+  }
+}
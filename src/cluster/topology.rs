@@ -0,0 +1,189 @@
+use std::{collections::HashMap, net::SocketAddr};
+
+use cassandra_proto::types::{map::Map, rows::Row, AsRustType, IntoRustByName};
+use log::{debug, warn};
+
+use super::{
+  node::{rpc_address, Node},
+  token_map::ReplicationStrategy,
+};
+use crate::{error, query::QueryExecutor, token::Partitioner};
+
+/// Port of a native protocol server which Cassandra listens on by default.
+pub(super) const DEFAULT_PORT: u16 = 9042;
+
+const SELECT_LOCAL_QUERY: &str = "SELECT host_id, data_center, rack, tokens, release_version, \
+                                  rpc_address, broadcast_address AS peer, partitioner \
+                                  FROM system.local";
+const SELECT_PARTITIONER_QUERY: &str = "SELECT partitioner FROM system.local";
+const SELECT_PEERS_QUERY: &str = "SELECT host_id, data_center, rack, tokens, release_version, \
+                                  rpc_address, peer FROM system.peers";
+const SELECT_PEERS_V2_QUERY: &str = "SELECT host_id, data_center, rack, tokens, release_version, \
+                                     native_address, native_port, peer FROM system.peers_v2";
+const SELECT_REPLICATION_QUERY: &str =
+  "SELECT replication FROM system_schema.keyspaces WHERE keyspace_name = ?";
+
+/// Address translator maps addresses which nodes advertise to ones a client
+/// can reach them by, e.g. when a cluster runs behind NAT or in Docker.
+pub trait AddressTranslator: Send + Sync + 'static {
+  fn translate(&self, addr: SocketAddr) -> SocketAddr;
+}
+
+/// Address translator which uses advertised addresses as is.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IdentityTranslator;
+
+impl AddressTranslator for IdentityTranslator {
+  fn translate(&self, addr: SocketAddr) -> SocketAddr {
+    addr
+  }
+}
+
+async fn select_rows<E: QueryExecutor>(session: &E, query: &str) -> error::Result<Vec<Row>> {
+  Ok(
+    session
+      .query(query)
+      .await?
+      .get_body()?
+      .into_rows()
+      .unwrap_or_default(),
+  )
+}
+
+/// Reads an information about a node which a session is connected to.
+pub(crate) async fn fetch_local<E: QueryExecutor>(addr: &str, session: &E) -> error::Result<Node> {
+  let row = select_rows(session, SELECT_LOCAL_QUERY)
+    .await?
+    .into_iter()
+    .next()
+    .ok_or("Cannot read a local node info")?;
+  let port = port_of(addr);
+
+  Node::from_row(
+    addr,
+    rpc_address(&row, "rpc_address", port).ok(),
+    &row,
+    partitioner_of(&row)?,
+  )
+}
+
+/// Reads all peers of a node which a session is connected to. Peers which
+/// listen on a default port are assumed to use the same port as this node.
+/// `system.peers_v2` is used if a node supports it, `system.peers` otherwise.
+pub(crate) async fn fetch_peers<E: QueryExecutor>(
+  session: &E,
+  default_port: u16,
+  translator: &dyn AddressTranslator,
+) -> error::Result<Vec<Node>> {
+  // peers tables do not tell a partitioner, all nodes of a cluster use the same one
+  let partitioner = match select_rows(session, SELECT_PARTITIONER_QUERY)
+    .await?
+    .first()
+  {
+    Some(row) => partitioner_of(row)?,
+    None => Some(Partitioner::default()),
+  };
+  let to_node = |row: &Row, rpc: SocketAddr| {
+    Node::from_row(translator.translate(rpc), Some(rpc), row, partitioner)
+  };
+
+  match select_rows(session, SELECT_PEERS_V2_QUERY).await {
+    Ok(rows) => rows
+      .iter()
+      .map(|row| {
+        let port: Option<i32> = row.get_by_name("native_port")?;
+        let port = port.map(|port| port as u16).unwrap_or(default_port);
+        Ok((row, rpc_address(row, "native_address", port)?))
+      })
+      .map(|peer| peer.and_then(|(row, rpc)| to_node(row, rpc)))
+      .collect(),
+    Err(err) => {
+      debug!("CDRS cluster: peers_v2 is not available: {:?}", err);
+      select_rows(session, SELECT_PEERS_QUERY)
+        .await?
+        .iter()
+        .map(|row| to_node(row, rpc_address(row, "rpc_address", default_port)?))
+        .collect()
+    }
+  }
+}
+
+/// Reads replication settings of a keyspace.
+pub(crate) async fn fetch_replication<E: QueryExecutor>(
+  session: &E,
+  keyspace: &str,
+) -> error::Result<ReplicationStrategy> {
+  let row = session
+    .query_with_values(SELECT_REPLICATION_QUERY, vec![keyspace])
+    .await?
+    .get_body()?
+    .into_rows()
+    .and_then(|rows| rows.into_iter().next())
+    .ok_or_else(|| format!("Keyspace {} does not exist", keyspace))?;
+  let replication: Map = row.get_r_by_name("replication")?;
+  let options: HashMap<String, String> = replication.as_r_type()?;
+
+  ReplicationStrategy::from_options(&options)
+}
+
+/// Returns a port of a given address, which may be an IP address or a host name,
+/// or a default one if the address has no port. IPv6 addresses with a port
+/// are enclosed in brackets, e.g. `[::1]:9042`.
+pub(super) fn port_of(addr: &str) -> u16 {
+  let port = match addr.rfind(':') {
+    Some(colon) if addr.starts_with('[') => {
+      addr[..colon].ends_with(']').then(|| &addr[colon + 1..])
+    }
+    // a bare IPv6 address has several colons and no port
+    Some(colon) if addr[..colon].contains(':') => None,
+    Some(colon) => Some(&addr[colon + 1..]),
+    None => None,
+  };
+
+  port
+    .and_then(|port| port.parse().ok())
+    .unwrap_or(DEFAULT_PORT)
+}
+
+/// Reads a partitioner from a row of `system.local`, Murmur3 one is a default.
+/// Tokens of a partitioner the driver does not know cannot be parsed,
+/// so nodes own no tokens then.
+fn partitioner_of(row: &Row) -> error::Result<Option<Partitioner>> {
+  let class: Option<String> = row.get_by_name("partitioner")?;
+  let class = match class {
+    Some(class) => class,
+    None => return Ok(Some(Partitioner::default())),
+  };
+
+  let partitioner = Partitioner::from_class_name(&class);
+  if partitioner.is_none() {
+    warn!(
+      "CDRS cluster: partitioner {} is not supported, requests are not routed by tokens",
+      class
+    );
+  }
+
+  Ok(partitioner)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_port_of() {
+    assert_eq!(port_of("127.0.0.1:9043"), 9043);
+    assert_eq!(port_of("[::1]:9043"), 9043);
+    assert_eq!(port_of("localhost:9043"), 9043);
+    assert_eq!(port_of("cassandra-1:9044"), 9044);
+    assert_eq!(port_of("cassandra-1"), DEFAULT_PORT);
+    assert_eq!(port_of("::1"), DEFAULT_PORT);
+    assert_eq!(port_of("[::1]"), DEFAULT_PORT);
+  }
+
+  #[test]
+  fn test_identity_translator() {
+    let addr: SocketAddr = "10.0.0.1:9042".parse().unwrap();
+    assert_eq!(IdentityTranslator.translate(addr), addr);
+  }
+}
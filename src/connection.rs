@@ -0,0 +1,482 @@
+//! Multiplexed connection to a DB server.
+//!
+//! A single transport is shared by any number of concurrent requests. Each
+//! request gets its own stream id, frames are written by a dedicated writer
+//! task and responses are routed back to requesters by a background reader
+//! task which matches incoming frames by stream id. Events which a server
+//! pushes on a dedicated stream are broadcast to all event listeners.
+
+use std::{
+  collections::{HashMap, HashSet},
+  io, net,
+  pin::Pin,
+  sync::{Arc, Mutex, MutexGuard},
+  task::{Context, Poll},
+  time::Duration,
+};
+
+use async_std::{
+  future,
+  io::{Read, Write},
+  prelude::*,
+  task,
+};
+use cassandra_proto::frame::{parser_async::convert_frame_into_result, Frame, IntoBytes};
+use futures::{
+  channel::{mpsc, oneshot},
+  future::{AbortHandle, Abortable},
+};
+use log::{error, warn};
+
+use crate::{
+  compressor::Compression, error, events::ServerEvent, frame_channel::FrameChannel,
+  transport::CDRSTransport,
+};
+
+pub(crate) type StreamId = u16;
+
+/// Stream id `-1` which a server uses to push events.
+const EVENT_STREAM: StreamId = 0xFFFF;
+
+/// Number of stream ids which can be used by client requests. Starting from
+/// protocol v3 stream id is a signed 16-bit integer and negative ids are
+/// reserved for frames initiated by a server.
+const MAX_STREAMS: usize = 32_768;
+
+/// Number of timed out requests without responses after which a connection
+/// is considered broken and is closed.
+const MAX_ORPHANED_STREAMS: usize = 1_024;
+
+/// Transport handle which is shared between the reader and the writer tasks.
+///
+/// Any lock is held only for the time of a single non-blocking `poll_*` call,
+/// so reading and writing may progress independently.
+pub(crate) struct SharedTransport<T>(Arc<Mutex<T>>);
+
+impl<T> SharedTransport<T> {
+  fn new(transport: T) -> Self {
+    SharedTransport(Arc::new(Mutex::new(transport)))
+  }
+
+  fn lock(&self) -> MutexGuard<'_, T> {
+    self
+      .0
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+}
+
+impl<T> Clone for SharedTransport<T> {
+  fn clone(&self) -> Self {
+    SharedTransport(self.0.clone())
+  }
+}
+
+impl<T: CDRSTransport> Read for SharedTransport<T> {
+  fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+    Pin::new(&mut *self.lock()).poll_read(cx, buf)
+  }
+}
+
+impl<T: CDRSTransport> Write for SharedTransport<T> {
+  fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+    Pin::new(&mut *self.lock()).poll_write(cx, buf)
+  }
+
+  fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+    Pin::new(&mut *self.lock()).poll_flush(cx)
+  }
+
+  fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+    Pin::new(&mut *self.lock()).poll_close(cx)
+  }
+}
+
+impl<T: CDRSTransport> CDRSTransport for SharedTransport<T> {
+  fn close(&mut self, close: net::Shutdown) -> io::Result<()> {
+    self.lock().close(close)
+  }
+
+  fn is_alive(&self) -> bool {
+    self.lock().is_alive()
+  }
+}
+
+/// Requests which are waiting for responses, keyed by stream id.
+///
+/// Stream ids of requests which timed out stay reserved as orphaned ones until
+/// late responses arrive, so such responses are never delivered to other requests.
+struct Pending {
+  senders: HashMap<StreamId, oneshot::Sender<Frame>>,
+  orphaned: HashSet<StreamId>,
+  next_stream: StreamId,
+  is_closed: bool,
+}
+
+impl Pending {
+  fn new() -> Self {
+    Pending {
+      senders: HashMap::new(),
+      orphaned: HashSet::new(),
+      next_stream: 0,
+      is_closed: false,
+    }
+  }
+
+  fn is_used(&self, stream: StreamId) -> bool {
+    self.senders.contains_key(&stream) || self.orphaned.contains(&stream)
+  }
+
+  /// Reserves a free stream id for a new request.
+  fn register(&mut self) -> error::Result<(StreamId, oneshot::Receiver<Frame>)> {
+    if self.is_closed {
+      return Err(connection_closed());
+    }
+
+    if self.senders.len() + self.orphaned.len() >= MAX_STREAMS {
+      return Err("all stream ids of the connection are in use".into());
+    }
+
+    let mut stream = self.next_stream;
+    while self.is_used(stream) {
+      stream = (stream + 1) % MAX_STREAMS as StreamId;
+    }
+    self.next_stream = (stream + 1) % MAX_STREAMS as StreamId;
+
+    let (sender, receiver) = oneshot::channel();
+    self.senders.insert(stream, sender);
+
+    Ok((stream, receiver))
+  }
+
+  /// Stops waiting for a response to a request which timed out. It returns
+  /// `false` if there are too many orphaned streams, so the connection should
+  /// not be used anymore.
+  fn orphan(&mut self, stream: StreamId) -> bool {
+    if self.senders.remove(&stream).is_some() {
+      self.orphaned.insert(stream);
+    }
+
+    self.orphaned.len() < MAX_ORPHANED_STREAMS
+  }
+
+  /// Returns a sender of a request which waits for a response with a given
+  /// stream id. A stream id of a timed out request is released.
+  fn complete(&mut self, stream: StreamId) -> Option<oneshot::Sender<Frame>> {
+    if self.orphaned.remove(&stream) {
+      return None;
+    }
+
+    let sender = self.senders.remove(&stream);
+    if sender.is_none() {
+      warn!(
+        "CDRS connection: received a frame for unknown stream {}",
+        stream
+      );
+    }
+
+    sender
+  }
+
+  /// Marks a connection as closed. All requests which are still waiting
+  /// for responses are cancelled.
+  fn close(&mut self) {
+    self.is_closed = true;
+    self.senders.clear();
+    self.orphaned.clear();
+  }
+}
+
+fn lock<V>(value: &Mutex<V>) -> MutexGuard<'_, V> {
+  value
+    .lock()
+    .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Receivers of server events. Listeners are dropped when a connection
+/// is closed, so their event streams end.
+type Listeners = Arc<Mutex<Vec<mpsc::UnboundedSender<ServerEvent>>>>;
+
+/// Connection which multiplexes concurrent requests over a single transport.
+pub(crate) struct Connection<T> {
+  transport: SharedTransport<T>,
+  requests: mpsc::UnboundedSender<Vec<u8>>,
+  pending: Arc<Mutex<Pending>>,
+  listeners: Listeners,
+  reader: AbortHandle,
+  writer: AbortHandle,
+}
+
+impl<T: CDRSTransport + 'static> Connection<T> {
+  /// Wraps a transport and spawns reader and writer tasks which serve it.
+  pub fn new(transport: T, compressor: Compression) -> Self {
+    let transport = SharedTransport::new(transport);
+    let pending = Arc::new(Mutex::new(Pending::new()));
+    let listeners: Listeners = Arc::new(Mutex::new(vec![]));
+    let (requests, requests_receiver) = mpsc::unbounded();
+
+    let channel = FrameChannel::new(transport.clone(), compressor);
+    let (reader, reader_registration) = AbortHandle::new_pair();
+    task::spawn(Abortable::new(
+      read_frames(channel, pending.clone(), listeners.clone()),
+      reader_registration,
+    ));
+
+    let (writer, writer_registration) = AbortHandle::new_pair();
+    task::spawn(Abortable::new(
+      write_frames(transport.clone(), requests_receiver, pending.clone()),
+      writer_registration,
+    ));
+
+    Connection {
+      transport,
+      requests,
+      pending,
+      listeners,
+      reader,
+      writer,
+    }
+  }
+
+  /// Returns a receiver of all server events which come to this connection.
+  /// A server sends only events which were registered by a `REGISTER` request.
+  pub fn listen(&self) -> mpsc::UnboundedReceiver<ServerEvent> {
+    let (sender, receiver) = mpsc::unbounded();
+    // a connection that is already closed drops the sender immediately
+    if !lock(&self.pending).is_closed {
+      lock(&self.listeners).push(sender);
+    }
+
+    receiver
+  }
+
+  /// Sends a request frame and waits for a response to it at most a given
+  /// time. A stream id of the frame is replaced by a free one of this connection.
+  pub async fn send(&self, mut frame: Frame, timeout: Option<Duration>) -> error::Result<Frame> {
+    let (stream, response) = lock(&self.pending).register()?;
+    frame.stream = stream;
+
+    if self.requests.unbounded_send(frame.into_cbytes()).is_err() {
+      lock(&self.pending).senders.remove(&stream);
+      return Err(connection_closed());
+    }
+
+    // If a caller stops waiting for the response, either because of a timeout
+    // or because this future was dropped, the stream id stays reserved until
+    // the response arrives, so it will never be delivered to another request.
+    let mut guard = ResponseGuard {
+      connection: self,
+      stream,
+      is_answered: false,
+    };
+    let response = match timeout {
+      Some(timeout) => future::timeout(timeout, response)
+        .await
+        .map_err(|_| error::Error::Timeout(timeout))?,
+      None => response.await,
+    };
+    guard.is_answered = true;
+    let frame = response.map_err(|_| connection_closed())?;
+
+    Ok(convert_frame_into_result(frame)?)
+  }
+
+  /// Checks that a connection is not closed and the transport is alive.
+  pub fn is_alive(&self) -> bool {
+    !lock(&self.pending).is_closed && self.transport.is_alive()
+  }
+
+  /// Returns a number of requests which are waiting for responses.
+  pub fn in_flight(&self) -> usize {
+    lock(&self.pending).senders.len()
+  }
+}
+
+impl<T> Connection<T> {
+  /// Stops reader and writer tasks and fails all pending requests.
+  fn close(&self) {
+    self.reader.abort();
+    self.writer.abort();
+    lock(&self.pending).close();
+    lock(&self.listeners).clear();
+  }
+}
+
+impl<T> Drop for Connection<T> {
+  fn drop(&mut self) {
+    self.close();
+  }
+}
+
+/// Orphans a stream id of a request which a caller stopped waiting for.
+struct ResponseGuard<'a, T> {
+  connection: &'a Connection<T>,
+  stream: StreamId,
+  is_answered: bool,
+}
+
+impl<'a, T> Drop for ResponseGuard<'a, T> {
+  fn drop(&mut self) {
+    if self.is_answered {
+      return;
+    }
+
+    if !lock(&self.connection.pending).orphan(self.stream) {
+      error!("CDRS connection: too many requests were abandoned, closing connection");
+      self.connection.close();
+    }
+  }
+}
+
+/// Error which is returned when a response did not arrive in time.
+fn connection_closed() -> error::Error {
+  error::Error::ConnectionClosed("connection was closed".to_string())
+}
+
+async fn read_frames<T: CDRSTransport>(
+  mut channel: FrameChannel<SharedTransport<T>>,
+  pending: Arc<Mutex<Pending>>,
+  listeners: Listeners,
+) {
+  while let Some(frame) = channel.next().await {
+    if frame.stream == EVENT_STREAM {
+      broadcast_event(frame, &listeners);
+      continue;
+    }
+
+    let sender = lock(&pending).complete(frame.stream);
+    // a requester may have stopped waiting for a response, so it's fine to lose it
+    if let Some(sender) = sender {
+      let _ = sender.send(frame);
+    }
+  }
+
+  lock(&pending).close();
+  lock(&listeners).clear();
+}
+
+fn broadcast_event(frame: Frame, listeners: &Listeners) {
+  let event = match frame.get_body().map(|body| body.into_server_event()) {
+    Ok(Some(body)) => ServerEvent::from(body.event),
+    Ok(None) => return warn!("CDRS connection: received a non-event frame on event stream"),
+    Err(err) => return error!("CDRS connection: cannot parse an event: {:?}", err),
+  };
+
+  lock(listeners).retain(|listener| listener.unbounded_send(event.clone()).is_ok());
+}
+
+async fn write_frames<T: CDRSTransport>(
+  mut transport: SharedTransport<T>,
+  mut requests: mpsc::UnboundedReceiver<Vec<u8>>,
+  pending: Arc<Mutex<Pending>>,
+) {
+  while let Some(bytes) = requests.next().await {
+    if let Err(err) = write_frame(&mut transport, &mut requests, bytes).await {
+      error!("CDRS connection: {:?}", err);
+      break;
+    }
+  }
+
+  lock(&pending).close();
+  let _ = transport.close(net::Shutdown::Both);
+}
+
+/// Writes a frame along with all other frames which are already queued
+/// and flushes them at once.
+async fn write_frame<T: CDRSTransport>(
+  transport: &mut SharedTransport<T>,
+  requests: &mut mpsc::UnboundedReceiver<Vec<u8>>,
+  bytes: Vec<u8>,
+) -> io::Result<()> {
+  transport.write_all(&bytes).await?;
+  while let Ok(bytes) = requests.try_recv() {
+    transport.write_all(&bytes).await?;
+  }
+  transport.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_pending_register_unique_streams() {
+    let mut pending = Pending::new();
+    let (first, _first_receiver) = pending.register().unwrap();
+    let (second, _second_receiver) = pending.register().unwrap();
+
+    assert_ne!(first, second);
+    assert_eq!(pending.senders.len(), 2);
+  }
+
+  #[test]
+  fn test_pending_register_skips_used_streams() {
+    let mut pending = Pending::new();
+    let (first, _first_receiver) = pending.register().unwrap();
+    pending.next_stream = first;
+
+    let (second, _second_receiver) = pending.register().unwrap();
+    assert_ne!(first, second);
+  }
+
+  #[test]
+  fn test_pending_register_wraps_around() {
+    let mut pending = Pending::new();
+    pending.next_stream = (MAX_STREAMS - 1) as StreamId;
+    let (last, _last_receiver) = pending.register().unwrap();
+    let (first, _first_receiver) = pending.register().unwrap();
+
+    assert_eq!(last, (MAX_STREAMS - 1) as StreamId);
+    assert_eq!(first, 0);
+  }
+
+  #[test]
+  fn test_pending_register_exhausted() {
+    let mut pending = Pending::new();
+    let receivers: Vec<_> = (0..MAX_STREAMS)
+      .map(|_| pending.register().unwrap())
+      .collect();
+
+    assert_eq!(receivers.len(), MAX_STREAMS);
+    assert!(pending.register().is_err());
+  }
+
+  #[test]
+  fn test_pending_orphaned_stream_stays_reserved() {
+    let mut pending = Pending::new();
+    let (stream, _receiver) = pending.register().unwrap();
+    assert!(pending.orphan(stream));
+
+    pending.next_stream = stream;
+    let (next, _next_receiver) = pending.register().unwrap();
+    assert_ne!(stream, next, "should not reuse an orphaned stream");
+
+    assert!(pending.complete(stream).is_none());
+    assert!(
+      pending.orphaned.is_empty(),
+      "should release a stream on response"
+    );
+  }
+
+  #[test]
+  fn test_pending_too_many_orphaned_streams() {
+    let mut pending = Pending::new();
+    let streams: Vec<StreamId> = (0..MAX_ORPHANED_STREAMS)
+      .map(|_| pending.register().unwrap().0)
+      .collect();
+
+    let (last, rest) = streams.split_last().unwrap();
+    assert!(rest.iter().all(|stream| pending.orphan(*stream)));
+    assert!(!pending.orphan(*last));
+  }
+
+  #[test]
+  fn test_pending_close() {
+    let mut pending = Pending::new();
+    let (_, mut receiver) = pending.register().unwrap();
+    pending.close();
+
+    assert!(receiver.try_recv().is_err());
+    assert!(pending.register().is_err());
+  }
+}
@@ -0,0 +1,384 @@
+//! Errors of the driver. A server reports an error with one of codes which
+//! the protocol defines, each code has its own variant with a payload
+//! the server sent. Other variants describe errors which happen on a client.
+
+use std::{error, fmt, io, result, time::Duration};
+
+use cassandra_proto::{
+  consistency::Consistency,
+  error as proto,
+  frame::frame_error::{AdditionalErrorInfo, CDRSError, WriteType},
+};
+
+pub type Result<T> = result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+  /// Server failed because of an internal error.
+  Server {
+    message: String,
+  },
+  /// Server considers a request to violate the protocol.
+  Protocol {
+    message: String,
+  },
+  /// Server rejected credentials.
+  Authentication {
+    message: String,
+  },
+  /// Not enough replicas are alive to achieve a requested consistency.
+  Unavailable {
+    message: String,
+    consistency: Consistency,
+    required: i32,
+    alive: i32,
+  },
+  /// Coordinator is too busy to handle a request.
+  Overloaded {
+    message: String,
+  },
+  /// Coordinator is bootstrapping.
+  IsBootstrapping {
+    message: String,
+  },
+  /// Truncation failed.
+  Truncate {
+    message: String,
+  },
+  /// Coordinator did not receive enough acknowledgements of a write in time.
+  WriteTimeout {
+    message: String,
+    consistency: Consistency,
+    received: i32,
+    block_for: i32,
+    write_type: WriteType,
+  },
+  /// Coordinator did not receive enough responses to a read in time.
+  ReadTimeout {
+    message: String,
+    consistency: Consistency,
+    received: i32,
+    block_for: i32,
+    data_present: bool,
+  },
+  /// Replicas failed to execute a read.
+  ReadFailure {
+    message: String,
+    consistency: Consistency,
+    received: i32,
+    block_for: i32,
+    num_failures: i32,
+    data_present: bool,
+  },
+  /// User defined function failed.
+  FunctionFailure {
+    message: String,
+    keyspace: String,
+    function: String,
+    arg_types: Vec<String>,
+  },
+  /// Replicas failed to execute a write.
+  WriteFailure {
+    message: String,
+    consistency: Consistency,
+    received: i32,
+    block_for: i32,
+    num_failures: i32,
+    write_type: WriteType,
+  },
+  /// Query has a syntax error.
+  Syntax {
+    message: String,
+  },
+  /// User is not allowed to perform a request.
+  Unauthorized {
+    message: String,
+  },
+  /// Query is syntactically correct but invalid.
+  Invalid {
+    message: String,
+  },
+  /// Query is invalid because of a configuration issue.
+  Config {
+    message: String,
+  },
+  /// Keyspace or table which a query creates already exists. `table`
+  /// is empty if a keyspace exists.
+  AlreadyExists {
+    message: String,
+    keyspace: String,
+    table: String,
+  },
+  /// Server does not know a prepared query with a given id.
+  Unprepared {
+    message: String,
+    id: Vec<u8>,
+  },
+  /// Response did not arrive in time.
+  Timeout(Duration),
+  /// Connection was closed before a response arrived.
+  ConnectionClosed(String),
+  /// Client could not authenticate, e.g. it does not support
+  /// an authenticator a server requires.
+  Auth(String),
+  /// Frame could not be compressed or decompressed.
+  Compression(String),
+  /// Server responded in a way the protocol does not allow.
+  ProtocolViolation(String),
+  Io(io::Error),
+  /// Any other error, e.g. a value which could not be converted.
+  General(String),
+}
+
+impl Error {
+  /// Code of an error a server responded with.
+  pub fn code(&self) -> Option<i32> {
+    let code = match self {
+      Error::Server { .. } => 0x0000,
+      Error::Protocol { .. } => 0x000A,
+      Error::Authentication { .. } => 0x0100,
+      Error::Unavailable { .. } => 0x1000,
+      Error::Overloaded { .. } => 0x1001,
+      Error::IsBootstrapping { .. } => 0x1002,
+      Error::Truncate { .. } => 0x1003,
+      Error::WriteTimeout { .. } => 0x1100,
+      Error::ReadTimeout { .. } => 0x1200,
+      Error::ReadFailure { .. } => 0x1300,
+      Error::FunctionFailure { .. } => 0x1400,
+      Error::WriteFailure { .. } => 0x1500,
+      Error::Syntax { .. } => 0x2000,
+      Error::Unauthorized { .. } => 0x2100,
+      Error::Invalid { .. } => 0x2200,
+      Error::Config { .. } => 0x2300,
+      Error::AlreadyExists { .. } => 0x2400,
+      Error::Unprepared { .. } => 0x2500,
+      _ => return None,
+    };
+
+    Some(code)
+  }
+
+  /// Message of an error a server responded with.
+  pub fn server_message(&self) -> Option<&str> {
+    match self {
+      Error::Server { message }
+      | Error::Protocol { message }
+      | Error::Authentication { message }
+      | Error::Unavailable { message, .. }
+      | Error::Overloaded { message }
+      | Error::IsBootstrapping { message }
+      | Error::Truncate { message }
+      | Error::WriteTimeout { message, .. }
+      | Error::ReadTimeout { message, .. }
+      | Error::ReadFailure { message, .. }
+      | Error::FunctionFailure { message, .. }
+      | Error::WriteFailure { message, .. }
+      | Error::Syntax { message }
+      | Error::Unauthorized { message }
+      | Error::Invalid { message }
+      | Error::Config { message }
+      | Error::AlreadyExists { message, .. }
+      | Error::Unprepared { message, .. } => Some(message),
+      _ => None,
+    }
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if let (Some(code), Some(message)) = (self.code(), self.server_message()) {
+      return write!(f, "Server error {:#06x}: {}", code, message);
+    }
+
+    match self {
+      Error::Timeout(timeout) => write!(f, "Request timed out after {:?}", timeout),
+      Error::ConnectionClosed(reason) => write!(f, "Connection closed: {}", reason),
+      Error::Auth(reason) => write!(f, "Authentication error: {}", reason),
+      Error::Compression(reason) => write!(f, "Compression error: {}", reason),
+      Error::ProtocolViolation(reason) => write!(f, "Protocol violation: {}", reason),
+      Error::Io(err) => write!(f, "IO error: {}", err),
+      Error::General(reason) => write!(f, "{}", reason),
+      // server errors are written above
+      _ => write!(f, "{:?}", self),
+    }
+  }
+}
+
+impl error::Error for Error {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    match self {
+      Error::Io(err) => Some(err),
+      _ => None,
+    }
+  }
+}
+
+impl From<CDRSError> for Error {
+  fn from(err: CDRSError) -> Self {
+    let message = err.message.into_plain();
+
+    match err.additional_info {
+      AdditionalErrorInfo::Server(_) => Error::Server { message },
+      AdditionalErrorInfo::Protocol(_) => Error::Protocol { message },
+      AdditionalErrorInfo::Authentication(_) => Error::Authentication { message },
+      AdditionalErrorInfo::Unavailable(info) => Error::Unavailable {
+        message,
+        consistency: info.cl,
+        required: info.required,
+        alive: info.alive,
+      },
+      AdditionalErrorInfo::Overloaded(_) => Error::Overloaded { message },
+      AdditionalErrorInfo::IsBootstrapping(_) => Error::IsBootstrapping { message },
+      AdditionalErrorInfo::Truncate(_) => Error::Truncate { message },
+      AdditionalErrorInfo::WriteTimeout(info) => Error::WriteTimeout {
+        message,
+        consistency: info.cl,
+        received: info.received,
+        block_for: info.blockfor,
+        write_type: info.write_type,
+      },
+      AdditionalErrorInfo::ReadTimeout(info) => Error::ReadTimeout {
+        message,
+        data_present: info.replica_has_responded(),
+        consistency: info.cl,
+        received: info.received,
+        block_for: info.blockfor,
+      },
+      AdditionalErrorInfo::ReadFailure(info) => Error::ReadFailure {
+        message,
+        data_present: info.replica_has_responded(),
+        consistency: info.cl,
+        received: info.received,
+        block_for: info.blockfor,
+        num_failures: info.num_failures,
+      },
+      AdditionalErrorInfo::FunctionFailure(info) => Error::FunctionFailure {
+        message,
+        keyspace: info.keyspace.into_plain(),
+        function: info.function.into_plain(),
+        arg_types: info.arg_types.into_plain(),
+      },
+      AdditionalErrorInfo::WriteFailure(info) => Error::WriteFailure {
+        message,
+        consistency: info.cl,
+        received: info.received,
+        block_for: info.blockfor,
+        num_failures: info.num_failures,
+        write_type: info.write_type,
+      },
+      AdditionalErrorInfo::Syntax(_) => Error::Syntax { message },
+      AdditionalErrorInfo::Unauthorized(_) => Error::Unauthorized { message },
+      AdditionalErrorInfo::Invalid(_) => Error::Invalid { message },
+      AdditionalErrorInfo::Config(_) => Error::Config { message },
+      AdditionalErrorInfo::AlreadyExists(info) => Error::AlreadyExists {
+        message,
+        keyspace: info.ks.into_plain(),
+        table: info.table.into_plain(),
+      },
+      AdditionalErrorInfo::Unprepared(info) => Error::Unprepared {
+        message,
+        id: info.id.into_plain().unwrap_or_default(),
+      },
+    }
+  }
+}
+
+/// Errors of the protocol implementation are mostly ones of decoding values.
+impl From<proto::Error> for Error {
+  fn from(err: proto::Error) -> Self {
+    match err {
+      proto::Error::Server(err) => err.into(),
+      proto::Error::Io(err) => Error::Io(err),
+      proto::Error::Compression(reason) => Error::Compression(reason),
+      proto::Error::General(reason) => Error::General(reason),
+      err => Error::General(err.to_string()),
+    }
+  }
+}
+
+impl From<io::Error> for Error {
+  fn from(err: io::Error) -> Self {
+    Error::Io(err)
+  }
+}
+
+impl From<String> for Error {
+  fn from(reason: String) -> Self {
+    Error::General(reason)
+  }
+}
+
+impl From<&str> for Error {
+  fn from(reason: &str) -> Self {
+    Error::General(reason.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use cassandra_proto::frame::FromCursor;
+  use std::io::Cursor;
+
+  /// Body of ERROR frame with a given code, message and payload.
+  fn server_error(code: i32, message: &str, payload: &[u8]) -> CDRSError {
+    let mut bytes = code.to_be_bytes().to_vec();
+    bytes.extend_from_slice(&(message.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(message.as_bytes());
+    bytes.extend_from_slice(payload);
+
+    CDRSError::from_cursor(&mut Cursor::new(&bytes)).unwrap()
+  }
+
+  #[test]
+  fn test_server_errors() {
+    let err = Error::from(server_error(0x2000, "line 1:0 no viable alternative", &[]));
+    assert_eq!(err.code(), Some(0x2000));
+    assert_eq!(
+      err.to_string(),
+      "Server error 0x2000: line 1:0 no viable alternative"
+    );
+    assert!(matches!(err, Error::Syntax { .. }));
+
+    // QUORUM, 2 required, 1 alive
+    let err = Error::from(server_error(
+      0x1000,
+      "unavailable",
+      &[0, 4, 0, 0, 0, 2, 0, 0, 0, 1],
+    ));
+    assert!(matches!(
+      err,
+      Error::Unavailable {
+        consistency: Consistency::Quorum,
+        required: 2,
+        alive: 1,
+        ..
+      }
+    ));
+
+    let err = Error::from(server_error(0x2400, "exists", &[0, 2, b'k', b's', 0, 0]));
+    match err {
+      Error::AlreadyExists {
+        keyspace, table, ..
+      } => assert_eq!((keyspace.as_str(), table.as_str()), ("ks", "")),
+      err => panic!("unexpected error {:?}", err),
+    }
+
+    let err = Error::from(server_error(0x2500, "unprepared", &[0, 2, 1, 2]));
+    assert!(matches!(err, Error::Unprepared { ref id, .. } if id == &[1, 2]));
+  }
+
+  #[test]
+  fn test_client_errors() {
+    let err = Error::Timeout(Duration::from_secs(1));
+    assert_eq!(err.code(), None);
+    assert_eq!(err.to_string(), "Request timed out after 1s");
+
+    let err = Error::from(proto::Error::Compression("bad frame".into()));
+    assert!(matches!(err, Error::Compression(_)));
+    assert_eq!(
+      Error::from(proto::Error::General("oops".into())).to_string(),
+      "oops"
+    );
+  }
+}
@@ -0,0 +1,289 @@
+//! Events which a server pushes to clients that registered for them.
+
+use std::{
+  net::SocketAddr,
+  pin::Pin,
+  task::{Context, Poll},
+};
+
+use cassandra_proto::frame::events as proto;
+use futures::{channel::mpsc, stream::Stream};
+
+/// Kind of server events a client may register for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventType {
+  TopologyChange,
+  StatusChange,
+  SchemaChange,
+}
+
+impl EventType {
+  /// All kinds of server events.
+  pub const ALL: [EventType; 3] = [
+    EventType::TopologyChange,
+    EventType::StatusChange,
+    EventType::SchemaChange,
+  ];
+}
+
+impl From<EventType> for proto::SimpleServerEvent {
+  fn from(event_type: EventType) -> Self {
+    match event_type {
+      EventType::TopologyChange => proto::SimpleServerEvent::TopologyChange,
+      EventType::StatusChange => proto::SimpleServerEvent::StatusChange,
+      EventType::SchemaChange => proto::SimpleServerEvent::SchemaChange,
+    }
+  }
+}
+
+/// Event which a server pushed to a client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerEvent {
+  TopologyChange(TopologyChange),
+  StatusChange(StatusChange),
+  SchemaChange(SchemaChange),
+}
+
+impl ServerEvent {
+  pub fn event_type(&self) -> EventType {
+    match self {
+      ServerEvent::TopologyChange(_) => EventType::TopologyChange,
+      ServerEvent::StatusChange(_) => EventType::StatusChange,
+      ServerEvent::SchemaChange(_) => EventType::SchemaChange,
+    }
+  }
+}
+
+/// A node was added to a cluster or removed from it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopologyChange {
+  pub change_type: TopologyChangeType,
+  pub addr: SocketAddr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopologyChangeType {
+  NewNode,
+  RemovedNode,
+}
+
+/// A node went up or down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusChange {
+  pub change_type: StatusChangeType,
+  pub addr: SocketAddr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusChangeType {
+  Up,
+  Down,
+}
+
+/// A schema element was created, updated or dropped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaChange {
+  pub change_type: SchemaChangeType,
+  pub target: SchemaChangeTarget,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaChangeType {
+  Created,
+  Updated,
+  Dropped,
+}
+
+/// Schema element which was changed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaChangeTarget {
+  Keyspace(String),
+  Table {
+    keyspace: String,
+    table: String,
+  },
+  Type {
+    keyspace: String,
+    name: String,
+  },
+  Function {
+    keyspace: String,
+    name: String,
+    arguments: Vec<String>,
+  },
+  Aggregate {
+    keyspace: String,
+    name: String,
+    arguments: Vec<String>,
+  },
+}
+
+impl SchemaChangeTarget {
+  /// Keyspace which a changed element belongs to.
+  pub fn keyspace(&self) -> &str {
+    match self {
+      SchemaChangeTarget::Keyspace(keyspace)
+      | SchemaChangeTarget::Table { keyspace, .. }
+      | SchemaChangeTarget::Type { keyspace, .. }
+      | SchemaChangeTarget::Function { keyspace, .. }
+      | SchemaChangeTarget::Aggregate { keyspace, .. } => keyspace,
+    }
+  }
+}
+
+impl From<proto::ServerEvent> for ServerEvent {
+  fn from(event: proto::ServerEvent) -> Self {
+    match event {
+      proto::ServerEvent::TopologyChange(change) => ServerEvent::TopologyChange(TopologyChange {
+        change_type: match change.change_type {
+          proto::TopologyChangeType::NewNode => TopologyChangeType::NewNode,
+          proto::TopologyChangeType::RemovedNode => TopologyChangeType::RemovedNode,
+        },
+        addr: change.addr.addr,
+      }),
+      proto::ServerEvent::StatusChange(change) => ServerEvent::StatusChange(StatusChange {
+        change_type: match change.change_type {
+          proto::StatusChangeType::Up => StatusChangeType::Up,
+          proto::StatusChangeType::Down => StatusChangeType::Down,
+        },
+        addr: change.addr.addr,
+      }),
+      proto::ServerEvent::SchemaChange(change) => ServerEvent::SchemaChange(change.into()),
+    }
+  }
+}
+
+/// A schema change is sent both as an event and as a result of a query
+/// which changed a schema.
+impl From<proto::SchemaChange> for SchemaChange {
+  fn from(change: proto::SchemaChange) -> Self {
+    SchemaChange {
+      change_type: match change.change_type {
+        proto::ChangeType::Created => SchemaChangeType::Created,
+        proto::ChangeType::Updated => SchemaChangeType::Updated,
+        proto::ChangeType::Dropped => SchemaChangeType::Dropped,
+      },
+      target: schema_change_target(change.target, change.options),
+    }
+  }
+}
+
+fn schema_change_target(
+  target: proto::Target,
+  options: proto::ChangeSchemeOptions,
+) -> SchemaChangeTarget {
+  use proto::ChangeSchemeOptions::*;
+
+  match (target, options) {
+    (proto::Target::Table, TableType((keyspace, table))) => {
+      SchemaChangeTarget::Table { keyspace, table }
+    }
+    (proto::Target::Type, TableType((keyspace, name))) => {
+      SchemaChangeTarget::Type { keyspace, name }
+    }
+    (proto::Target::Function, FunctionAggregate((keyspace, name, arguments))) => {
+      SchemaChangeTarget::Function {
+        keyspace,
+        name,
+        arguments,
+      }
+    }
+    (proto::Target::Aggregate, FunctionAggregate((keyspace, name, arguments))) => {
+      SchemaChangeTarget::Aggregate {
+        keyspace,
+        name,
+        arguments,
+      }
+    }
+    // a server always sends options which match a target, anything else
+    // could be treated as a change of a whole keyspace
+    (_, Keyspace(keyspace))
+    | (_, TableType((keyspace, _)))
+    | (_, FunctionAggregate((keyspace, _, _))) => SchemaChangeTarget::Keyspace(keyspace),
+  }
+}
+
+/// Stream of server events of types which were registered.
+/// Events are registered for again when a connection they are received by
+/// is closed, so it ends only when a session is dropped or cannot reconnect.
+pub struct EventStream {
+  event_types: Vec<EventType>,
+  events: mpsc::UnboundedReceiver<ServerEvent>,
+}
+
+impl EventStream {
+  pub(crate) fn new(
+    event_types: Vec<EventType>,
+    events: mpsc::UnboundedReceiver<ServerEvent>,
+  ) -> Self {
+    EventStream {
+      event_types,
+      events,
+    }
+  }
+}
+
+impl Stream for EventStream {
+  type Item = ServerEvent;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+    loop {
+      match Pin::new(&mut self.events).poll_next(cx) {
+        // other event types could be registered over the same connection
+        Poll::Ready(Some(event)) if !self.event_types.contains(&event.event_type()) => continue,
+        poll => return poll,
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use futures::{executor::block_on, stream::StreamExt};
+
+  #[test]
+  fn test_schema_change_target() {
+    let target = schema_change_target(
+      proto::Target::Table,
+      proto::ChangeSchemeOptions::TableType(("ks".into(), "tbl".into())),
+    );
+    assert_eq!(
+      target,
+      SchemaChangeTarget::Table {
+        keyspace: "ks".into(),
+        table: "tbl".into()
+      }
+    );
+    assert_eq!(target.keyspace(), "ks");
+
+    let target = schema_change_target(
+      proto::Target::Keyspace,
+      proto::ChangeSchemeOptions::Keyspace("ks".into()),
+    );
+    assert_eq!(target, SchemaChangeTarget::Keyspace("ks".into()));
+  }
+
+  #[test]
+  fn test_event_stream_filters_event_types() {
+    let (sender, receiver) = mpsc::unbounded();
+    let stream = EventStream::new(vec![EventType::StatusChange], receiver);
+    let addr: SocketAddr = "127.0.0.1:9042".parse().unwrap();
+    let status = ServerEvent::StatusChange(StatusChange {
+      change_type: StatusChangeType::Down,
+      addr,
+    });
+
+    sender
+      .unbounded_send(ServerEvent::TopologyChange(TopologyChange {
+        change_type: TopologyChangeType::NewNode,
+        addr,
+      }))
+      .unwrap();
+    sender.unbounded_send(status.clone()).unwrap();
+    drop(sender);
+
+    let events: Vec<ServerEvent> = block_on(stream.collect());
+    assert_eq!(events, vec![status]);
+  }
+}
@@ -8,13 +8,14 @@ use async_std::{
   io::{IoSlice, Write},
   prelude::*,
 };
-use cassandra_proto::frame::{parser_async::parse_frame_async, Frame, IntoBytes};
+use cassandra_proto::frame::{parser_async::parse_frame_async, Frame, IntoBytes, LENGTH_LEN};
 use futures::{sink::Sink, stream::Stream};
 use log::error;
 
 use crate::{compressor::Compression, transport::CDRSTransport};
 
-const READING_BUFFER_SIZE: usize = 1_000;
+const READING_BUFFER_SIZE: usize = 8_000;
+const HEADER_SIZE: usize = 9;
 
 /// Async channel that enable frame exchange with DB server.
 pub struct FrameChannel<T> {
@@ -35,6 +36,20 @@ impl<T> FrameChannel<T> {
       is_terminated: false,
     }
   }
+
+  /// Checks whether the receiving buffer contains a whole frame, so frames
+  /// are not re-parsed every time a next chunk of a big body arrives.
+  fn has_complete_frame(&self) -> bool {
+    if self.receving_buffer.len() < HEADER_SIZE {
+      return false;
+    }
+
+    let length = self.receving_buffer[HEADER_SIZE - LENGTH_LEN..HEADER_SIZE]
+      .iter()
+      .fold(0usize, |acc, byte| (acc << 8) | *byte as usize);
+
+    self.receving_buffer.len() >= HEADER_SIZE + length
+  }
 }
 
 impl<T: CDRSTransport> Sink<Frame> for FrameChannel<T> {
@@ -101,49 +116,52 @@ impl<T: CDRSTransport> Stream for FrameChannel<T> {
   type Item = Frame;
 
   fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
-    let compressor = self.compressor;
-    let transport = Pin::new(&mut self.transport);
-    let mut buffer_slice = [0u8; READING_BUFFER_SIZE];
+    if self.is_terminated {
+      return Poll::Ready(None);
+    }
 
-    match transport.poll_read(cx, &mut buffer_slice) {
-      Poll::Ready(result) => match result {
-        Ok(n) => {
-          self.receving_buffer.extend_from_slice(&buffer_slice[0..n]);
-          if n == READING_BUFFER_SIZE || n == 0 {
-            return Poll::Pending;
-          } else {
-            // n < READING_BUFFER_SIZE means the function can proceed further
+    loop {
+      // the receiving buffer may already contain a frame or even several ones
+      if self.has_complete_frame() {
+        let compressor = self.compressor;
+        let mut buffer_cursor = io::Cursor::new(&mut self.receving_buffer);
+
+        match parse_frame_async(&mut buffer_cursor, &compressor) {
+          Err(err) => {
+            error!("CDRS frame_channel: parse frame error {:?}", err);
+            self.is_terminated = true;
+            return Poll::Ready(None);
+          }
+          Ok(Some(frame)) => {
+            let cursor_position = buffer_cursor.position();
+            self.receving_buffer = buffer_cursor
+              .into_inner()
+              .split_off(cursor_position as usize);
+
+            return Poll::Ready(Some(frame));
           }
+          Ok(None) => {}
+        }
+      }
+
+      let mut buffer_slice = [0u8; READING_BUFFER_SIZE];
+      match Pin::new(&mut self.transport).poll_read(cx, &mut buffer_slice) {
+        Poll::Ready(Ok(0)) => {
+          // transport has reached EOF
+          self.is_terminated = true;
+          return Poll::Ready(None);
+        }
+        Poll::Ready(Ok(n)) => {
+          self.receving_buffer.extend_from_slice(&buffer_slice[0..n]);
         }
-        Err(err) => {
+        Poll::Ready(Err(err)) => {
           error!("CDRS frame_channel: {:?}", err);
           self.is_terminated = true;
           return Poll::Ready(None);
         }
-      },
-      Poll::Pending => {
-        return Poll::Pending;
-      }
-    }
-
-    let mut buffer_cursor = io::Cursor::new(&mut self.receving_buffer);
-
-    match parse_frame_async(&mut buffer_cursor, &compressor) {
-      Err(err) => {
-        error!("CDRS frame_channel: parse frame error {:?}", err);
-        self.is_terminated = true;
-        return Poll::Ready(None);
-      }
-      Ok(Some(frame)) => {
-        let cursor_position = buffer_cursor.position();
-        self.receving_buffer = buffer_cursor
-          .into_inner()
-          .split_off(cursor_position as usize);
-
-        return Poll::Ready(Some(frame));
-      }
-      Ok(None) => {
-        return Poll::Pending;
+        Poll::Pending => {
+          return Poll::Pending;
+        }
       }
     }
   }
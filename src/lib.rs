@@ -1,29 +1,58 @@
 extern crate async_std;
 extern crate async_tls;
 extern crate async_trait;
+extern crate base64;
 extern crate cassandra_proto;
+extern crate cdrs_async_derive;
 extern crate futures;
 extern crate log;
 extern crate lz4_compress;
+extern crate md5;
+extern crate ring;
 extern crate snap;
+extern crate time;
+extern crate uuid;
+
+// allows derived code to refer to the crate by name inside of it
+extern crate self as cdrs_async;
 
 pub mod authenticators;
+pub mod cluster;
+pub mod error;
+pub mod events;
+pub mod load_balancing;
+pub mod metadata;
 pub mod query;
+pub mod reconnection;
+pub mod retry;
+pub mod row;
+pub mod scan;
+pub mod token;
+pub mod types;
+pub mod values;
 
 pub(crate) mod frame_channel;
 
 mod compressor;
+mod connection;
 mod pager;
+mod prepared_cache;
+mod schema_agreement;
 mod session;
+mod session_config;
 mod transport;
 mod transport_tcp;
 mod transport_tls;
 mod utils;
 
 pub use cassandra_proto::compression::Compressor;
+pub use cdrs_async_derive::{FromRow, FromUdt, IntoQueryValues, IntoUdt};
 pub use compressor::Compression;
-pub use pager::PageSize;
+pub use pager::{PageSize, PagerState, RowStream, SessionPager};
+pub use row::FromRow;
 pub use session::Session;
+pub use session_config::SessionConfig;
 pub use transport::CDRSTransport;
 pub use transport_tcp::TransportTcp;
 pub use transport_tls::TransportTls;
+pub use types::{CqlTuple, FromCql};
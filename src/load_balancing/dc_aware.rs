@@ -0,0 +1,118 @@
+use std::{
+  collections::BTreeMap,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  },
+};
+
+use super::{rotate, LoadBalancingPolicy, RoutingInfo};
+use crate::cluster::{Node, TokenMap};
+
+/// Round-robin policy which prefers nodes of a local data center.
+/// Nodes of remote data centers are used only as a fallback and only
+/// if it is enabled via `remote_nodes_per_dc`.
+#[derive(Debug)]
+pub struct DcAwareRoundRobin {
+  local_dc: String,
+  remote_nodes_per_dc: usize,
+  next: AtomicUsize,
+}
+
+impl DcAwareRoundRobin {
+  /// Creates a policy which sends requests to nodes of a given data center only.
+  pub fn new<D: ToString>(local_dc: D) -> Self {
+    DcAwareRoundRobin {
+      local_dc: local_dc.to_string(),
+      remote_nodes_per_dc: 0,
+      next: AtomicUsize::new(0),
+    }
+  }
+
+  /// Sets a number of nodes of each remote data center which are tried
+  /// after all local ones. Nodes with unknown data center are considered
+  /// remote ones.
+  pub fn remote_nodes_per_dc(mut self, remote_nodes_per_dc: usize) -> Self {
+    self.remote_nodes_per_dc = remote_nodes_per_dc;
+
+    self
+  }
+}
+
+impl LoadBalancingPolicy for DcAwareRoundRobin {
+  fn query_plan(
+    &self,
+    _routing: &RoutingInfo,
+    nodes: &[Arc<Node>],
+    _token_map: &TokenMap,
+  ) -> Vec<Arc<Node>> {
+    let offset = self.next.fetch_add(1, Ordering::Relaxed);
+    let mut local = vec![];
+    let mut remote: BTreeMap<Option<&str>, Vec<Arc<Node>>> = BTreeMap::new();
+
+    for node in nodes {
+      if node.datacenter() == Some(self.local_dc.as_str()) {
+        local.push(node.clone());
+      } else if self.remote_nodes_per_dc > 0 {
+        remote
+          .entry(node.datacenter())
+          .or_default()
+          .push(node.clone());
+      }
+    }
+
+    let mut plan = rotate(&local, offset);
+    for dc_nodes in remote.values() {
+      plan.extend(
+        rotate(dc_nodes, offset)
+          .into_iter()
+          .take(self.remote_nodes_per_dc),
+      );
+    }
+
+    plan
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::load_balancing::{addrs, test_nodes};
+
+  #[test]
+  fn test_dc_aware_uses_local_nodes_only() {
+    let nodes = test_nodes(&[("a", "dc1", &[]), ("b", "dc2", &[]), ("c", "dc1", &[])]);
+    let policy = DcAwareRoundRobin::new("dc1");
+    let routing = RoutingInfo::default();
+
+    assert_eq!(
+      addrs(&policy.query_plan(&routing, &nodes, &TokenMap::default())),
+      vec!["a", "c"]
+    );
+    assert_eq!(
+      addrs(&policy.query_plan(&routing, &nodes, &TokenMap::default())),
+      vec!["c", "a"]
+    );
+  }
+
+  #[test]
+  fn test_dc_aware_falls_back_to_remote_nodes() {
+    let nodes = test_nodes(&[
+      ("a", "dc1", &[]),
+      ("b", "dc2", &[]),
+      ("c", "dc2", &[]),
+      ("d", "dc3", &[]),
+    ]);
+    let policy = DcAwareRoundRobin::new("dc1").remote_nodes_per_dc(1);
+    let routing = RoutingInfo::default();
+
+    assert_eq!(
+      addrs(&policy.query_plan(&routing, &nodes, &TokenMap::default())),
+      vec!["a", "b", "d"]
+    );
+    assert_eq!(
+      addrs(&policy.query_plan(&routing, &nodes, &TokenMap::default())),
+      vec!["a", "c", "d"]
+    );
+  }
+}
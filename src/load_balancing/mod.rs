@@ -0,0 +1,116 @@
+//! Load balancing policies decide which cluster nodes should serve
+//! a request and in which order they are tried.
+
+mod dc_aware;
+mod round_robin;
+mod token_aware;
+
+use std::sync::Arc;
+
+use crate::{
+  cluster::{Node, TokenMap},
+  token::{PartitionToken, Partitioner},
+};
+
+pub use dc_aware::DcAwareRoundRobin;
+pub use round_robin::RoundRobin;
+pub use token_aware::TokenAware;
+
+/// Information about a request which policies may use for routing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoutingInfo {
+  token: Option<PartitionToken>,
+  routing_key: Option<Vec<u8>>,
+}
+
+impl RoutingInfo {
+  /// Routing information of a request which targets a partition
+  /// with a given token.
+  pub fn with_token<T: Into<PartitionToken>>(token: T) -> Self {
+    RoutingInfo {
+      token: Some(token.into()),
+      routing_key: None,
+    }
+  }
+
+  /// Routing information of a request which targets a partition
+  /// with a given serialized partition key. A token of the partition
+  /// is computed by a partitioner of a cluster.
+  pub fn with_routing_key(routing_key: &[u8]) -> Self {
+    RoutingInfo {
+      token: None,
+      routing_key: Some(routing_key.to_vec()),
+    }
+  }
+
+  /// Token of a partition targeted by a request if it is known.
+  /// It is computed from a routing key by a given partitioner,
+  /// which is usually one of `TokenMap`.
+  pub fn token(&self, partitioner: Partitioner) -> Option<PartitionToken> {
+    match (&self.token, &self.routing_key) {
+      (Some(token), _) => Some(token.clone()),
+      (None, Some(routing_key)) => Some(partitioner.token(routing_key)),
+      (None, None) => None,
+    }
+  }
+}
+
+/// Load balancing policy builds a query plan for each request.
+pub trait LoadBalancingPolicy: Send + Sync + 'static {
+  /// Returns nodes in the order they should be tried to serve a request.
+  /// Nodes which are not returned are not used for a request at all.
+  /// A ring of tokens of given nodes is built once each time a topology
+  /// of a cluster changes, so it is cheap to look up replicas in it.
+  fn query_plan(
+    &self,
+    routing: &RoutingInfo,
+    nodes: &[Arc<Node>],
+    token_map: &TokenMap,
+  ) -> Vec<Arc<Node>>;
+}
+
+/// Returns nodes starting from one with a given offset.
+pub(crate) fn rotate<T: Clone>(items: &[T], offset: usize) -> Vec<T> {
+  if items.is_empty() {
+    return vec![];
+  }
+
+  let offset = offset % items.len();
+  items[offset..]
+    .iter()
+    .chain(items[..offset].iter())
+    .cloned()
+    .collect()
+}
+
+#[cfg(test)]
+pub(crate) fn test_nodes(nodes: &[(&str, &str, &[i64])]) -> Vec<Arc<Node>> {
+  nodes
+    .iter()
+    .map(|(addr, dc, tokens)| {
+      Arc::new(Node::new(
+        addr,
+        Some(dc.to_string()),
+        None,
+        tokens.iter().cloned().map(crate::token::Token).collect(),
+      ))
+    })
+    .collect()
+}
+
+#[cfg(test)]
+pub(crate) fn addrs(nodes: &[Arc<Node>]) -> Vec<&str> {
+  nodes.iter().map(|node| node.addr()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_rotate() {
+    assert_eq!(rotate(&[1, 2, 3], 0), vec![1, 2, 3]);
+    assert_eq!(rotate(&[1, 2, 3], 4), vec![2, 3, 1]);
+    assert!(rotate::<u8>(&[], 1).is_empty());
+  }
+}
@@ -0,0 +1,56 @@
+use std::sync::{
+  atomic::{AtomicUsize, Ordering},
+  Arc,
+};
+
+use super::{rotate, LoadBalancingPolicy, RoutingInfo};
+use crate::cluster::{Node, TokenMap};
+
+/// Policy which dispatches requests across all nodes one by one.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+  next: AtomicUsize,
+}
+
+impl RoundRobin {
+  pub fn new() -> Self {
+    Default::default()
+  }
+}
+
+impl LoadBalancingPolicy for RoundRobin {
+  fn query_plan(
+    &self,
+    _routing: &RoutingInfo,
+    nodes: &[Arc<Node>],
+    _token_map: &TokenMap,
+  ) -> Vec<Arc<Node>> {
+    rotate(nodes, self.next.fetch_add(1, Ordering::Relaxed))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::load_balancing::{addrs, test_nodes};
+
+  #[test]
+  fn test_round_robin_rotates_nodes() {
+    let nodes = test_nodes(&[("a", "dc1", &[]), ("b", "dc1", &[]), ("c", "dc2", &[])]);
+    let policy = RoundRobin::new();
+    let routing = RoutingInfo::default();
+
+    assert_eq!(
+      addrs(&policy.query_plan(&routing, &nodes, &TokenMap::default())),
+      vec!["a", "b", "c"]
+    );
+    assert_eq!(
+      addrs(&policy.query_plan(&routing, &nodes, &TokenMap::default())),
+      vec!["b", "c", "a"]
+    );
+    assert_eq!(
+      addrs(&policy.query_plan(&routing, &nodes, &TokenMap::default())),
+      vec!["c", "a", "b"]
+    );
+  }
+}
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use super::{LoadBalancingPolicy, RoutingInfo};
+use crate::cluster::{Node, ReplicationStrategy, TokenMap};
+
+/// Policy which sends a request to a node which owns a requested partition
+/// first. Other nodes are tried in the order a child policy returns them.
+/// If a token of a request is not known the child policy is used as is.
+///
+/// If a replication strategy of a keyspace is given, all replicas
+/// of a partition are tried first in the order a child policy returns them.
+#[derive(Debug)]
+pub struct TokenAware<P> {
+  child: P,
+  replication: Option<ReplicationStrategy>,
+}
+
+impl<P: LoadBalancingPolicy> TokenAware<P> {
+  pub fn new(child: P) -> Self {
+    TokenAware {
+      child,
+      replication: None,
+    }
+  }
+
+  /// Sets a replication strategy of a keyspace requests are sent to.
+  pub fn replication(mut self, replication: ReplicationStrategy) -> Self {
+    self.replication = Some(replication);
+
+    self
+  }
+}
+
+impl<P: LoadBalancingPolicy> LoadBalancingPolicy for TokenAware<P> {
+  fn query_plan(
+    &self,
+    routing: &RoutingInfo,
+    nodes: &[Arc<Node>],
+    token_map: &TokenMap,
+  ) -> Vec<Arc<Node>> {
+    let plan = self.child.query_plan(routing, nodes, token_map);
+    let token = match routing.token(token_map.partitioner()) {
+      Some(token) => token,
+      None => return plan,
+    };
+
+    let replicas: Vec<Arc<Node>> = match &self.replication {
+      Some(replication) => token_map.replicas(token, replication),
+      None => token_map.owner(token).cloned().into_iter().collect(),
+    };
+    let is_replica = |node: &Arc<Node>| replicas.iter().any(|replica| Arc::ptr_eq(replica, node));
+
+    let (mut replicas, others): (Vec<_>, Vec<_>) = plan.into_iter().partition(is_replica);
+    replicas.extend(others);
+    replicas
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    load_balancing::{addrs, test_nodes, DcAwareRoundRobin, RoundRobin},
+    token::Token,
+  };
+
+  #[test]
+  fn test_token_aware_puts_owner_first() {
+    let nodes = test_nodes(&[("a", "dc1", &[-100, 0]), ("b", "dc1", &[-50, 50])]);
+    let token_map = TokenMap::new(&nodes);
+    let policy = TokenAware::new(RoundRobin::new());
+
+    let plan = policy.query_plan(&RoutingInfo::with_token(Token(-60)), &nodes, &token_map);
+    assert_eq!(addrs(&plan), vec!["b", "a"]);
+    let plan = policy.query_plan(&RoutingInfo::with_token(Token(0)), &nodes, &token_map);
+    assert_eq!(addrs(&plan), vec!["a", "b"]);
+    let plan = policy.query_plan(&RoutingInfo::with_token(Token(10)), &nodes, &token_map);
+    assert_eq!(addrs(&plan), vec!["b", "a"]);
+  }
+
+  #[test]
+  fn test_token_aware_wraps_around_ring() {
+    let nodes = test_nodes(&[("a", "dc1", &[-100, 0]), ("b", "dc1", &[-50, 50])]);
+    let token_map = TokenMap::new(&nodes);
+    let policy = TokenAware::new(RoundRobin::new());
+
+    let plan = policy.query_plan(&RoutingInfo::with_token(Token(51)), &nodes, &token_map);
+    assert_eq!(addrs(&plan), vec!["a", "b"]);
+  }
+
+  #[test]
+  fn test_token_aware_without_token() {
+    let nodes = test_nodes(&[("a", "dc1", &[0]), ("b", "dc1", &[50])]);
+    let token_map = TokenMap::new(&nodes);
+    let policy = TokenAware::new(RoundRobin::new());
+
+    assert_eq!(
+      addrs(&policy.query_plan(&RoutingInfo::default(), &nodes, &token_map)),
+      vec!["a", "b"]
+    );
+    assert_eq!(
+      addrs(&policy.query_plan(&RoutingInfo::default(), &nodes, &token_map)),
+      vec!["b", "a"]
+    );
+  }
+
+  #[test]
+  fn test_token_aware_respects_child_plan() {
+    let nodes = test_nodes(&[("a", "dc1", &[0]), ("b", "dc2", &[50])]);
+    let token_map = TokenMap::new(&nodes);
+    let policy = TokenAware::new(DcAwareRoundRobin::new("dc1"));
+
+    let plan = policy.query_plan(&RoutingInfo::with_token(Token(10)), &nodes, &token_map);
+    assert_eq!(addrs(&plan), vec!["a"]);
+  }
+
+  #[test]
+  fn test_token_aware_puts_replicas_first() {
+    let nodes = test_nodes(&[
+      ("a", "dc1", &[0]),
+      ("b", "dc1", &[10]),
+      ("c", "dc1", &[20]),
+      ("d", "dc1", &[30]),
+    ]);
+    let token_map = TokenMap::new(&nodes);
+    let policy = TokenAware::new(RoundRobin::new()).replication(ReplicationStrategy::Simple {
+      replication_factor: 2,
+    });
+
+    let plan = policy.query_plan(&RoutingInfo::with_token(Token(15)), &nodes, &token_map);
+    assert_eq!(addrs(&plan), vec!["c", "d", "a", "b"]);
+    let plan = policy.query_plan(&RoutingInfo::with_token(Token(15)), &nodes, &token_map);
+    assert_eq!(addrs(&plan), vec!["c", "d", "b", "a"]);
+  }
+}
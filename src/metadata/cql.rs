@@ -0,0 +1,602 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use super::schema::{
+  AggregateMetadata, ClusteringOrder, ColumnKind, ColumnMetadata, FunctionMetadata, IndexMetadata,
+  KeyspaceMetadata, OptionValue, TableMetadata, TableOptions, UserTypeMetadata, ViewMetadata,
+};
+
+/// Keywords which could not be used as identifiers without quotes.
+const RESERVED_KEYWORDS: &[&str] = &[
+  "add",
+  "allow",
+  "alter",
+  "and",
+  "apply",
+  "asc",
+  "authorize",
+  "batch",
+  "begin",
+  "by",
+  "columnfamily",
+  "create",
+  "default",
+  "delete",
+  "desc",
+  "describe",
+  "drop",
+  "entries",
+  "execute",
+  "from",
+  "full",
+  "grant",
+  "if",
+  "in",
+  "index",
+  "infinity",
+  "insert",
+  "into",
+  "is",
+  "keyspace",
+  "limit",
+  "materialized",
+  "mbean",
+  "mbeans",
+  "modify",
+  "nan",
+  "norecursive",
+  "not",
+  "null",
+  "of",
+  "on",
+  "or",
+  "order",
+  "primary",
+  "rename",
+  "replace",
+  "revoke",
+  "schema",
+  "select",
+  "set",
+  "table",
+  "to",
+  "token",
+  "truncate",
+  "unlogged",
+  "unset",
+  "update",
+  "use",
+  "using",
+  "view",
+  "where",
+  "with",
+];
+
+/// Quotes an identifier unless it is a lowercase one which is not
+/// a reserved keyword.
+pub fn quote_identifier(name: &str) -> String {
+  let is_plain = name
+    .chars()
+    .next()
+    .map(|c| c.is_ascii_lowercase())
+    .unwrap_or(false)
+    && name
+      .chars()
+      .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    && !RESERVED_KEYWORDS.contains(&name);
+
+  if is_plain {
+    name.to_string()
+  } else {
+    format!("\"{}\"", name.replace('"', "\"\""))
+  }
+}
+
+fn quote_string(value: &str) -> String {
+  format!("'{}'", value.replace('\'', "''"))
+}
+
+fn qualified_name(keyspace: &str, name: &str) -> String {
+  format!("{}.{}", quote_identifier(keyspace), quote_identifier(name))
+}
+
+fn map_literal(map: &BTreeMap<String, String>) -> String {
+  let entries: Vec<String> = map
+    .iter()
+    .map(|(key, value)| format!("{}: {}", quote_string(key), quote_string(value)))
+    .collect();
+
+  format!("{{{}}}", entries.join(", "))
+}
+
+impl OptionValue {
+  /// Renders a value as a CQL literal.
+  pub fn to_cql(&self) -> String {
+    match self {
+      OptionValue::Boolean(value) => value.to_string(),
+      OptionValue::Int(value) => value.to_string(),
+      OptionValue::Double(value) => value.to_string(),
+      OptionValue::Text(value) => quote_string(value),
+      OptionValue::Map(value) => map_literal(value),
+    }
+  }
+}
+
+impl KeyspaceMetadata {
+  /// Renders `CREATE KEYSPACE` statement.
+  pub fn to_cql(&self) -> String {
+    format!(
+      "CREATE KEYSPACE {} WITH replication = {} AND durable_writes = {};",
+      quote_identifier(&self.name),
+      map_literal(&self.replication),
+      self.durable_writes
+    )
+  }
+
+  /// Renders statements which create the keyspace and all its elements,
+  /// so that each element goes after ones it could depend on.
+  pub fn to_full_cql(&self) -> String {
+    let mut statements = vec![self.to_cql()];
+    statements.extend(self.sorted_user_types().iter().map(|t| t.to_cql()));
+    statements.extend(self.functions.iter().map(FunctionMetadata::to_cql));
+    statements.extend(self.aggregates.iter().map(AggregateMetadata::to_cql));
+    for table in self.tables.values() {
+      statements.push(table.to_cql());
+      statements.extend(table.indexes.iter().map(|index| table.index_cql(index)));
+    }
+    statements.extend(self.views.values().map(ViewMetadata::to_cql));
+
+    statements.join("\n\n")
+  }
+
+  /// Sorts types so that types which are used by fields of other ones
+  /// go first.
+  fn sorted_user_types(&self) -> Vec<&UserTypeMetadata> {
+    let mut remaining: Vec<&UserTypeMetadata> = self.user_types.values().collect();
+    let mut sorted: Vec<&UserTypeMetadata> = vec![];
+
+    while !remaining.is_empty() {
+      let position = remaining
+        .iter()
+        .position(|user_type| {
+          remaining
+            .iter()
+            .all(|other| other.name == user_type.name || !user_type.uses_type(&other.name))
+        })
+        // types could not depend on each other cyclically
+        .unwrap_or(0);
+      sorted.push(remaining.remove(position));
+    }
+
+    sorted
+  }
+}
+
+/// Checks that a CQL type refers to a type with a given name.
+fn type_uses(cql_type: &str, name: &str) -> bool {
+  let quoted = quote_identifier(name);
+  cql_type
+    .split(|c: char| c == '<' || c == '>' || c == ',' || c.is_whitespace())
+    .any(|part| part == name || part == quoted)
+}
+
+impl UserTypeMetadata {
+  fn uses_type(&self, name: &str) -> bool {
+    self
+      .fields
+      .iter()
+      .any(|(_, cql_type)| type_uses(cql_type, name))
+  }
+
+  /// Renders `CREATE TYPE` statement.
+  pub fn to_cql(&self) -> String {
+    let fields: Vec<String> = self
+      .fields
+      .iter()
+      .map(|(name, cql_type)| format!("    {} {}", quote_identifier(name), cql_type))
+      .collect();
+
+    format!(
+      "CREATE TYPE {} (\n{}\n);",
+      qualified_name(&self.keyspace, &self.name),
+      fields.join(",\n")
+    )
+  }
+}
+
+/// Renders `PRIMARY KEY` of a table or a view.
+fn primary_key(columns: &[ColumnMetadata]) -> String {
+  let names = |kind| -> Vec<String> {
+    columns
+      .iter()
+      .filter(|column| column.kind == kind)
+      .map(|column| quote_identifier(&column.name))
+      .collect()
+  };
+  let partition_key = names(ColumnKind::PartitionKey);
+  let clustering = names(ColumnKind::Clustering);
+
+  let mut key = if partition_key.len() == 1 {
+    partition_key[0].clone()
+  } else {
+    format!("({})", partition_key.join(", "))
+  };
+  for column in clustering {
+    key.push_str(", ");
+    key.push_str(&column);
+  }
+
+  format!("PRIMARY KEY ({})", key)
+}
+
+/// Renders `WITH` clause of a table or a view if it has any options.
+fn with_options(columns: &[ColumnMetadata], options: &TableOptions) -> Option<String> {
+  let mut clauses = vec![];
+
+  let clustering: Vec<String> = columns
+    .iter()
+    .filter(|column| column.kind == ColumnKind::Clustering)
+    .map(|column| {
+      let order = match column.clustering_order {
+        ClusteringOrder::Desc => "DESC",
+        _ => "ASC",
+      };
+      format!("{} {}", quote_identifier(&column.name), order)
+    })
+    .collect();
+  if !clustering.is_empty() {
+    clauses.push(format!("CLUSTERING ORDER BY ({})", clustering.join(", ")));
+  }
+  clauses.extend(
+    options
+      .iter()
+      .map(|(name, value)| format!("{} = {}", name, value.to_cql())),
+  );
+
+  if clauses.is_empty() {
+    None
+  } else {
+    Some(format!("WITH {}", clauses.join("\n    AND ")))
+  }
+}
+
+impl TableMetadata {
+  /// Renders `CREATE TABLE` statement. Indexes of the table are rendered
+  /// by `index_cql`.
+  pub fn to_cql(&self) -> String {
+    let mut cql = format!(
+      "CREATE TABLE {} (\n",
+      qualified_name(&self.keyspace, &self.name)
+    );
+    for column in &self.columns {
+      let _ = writeln!(
+        cql,
+        "    {} {}{},",
+        quote_identifier(&column.name),
+        column.cql_type,
+        if column.kind == ColumnKind::Static {
+          " static"
+        } else {
+          ""
+        }
+      );
+    }
+    let _ = write!(
+      cql,
+      "    {}\n){};",
+      primary_key(&self.columns),
+      with_options(&self.columns, &self.options)
+        .map(|options| format!(" {}", options))
+        .unwrap_or_default()
+    );
+
+    cql
+  }
+
+  /// Renders `CREATE INDEX` statement of an index of the table.
+  pub fn index_cql(&self, index: &IndexMetadata) -> String {
+    let table = qualified_name(&self.keyspace, &self.name);
+    let target = index.target().unwrap_or_default();
+
+    match index.class_name() {
+      Some(class_name) if index.kind == "CUSTOM" => {
+        let mut options = index.options.clone();
+        options.remove("target");
+        options.remove("class_name");
+
+        let mut cql = format!(
+          "CREATE CUSTOM INDEX {} ON {} ({}) USING {}",
+          quote_identifier(&index.name),
+          table,
+          target,
+          quote_string(class_name)
+        );
+        if !options.is_empty() {
+          let _ = write!(cql, " WITH OPTIONS = {}", map_literal(&options));
+        }
+        cql.push(';');
+        cql
+      }
+      _ => format!(
+        "CREATE INDEX {} ON {} ({});",
+        quote_identifier(&index.name),
+        table,
+        target
+      ),
+    }
+  }
+}
+
+impl ViewMetadata {
+  /// Renders `CREATE MATERIALIZED VIEW` statement.
+  pub fn to_cql(&self) -> String {
+    let columns = if self.include_all_columns {
+      "*".to_string()
+    } else {
+      self
+        .columns
+        .iter()
+        .map(|column| quote_identifier(&column.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+    };
+
+    format!(
+      "CREATE MATERIALIZED VIEW {} AS\n    SELECT {}\n    FROM {}\n    WHERE {}\n    {}{};",
+      qualified_name(&self.keyspace, &self.name),
+      columns,
+      qualified_name(&self.keyspace, &self.base_table),
+      self.where_clause,
+      primary_key(&self.columns),
+      with_options(&self.columns, &self.options)
+        .map(|options| format!("\n{}", options))
+        .unwrap_or_default()
+    )
+  }
+}
+
+impl FunctionMetadata {
+  /// Renders `CREATE FUNCTION` statement.
+  pub fn to_cql(&self) -> String {
+    let arguments: Vec<String> = self
+      .arguments
+      .iter()
+      .map(|(name, cql_type)| format!("{} {}", quote_identifier(name), cql_type))
+      .collect();
+    let body = if self.body.contains("$$") {
+      quote_string(&self.body)
+    } else {
+      format!("$${}$$", self.body)
+    };
+
+    format!(
+      "CREATE FUNCTION {}({})\n    {} ON NULL INPUT\n    RETURNS {}\n    LANGUAGE {}\n    AS {};",
+      qualified_name(&self.keyspace, &self.name),
+      arguments.join(", "),
+      if self.called_on_null_input {
+        "CALLED"
+      } else {
+        "RETURNS NULL"
+      },
+      self.return_type,
+      self.language,
+      body
+    )
+  }
+}
+
+impl AggregateMetadata {
+  /// Renders `CREATE AGGREGATE` statement.
+  pub fn to_cql(&self) -> String {
+    let mut cql = format!(
+      "CREATE AGGREGATE {}({})\n    SFUNC {}\n    STYPE {}",
+      qualified_name(&self.keyspace, &self.name),
+      self.argument_types.join(", "),
+      quote_identifier(&self.state_func),
+      self.state_type
+    );
+    if let Some(final_func) = &self.final_func {
+      let _ = write!(cql, "\n    FINALFUNC {}", quote_identifier(final_func));
+    }
+    if let Some(initcond) = &self.initcond {
+      let _ = write!(cql, "\n    INITCOND {}", initcond);
+    }
+    cql.push(';');
+
+    cql
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn column(name: &str, kind: ColumnKind, cql_type: &str) -> ColumnMetadata {
+    ColumnMetadata {
+      name: name.into(),
+      kind,
+      position: 0,
+      cql_type: cql_type.into(),
+      clustering_order: ClusteringOrder::None,
+    }
+  }
+
+  fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+    entries
+      .iter()
+      .map(|(key, value)| (key.to_string(), value.to_string()))
+      .collect()
+  }
+
+  fn table() -> TableMetadata {
+    let mut created = column("created", ColumnKind::Clustering, "timestamp");
+    created.clustering_order = ClusteringOrder::Desc;
+    let mut options = TableOptions::new();
+    options.insert("comment".into(), OptionValue::Text("user's events".into()));
+    options.insert(
+      "compaction".into(),
+      OptionValue::Map(map(&[("class", "LeveledCompactionStrategy")])),
+    );
+    options.insert("gc_grace_seconds".into(), OptionValue::Int(864000));
+
+    TableMetadata {
+      keyspace: "ks".into(),
+      name: "events".into(),
+      columns: vec![
+        column("user", ColumnKind::PartitionKey, "uuid"),
+        column("day", ColumnKind::PartitionKey, "date"),
+        created,
+        column("owner", ColumnKind::Static, "text"),
+        column("Payload", ColumnKind::Regular, "frozen<list<int>>"),
+      ],
+      options,
+      indexes: vec![IndexMetadata {
+        name: "events_payload".into(),
+        kind: "COMPOSITES".into(),
+        options: map(&[("target", "\"Payload\"")]),
+      }],
+    }
+  }
+
+  #[test]
+  fn test_quote_identifier() {
+    assert_eq!(quote_identifier("events"), "events");
+    assert_eq!(quote_identifier("user_2"), "user_2");
+    assert_eq!(quote_identifier("Events"), "\"Events\"");
+    assert_eq!(quote_identifier("select"), "\"select\"");
+    assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+    assert_eq!(quote_identifier("2a"), "\"2a\"");
+  }
+
+  #[test]
+  fn test_table_to_cql() {
+    assert_eq!(
+      table().to_cql(),
+      "CREATE TABLE ks.events (\n    \
+       user uuid,\n    \
+       day date,\n    \
+       created timestamp,\n    \
+       owner text static,\n    \
+       \"Payload\" frozen<list<int>>,\n    \
+       PRIMARY KEY ((user, day), created)\n\
+       ) WITH CLUSTERING ORDER BY (created DESC)\n    \
+       AND comment = 'user''s events'\n    \
+       AND compaction = {'class': 'LeveledCompactionStrategy'}\n    \
+       AND gc_grace_seconds = 864000;"
+    );
+  }
+
+  #[test]
+  fn test_index_to_cql() {
+    let mut table = table();
+    assert_eq!(
+      table.index_cql(&table.indexes[0]),
+      "CREATE INDEX events_payload ON ks.events (\"Payload\");"
+    );
+
+    table.indexes[0] = IndexMetadata {
+      name: "events_sasi".into(),
+      kind: "CUSTOM".into(),
+      options: map(&[
+        ("target", "owner"),
+        ("class_name", "org.apache.cassandra.index.sasi.SASIIndex"),
+        ("mode", "CONTAINS"),
+      ]),
+    };
+    assert_eq!(
+      table.index_cql(&table.indexes[0]),
+      "CREATE CUSTOM INDEX events_sasi ON ks.events (owner) \
+       USING 'org.apache.cassandra.index.sasi.SASIIndex' WITH OPTIONS = {'mode': 'CONTAINS'};"
+    );
+  }
+
+  #[test]
+  fn test_view_to_cql() {
+    let view = ViewMetadata {
+      keyspace: "ks".into(),
+      name: "events_by_owner".into(),
+      base_table: "events".into(),
+      include_all_columns: false,
+      where_clause: "owner IS NOT NULL".into(),
+      columns: vec![
+        column("owner", ColumnKind::PartitionKey, "text"),
+        column("user", ColumnKind::Clustering, "uuid"),
+      ],
+      options: TableOptions::new(),
+    };
+
+    assert_eq!(
+      view.to_cql(),
+      "CREATE MATERIALIZED VIEW ks.events_by_owner AS\n    \
+       SELECT owner, user\n    \
+       FROM ks.events\n    \
+       WHERE owner IS NOT NULL\n    \
+       PRIMARY KEY (owner, user)\n\
+       WITH CLUSTERING ORDER BY (user ASC);"
+    );
+  }
+
+  #[test]
+  fn test_keyspace_to_full_cql() {
+    let address = UserTypeMetadata {
+      keyspace: "ks".into(),
+      name: "address".into(),
+      fields: vec![("street".into(), "text".into())],
+    };
+    let person = UserTypeMetadata {
+      keyspace: "ks".into(),
+      name: "a_person".into(),
+      fields: vec![("home".into(), "frozen<address>".into())],
+    };
+    let keyspace = KeyspaceMetadata {
+      name: "ks".into(),
+      durable_writes: true,
+      replication: map(&[
+        ("class", "org.apache.cassandra.locator.SimpleStrategy"),
+        ("replication_factor", "1"),
+      ]),
+      tables: BTreeMap::new(),
+      views: BTreeMap::new(),
+      user_types: vec![address, person]
+        .into_iter()
+        .map(|user_type| (user_type.name.clone(), user_type))
+        .collect(),
+      functions: vec![FunctionMetadata {
+        keyspace: "ks".into(),
+        name: "plus".into(),
+        arguments: vec![("a".into(), "int".into()), ("b".into(), "int".into())],
+        return_type: "int".into(),
+        language: "java".into(),
+        body: "return a + b;".into(),
+        called_on_null_input: false,
+      }],
+      aggregates: vec![AggregateMetadata {
+        keyspace: "ks".into(),
+        name: "total".into(),
+        argument_types: vec!["int".into()],
+        return_type: "int".into(),
+        state_func: "plus".into(),
+        state_type: "int".into(),
+        final_func: None,
+        initcond: Some("0".into()),
+      }],
+    };
+
+    assert_eq!(
+      keyspace.to_full_cql(),
+      "CREATE KEYSPACE ks WITH replication = \
+       {'class': 'org.apache.cassandra.locator.SimpleStrategy', 'replication_factor': '1'} \
+       AND durable_writes = true;\n\n\
+       CREATE TYPE ks.address (\n    street text\n);\n\n\
+       CREATE TYPE ks.a_person (\n    home frozen<address>\n);\n\n\
+       CREATE FUNCTION ks.plus(a int, b int)\n    \
+       RETURNS NULL ON NULL INPUT\n    \
+       RETURNS int\n    \
+       LANGUAGE java\n    \
+       AS $$return a + b;$$;\n\n\
+       CREATE AGGREGATE ks.total(int)\n    \
+       SFUNC plus\n    \
+       STYPE int\n    \
+       INITCOND 0;"
+    );
+  }
+}
@@ -0,0 +1,294 @@
+use std::collections::{BTreeMap, HashMap};
+
+use cassandra_proto::types::rows::Row;
+
+use super::schema::{
+  AggregateMetadata, ClusteringOrder, ColumnKind, ColumnMetadata, FunctionMetadata, IndexMetadata,
+  KeyspaceMetadata, OptionValue, TableMetadata, TableOptions, UserTypeMetadata, ViewMetadata,
+};
+use crate::{
+  error,
+  query::QueryExecutor,
+  types::{get_or_default, get_required},
+};
+
+/// Options of tables and views by their types. Options which a server
+/// does not have are skipped.
+const BOOLEAN_OPTIONS: &[&str] = &["cdc"];
+const INT_OPTIONS: &[&str] = &[
+  "default_time_to_live",
+  "gc_grace_seconds",
+  "max_index_interval",
+  "memtable_flush_period_in_ms",
+  "min_index_interval",
+];
+const DOUBLE_OPTIONS: &[&str] = &[
+  "bloom_filter_fp_chance",
+  "crc_check_chance",
+  "dclocal_read_repair_chance",
+  "read_repair_chance",
+];
+const TEXT_OPTIONS: &[&str] = &[
+  "additional_write_policy",
+  "comment",
+  "read_repair",
+  "speculative_retry",
+];
+const MAP_OPTIONS: &[&str] = &["caching", "compaction", "compression"];
+
+/// Selects rows of a `system_schema` table of all keyspaces
+/// or of a given one.
+async fn select<E: QueryExecutor>(
+  session: &E,
+  table: &str,
+  keyspace: Option<&str>,
+) -> error::Result<Vec<Row>> {
+  let frame = match keyspace {
+    Some(keyspace) => {
+      let query = format!(
+        "SELECT * FROM system_schema.{} WHERE keyspace_name = ?",
+        table
+      );
+      session.query_with_values(query, vec![keyspace]).await?
+    }
+    None => {
+      session
+        .query(format!("SELECT * FROM system_schema.{}", table))
+        .await?
+    }
+  };
+
+  Ok(frame.get_body()?.into_rows().unwrap_or_default())
+}
+
+/// Reads schema of all keyspaces or of a given one.
+pub(super) async fn fetch_keyspaces<E: QueryExecutor>(
+  session: &E,
+  keyspace: Option<&str>,
+) -> error::Result<BTreeMap<String, KeyspaceMetadata>> {
+  let mut keyspaces = BTreeMap::new();
+  for row in select(session, "keyspaces", keyspace).await? {
+    let name: String = get_required(&row, "keyspace_name")?;
+    let replication: HashMap<String, String> = get_required(&row, "replication")?;
+    keyspaces.insert(
+      name.clone(),
+      KeyspaceMetadata {
+        name,
+        durable_writes: get_or_default(&row, "durable_writes")?,
+        replication: replication.into_iter().collect(),
+        tables: BTreeMap::new(),
+        views: BTreeMap::new(),
+        user_types: BTreeMap::new(),
+        functions: vec![],
+        aggregates: vec![],
+      },
+    );
+  }
+
+  let mut columns = group_by_table(select(session, "columns", keyspace).await?, "table_name")?;
+  let mut indexes = group_by_table(select(session, "indexes", keyspace).await?, "table_name")?;
+
+  for row in select(session, "tables", keyspace).await? {
+    let (keyspace, name) = names_of(&row, "table_name")?;
+    let table = TableMetadata {
+      columns: read_columns(columns.remove(&(keyspace.clone(), name.clone())))?,
+      options: read_options(&row)?,
+      indexes: indexes
+        .remove(&(keyspace.clone(), name.clone()))
+        .unwrap_or_default()
+        .iter()
+        .map(read_index)
+        .collect::<error::Result<_>>()?,
+      keyspace,
+      name,
+    };
+    if let Some(keyspace) = keyspaces.get_mut(&table.keyspace) {
+      keyspace.tables.insert(table.name.clone(), table);
+    }
+  }
+
+  for row in select(session, "views", keyspace).await? {
+    let (keyspace, name) = names_of(&row, "view_name")?;
+    let view = ViewMetadata {
+      base_table: get_required(&row, "base_table_name")?,
+      include_all_columns: get_or_default(&row, "include_all_columns")?,
+      where_clause: get_or_default(&row, "where_clause")?,
+      columns: read_columns(columns.remove(&(keyspace.clone(), name.clone())))?,
+      options: read_options(&row)?,
+      keyspace,
+      name,
+    };
+    if let Some(keyspace) = keyspaces.get_mut(&view.keyspace) {
+      keyspace.views.insert(view.name.clone(), view);
+    }
+  }
+
+  for row in select(session, "types", keyspace).await? {
+    let (keyspace, name) = names_of(&row, "type_name")?;
+    let field_names: Vec<String> = get_required(&row, "field_names")?;
+    let field_types: Vec<String> = get_required(&row, "field_types")?;
+    let user_type = UserTypeMetadata {
+      keyspace,
+      name,
+      fields: field_names.into_iter().zip(field_types).collect(),
+    };
+    if let Some(keyspace) = keyspaces.get_mut(&user_type.keyspace) {
+      keyspace
+        .user_types
+        .insert(user_type.name.clone(), user_type);
+    }
+  }
+
+  for row in select(session, "functions", keyspace).await? {
+    let (keyspace, name) = names_of(&row, "function_name")?;
+    let argument_names: Vec<String> = get_required(&row, "argument_names")?;
+    let argument_types: Vec<String> = get_required(&row, "argument_types")?;
+    let function = FunctionMetadata {
+      keyspace,
+      name,
+      arguments: argument_names.into_iter().zip(argument_types).collect(),
+      return_type: get_required(&row, "return_type")?,
+      language: get_required(&row, "language")?,
+      body: get_required(&row, "body")?,
+      called_on_null_input: get_or_default(&row, "called_on_null_input")?,
+    };
+    if let Some(keyspace) = keyspaces.get_mut(&function.keyspace) {
+      keyspace.functions.push(function);
+    }
+  }
+
+  for row in select(session, "aggregates", keyspace).await? {
+    let (keyspace, name) = names_of(&row, "aggregate_name")?;
+    let aggregate = AggregateMetadata {
+      keyspace,
+      name,
+      argument_types: get_required(&row, "argument_types")?,
+      return_type: get_required(&row, "return_type")?,
+      state_func: get_required(&row, "state_func")?,
+      state_type: get_required(&row, "state_type")?,
+      final_func: get_or_default(&row, "final_func")?,
+      initcond: get_or_default(&row, "initcond")?,
+    };
+    if let Some(keyspace) = keyspaces.get_mut(&aggregate.keyspace) {
+      keyspace.aggregates.push(aggregate);
+    }
+  }
+
+  Ok(keyspaces)
+}
+
+/// Returns a keyspace name and a name of an element of a schema row.
+fn names_of(row: &Row, name_column: &str) -> error::Result<(String, String)> {
+  Ok((
+    get_required(row, "keyspace_name")?,
+    get_required(row, name_column)?,
+  ))
+}
+
+fn group_by_table(
+  rows: Vec<Row>,
+  name_column: &str,
+) -> error::Result<HashMap<(String, String), Vec<Row>>> {
+  let mut groups: HashMap<(String, String), Vec<Row>> = HashMap::new();
+  for row in rows {
+    groups
+      .entry(names_of(&row, name_column)?)
+      .or_default()
+      .push(row);
+  }
+
+  Ok(groups)
+}
+
+/// Reads columns of a table and orders them as they go in `CREATE TABLE`.
+fn read_columns(rows: Option<Vec<Row>>) -> error::Result<Vec<ColumnMetadata>> {
+  let mut columns = rows
+    .unwrap_or_default()
+    .iter()
+    .map(read_column)
+    .collect::<error::Result<Vec<_>>>()?;
+
+  let rank = |kind: ColumnKind| match kind {
+    ColumnKind::PartitionKey => 0,
+    ColumnKind::Clustering => 1,
+    ColumnKind::Regular | ColumnKind::Static => 2,
+  };
+  columns.sort_by(|a, b| {
+    rank(a.kind)
+      .cmp(&rank(b.kind))
+      .then(a.position.cmp(&b.position))
+      .then_with(|| a.name.cmp(&b.name))
+  });
+
+  Ok(columns)
+}
+
+fn read_column(row: &Row) -> error::Result<ColumnMetadata> {
+  let kind: String = get_required(row, "kind")?;
+  let clustering_order: String = get_or_default(row, "clustering_order")?;
+  let position: i32 = get_or_default(row, "position")?;
+
+  Ok(ColumnMetadata {
+    name: get_required(row, "column_name")?,
+    kind: match kind.as_str() {
+      "partition_key" => ColumnKind::PartitionKey,
+      "clustering" => ColumnKind::Clustering,
+      "static" => ColumnKind::Static,
+      "regular" => ColumnKind::Regular,
+      kind => return Err(format!("Unknown column kind {}", kind).into()),
+    },
+    // regular columns have position -1
+    position: position.max(0),
+    cql_type: get_required(row, "type")?,
+    clustering_order: match clustering_order.as_str() {
+      "asc" => ClusteringOrder::Asc,
+      "desc" => ClusteringOrder::Desc,
+      _ => ClusteringOrder::None,
+    },
+  })
+}
+
+fn read_index(row: &Row) -> error::Result<IndexMetadata> {
+  let options: HashMap<String, String> = get_required(row, "options")?;
+
+  Ok(IndexMetadata {
+    name: get_required(row, "index_name")?,
+    kind: get_required(row, "kind")?,
+    options: options.into_iter().collect(),
+  })
+}
+
+fn read_options(row: &Row) -> error::Result<TableOptions> {
+  let mut options = TableOptions::new();
+  let mut insert = |name: &str, value: Option<OptionValue>| {
+    if let Some(value) = value {
+      options.insert(name.to_string(), value);
+    }
+  };
+
+  for name in BOOLEAN_OPTIONS {
+    let value: Option<bool> = get_or_default(row, name)?;
+    insert(name, value.map(OptionValue::Boolean));
+  }
+  for name in INT_OPTIONS {
+    let value: Option<i32> = get_or_default(row, name)?;
+    insert(name, value.map(OptionValue::Int));
+  }
+  for name in DOUBLE_OPTIONS {
+    let value: Option<f64> = get_or_default(row, name)?;
+    insert(name, value.map(OptionValue::Double));
+  }
+  for name in TEXT_OPTIONS {
+    let value: Option<String> = get_or_default(row, name)?;
+    insert(name, value.map(OptionValue::Text));
+  }
+  for name in MAP_OPTIONS {
+    let value: Option<HashMap<String, String>> = get_or_default(row, name)?;
+    insert(
+      name,
+      value.map(|value| OptionValue::Map(value.into_iter().collect())),
+    );
+  }
+
+  Ok(options)
+}
@@ -0,0 +1,133 @@
+//! Schema metadata of keyspaces, tables, views, user defined types,
+//! functions and aggregates which is read from `system_schema` tables.
+//! Elements of a schema could be rendered back to CQL statements
+//! which create them.
+//!
+//! ```ignore
+//! let events = session.register(&[EventType::SchemaChange]).await?;
+//! let metadata = Metadata::watch(session.clone(), events).await?;
+//!
+//! let keyspace = metadata.keyspace("ks").unwrap();
+//! println!("{}", keyspace.table("events").unwrap().to_cql());
+//! ```
+
+mod cql;
+mod loader;
+mod schema;
+
+use std::{
+  collections::BTreeMap,
+  sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_std::task;
+use futures::stream::StreamExt;
+use log::{debug, warn};
+
+use crate::{
+  error,
+  events::{EventStream, ServerEvent},
+  query::QueryExecutor,
+};
+
+pub use cql::quote_identifier;
+pub use schema::{
+  AggregateMetadata, ClusteringOrder, ColumnKind, ColumnMetadata, FunctionMetadata,
+  IndexMetadata, KeyspaceMetadata, OptionValue, TableMetadata, TableOptions, UserTypeMetadata,
+  ViewMetadata,
+};
+
+type Keyspaces = BTreeMap<String, Arc<KeyspaceMetadata>>;
+
+/// Schema of all keyspaces of a cluster. Cloning metadata is cheap,
+/// all clones share the same schema.
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+  keyspaces: Arc<Mutex<Keyspaces>>,
+}
+
+fn lock<V>(value: &Mutex<V>) -> MutexGuard<'_, V> {
+  value
+    .lock()
+    .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Metadata {
+  /// Reads schema of all keyspaces.
+  pub async fn load<E: QueryExecutor>(session: &E) -> error::Result<Self> {
+    let metadata = Metadata::default();
+    metadata.refresh(session).await?;
+
+    Ok(metadata)
+  }
+
+  /// Reads schema of all keyspaces and keeps it fresh by reloading
+  /// a keyspace each time a schema change event of it is received.
+  /// Events should be registered for before the schema is read,
+  /// so no change is missed. Other events of the stream are ignored.
+  pub async fn watch<E: QueryExecutor + 'static>(
+    session: E,
+    mut events: EventStream,
+  ) -> error::Result<Self> {
+    let metadata = Metadata::load(&session).await?;
+
+    let watched = metadata.clone();
+    task::spawn(async move {
+      while let Some(event) = events.next().await {
+        if let ServerEvent::SchemaChange(change) = event {
+          debug!("CDRS metadata: schema changed: {:?}", change);
+          let keyspace = change.target.keyspace();
+          if let Err(err) = watched.refresh_keyspace(&session, keyspace).await {
+            warn!(
+              "CDRS metadata: cannot refresh keyspace {}: {:?}",
+              keyspace, err
+            );
+          }
+        }
+      }
+
+      warn!("CDRS metadata: event stream ended, schema is not refreshed anymore");
+    });
+
+    Ok(metadata)
+  }
+
+  /// Reads schema of all keyspaces again.
+  pub async fn refresh<E: QueryExecutor>(&self, session: &E) -> error::Result<()> {
+    let keyspaces = loader::fetch_keyspaces(session, None).await?;
+    *lock(&self.keyspaces) = keyspaces
+      .into_iter()
+      .map(|(name, keyspace)| (name, Arc::new(keyspace)))
+      .collect();
+
+    Ok(())
+  }
+
+  /// Reads schema of a keyspace again. A keyspace is forgotten if it
+  /// does not exist anymore.
+  pub async fn refresh_keyspace<E: QueryExecutor>(
+    &self,
+    session: &E,
+    keyspace: &str,
+  ) -> error::Result<()> {
+    let mut keyspaces = loader::fetch_keyspaces(session, Some(keyspace)).await?;
+
+    let mut current = lock(&self.keyspaces);
+    match keyspaces.remove(keyspace) {
+      Some(metadata) => current.insert(keyspace.to_string(), Arc::new(metadata)),
+      None => current.remove(keyspace),
+    };
+
+    Ok(())
+  }
+
+  /// Returns names of all keyspaces.
+  pub fn keyspace_names(&self) -> Vec<String> {
+    lock(&self.keyspaces).keys().cloned().collect()
+  }
+
+  /// Returns schema of a keyspace as it was read last time.
+  pub fn keyspace(&self, name: &str) -> Option<Arc<KeyspaceMetadata>> {
+    lock(&self.keyspaces).get(name).cloned()
+  }
+}
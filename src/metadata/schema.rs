@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{cluster::ReplicationStrategy, error};
+
+/// Schema of a keyspace and of all elements which belong to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyspaceMetadata {
+  pub name: String,
+  pub durable_writes: bool,
+  /// Replication options as they are given in `CREATE KEYSPACE`.
+  pub replication: BTreeMap<String, String>,
+  pub tables: BTreeMap<String, TableMetadata>,
+  pub views: BTreeMap<String, ViewMetadata>,
+  pub user_types: BTreeMap<String, UserTypeMetadata>,
+  pub functions: Vec<FunctionMetadata>,
+  pub aggregates: Vec<AggregateMetadata>,
+}
+
+impl KeyspaceMetadata {
+  pub fn table(&self, name: &str) -> Option<&TableMetadata> {
+    self.tables.get(name)
+  }
+
+  pub fn view(&self, name: &str) -> Option<&ViewMetadata> {
+    self.views.get(name)
+  }
+
+  pub fn user_type(&self, name: &str) -> Option<&UserTypeMetadata> {
+    self.user_types.get(name)
+  }
+
+  /// Parses replication options, so replicas of partitions of the keyspace
+  /// could be found by `TokenMap`.
+  pub fn replication_strategy(&self) -> error::Result<ReplicationStrategy> {
+    let options: HashMap<String, String> = self
+      .replication
+      .iter()
+      .map(|(key, value)| (key.clone(), value.clone()))
+      .collect();
+
+    ReplicationStrategy::from_options(&options)
+  }
+}
+
+/// Schema of a table. Columns go in the order of the primary key
+/// followed by other columns sorted by their names.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableMetadata {
+  pub keyspace: String,
+  pub name: String,
+  pub columns: Vec<ColumnMetadata>,
+  pub options: TableOptions,
+  pub indexes: Vec<IndexMetadata>,
+}
+
+impl TableMetadata {
+  pub fn column(&self, name: &str) -> Option<&ColumnMetadata> {
+    self.columns.iter().find(|column| column.name == name)
+  }
+
+  pub fn partition_key(&self) -> Vec<&ColumnMetadata> {
+    columns_of_kind(&self.columns, ColumnKind::PartitionKey)
+  }
+
+  pub fn clustering_columns(&self) -> Vec<&ColumnMetadata> {
+    columns_of_kind(&self.columns, ColumnKind::Clustering)
+  }
+}
+
+/// Schema of a materialized view. Its columns are ordered the same way
+/// as the ones of a table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewMetadata {
+  pub keyspace: String,
+  pub name: String,
+  pub base_table: String,
+  pub include_all_columns: bool,
+  pub where_clause: String,
+  pub columns: Vec<ColumnMetadata>,
+  pub options: TableOptions,
+}
+
+impl ViewMetadata {
+  pub fn column(&self, name: &str) -> Option<&ColumnMetadata> {
+    self.columns.iter().find(|column| column.name == name)
+  }
+
+  pub fn partition_key(&self) -> Vec<&ColumnMetadata> {
+    columns_of_kind(&self.columns, ColumnKind::PartitionKey)
+  }
+
+  pub fn clustering_columns(&self) -> Vec<&ColumnMetadata> {
+    columns_of_kind(&self.columns, ColumnKind::Clustering)
+  }
+}
+
+fn columns_of_kind(columns: &[ColumnMetadata], kind: ColumnKind) -> Vec<&ColumnMetadata> {
+  columns
+    .iter()
+    .filter(|column| column.kind == kind)
+    .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnMetadata {
+  pub name: String,
+  pub kind: ColumnKind,
+  /// Position of a column within a partition key or clustering columns.
+  pub position: i32,
+  /// CQL type of a column, e.g. `frozen<list<int>>`.
+  pub cql_type: String,
+  pub clustering_order: ClusteringOrder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+  PartitionKey,
+  Clustering,
+  Regular,
+  Static,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClusteringOrder {
+  Asc,
+  Desc,
+  None,
+}
+
+/// Options of a table or a view, e.g. `compaction` or `gc_grace_seconds`,
+/// by their names. A set of options depends on a Cassandra version.
+pub type TableOptions = BTreeMap<String, OptionValue>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptionValue {
+  Boolean(bool),
+  Int(i32),
+  Double(f64),
+  Text(String),
+  Map(BTreeMap<String, String>),
+}
+
+/// Secondary index of a table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexMetadata {
+  pub name: String,
+  /// `COMPOSITES`, `KEYS` or `CUSTOM`.
+  pub kind: String,
+  pub options: BTreeMap<String, String>,
+}
+
+impl IndexMetadata {
+  /// Column or an expression an index is built on, e.g. `keys(tags)`.
+  pub fn target(&self) -> Option<&str> {
+    self.options.get("target").map(String::as_str)
+  }
+
+  /// Class of a custom index.
+  pub fn class_name(&self) -> Option<&str> {
+    self.options.get("class_name").map(String::as_str)
+  }
+}
+
+/// User defined type. Fields go in their declaration order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserTypeMetadata {
+  pub keyspace: String,
+  pub name: String,
+  /// Names of fields with their CQL types.
+  pub fields: Vec<(String, String)>,
+}
+
+/// User defined function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionMetadata {
+  pub keyspace: String,
+  pub name: String,
+  /// Names of arguments with their CQL types.
+  pub arguments: Vec<(String, String)>,
+  pub return_type: String,
+  pub language: String,
+  pub body: String,
+  pub called_on_null_input: bool,
+}
+
+/// User defined aggregate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateMetadata {
+  pub keyspace: String,
+  pub name: String,
+  pub argument_types: Vec<String>,
+  pub return_type: String,
+  pub state_func: String,
+  pub state_type: String,
+  pub final_func: Option<String>,
+  /// Initial state as a CQL literal.
+  pub initcond: Option<String>,
+}
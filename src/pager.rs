@@ -1,16 +1,29 @@
-use std::pin::Pin;
+use std::{
+  pin::Pin,
+  task::{Context, Poll},
+  vec,
+};
 
 use cassandra_proto::{
-  error,
-  frame::frame_result::{RowsMetadata, RowsMetadataFlag},
-  query::QueryParamsBuilder,
+  frame::{
+    frame_result::{RowsMetadata, RowsMetadataFlag},
+    Frame,
+  },
+  query::{QueryFlags, QueryParams, QueryParamsBuilder, QueryValues},
   types::{rows::Row, CBytes},
 };
+use futures::{
+  future::{BoxFuture, FutureExt},
+  stream::Stream,
+};
+use ring::{digest, hmac};
 
 use crate::{
+  error,
   query::{ExecExecutor, PreparedQuery, QueryExecutor},
   session::Session,
   transport::CDRSTransport,
+  utils::clone_query_params,
 };
 
 pub type PageSize = i32;
@@ -18,48 +31,143 @@ pub type PageSize = i32;
 pub struct SessionPager<T: CDRSTransport + 'static> {
   page_size: i32,
   session: Session<T>,
+  prefetch: bool,
 }
 
 impl<T: CDRSTransport + 'static> SessionPager<T> {
   pub fn new(session: Session<T>, page_size: PageSize) -> SessionPager<T> {
-    SessionPager { session, page_size }
+    SessionPager {
+      session,
+      page_size,
+      prefetch: false,
+    }
   }
 
-  pub fn query_with_pager_state<Q>(
+  /// Makes streams of rows request the next page while the current one
+  /// is being consumed.
+  pub fn prefetch(mut self, prefetch: bool) -> Self {
+    self.prefetch = prefetch;
+
+    self
+  }
+
+  /// Returns a stream of all rows of a query which fetches pages as needed.
+  pub fn query_stream<Q: ToString>(&mut self, query: Q) -> RowStream {
+    self.query(query).into_stream()
+  }
+
+  /// Returns a stream of all rows of a prepared query which fetches pages
+  /// as needed.
+  pub fn exec_stream(&mut self, query: PreparedQuery) -> RowStream {
+    self.exec(query).into_stream()
+  }
+
+  /// Pages a query with given parameters. Values, consistency, timestamp
+  /// and other parameters are sent with each page request, while a page size
+  /// and a paging state are set by the pager.
+  pub fn query_with_params_and_pager_state<Q>(
     &mut self,
     query: Q,
+    params: QueryParams,
     state: PagerState,
-  ) -> QueryPager<Q, SessionPager<T>>
+  ) -> QueryPager<'_, Q, SessionPager<T>>
   where
     Q: ToString,
   {
     QueryPager {
       pager: self,
       pager_state: state,
+      params,
       query,
     }
   }
 
-  pub fn query<Q>(&mut self, query: Q) -> QueryPager<Q, SessionPager<T>>
+  pub fn query_with_params<Q>(
+    &mut self,
+    query: Q,
+    params: QueryParams,
+  ) -> QueryPager<'_, Q, SessionPager<T>>
+  where
+    Q: ToString,
+  {
+    self.query_with_params_and_pager_state(query, params, PagerState::new())
+  }
+
+  pub fn query_with_values<Q, V>(
+    &mut self,
+    query: Q,
+    values: V,
+  ) -> QueryPager<'_, Q, SessionPager<T>>
+  where
+    Q: ToString,
+    V: Into<QueryValues>,
+  {
+    let params = QueryParamsBuilder::new().values(values.into()).finalize();
+
+    self.query_with_params(query, params)
+  }
+
+  pub fn query_with_pager_state<Q>(
+    &mut self,
+    query: Q,
+    state: PagerState,
+  ) -> QueryPager<'_, Q, SessionPager<T>>
+  where
+    Q: ToString,
+  {
+    self.query_with_params_and_pager_state(query, QueryParams::default(), state)
+  }
+
+  pub fn query<Q>(&mut self, query: Q) -> QueryPager<'_, Q, SessionPager<T>>
   where
     Q: ToString,
   {
     self.query_with_pager_state(query, PagerState::new())
   }
 
-  pub fn exec_with_pager_state(
+  /// Pages a prepared query with given parameters, see
+  /// `query_with_params_and_pager_state`.
+  pub fn exec_with_params_and_pager_state(
     &mut self,
     query: PreparedQuery,
+    params: QueryParams,
     state: PagerState,
-  ) -> ExecPager<SessionPager<T>> {
+  ) -> ExecPager<'_, SessionPager<T>> {
     ExecPager {
       pager: self,
       pager_state: state,
+      params,
       query,
     }
   }
 
-  pub fn exec(&mut self, query: PreparedQuery) -> ExecPager<SessionPager<T>> {
+  pub fn exec_with_params(
+    &mut self,
+    query: PreparedQuery,
+    params: QueryParams,
+  ) -> ExecPager<'_, SessionPager<T>> {
+    self.exec_with_params_and_pager_state(query, params, PagerState::new())
+  }
+
+  pub fn exec_with_values<V: Into<QueryValues>>(
+    &mut self,
+    query: PreparedQuery,
+    values: V,
+  ) -> ExecPager<'_, SessionPager<T>> {
+    let params = QueryParamsBuilder::new().values(values.into()).finalize();
+
+    self.exec_with_params(query, params)
+  }
+
+  pub fn exec_with_pager_state(
+    &mut self,
+    query: PreparedQuery,
+    state: PagerState,
+  ) -> ExecPager<'_, SessionPager<T>> {
+    self.exec_with_params_and_pager_state(query, QueryParams::default(), state)
+  }
+
+  pub fn exec(&mut self, query: PreparedQuery) -> ExecPager<'_, SessionPager<T>> {
     self.exec_with_pager_state(query, PagerState::new())
   }
 }
@@ -67,34 +175,22 @@ impl<T: CDRSTransport + 'static> SessionPager<T> {
 pub struct QueryPager<'a, Q: ToString, P: 'a> {
   pager: &'a mut P,
   pager_state: PagerState,
+  params: QueryParams,
   query: Q,
 }
 
 impl<'a, Q: ToString, T: CDRSTransport + 'static> QueryPager<'a, Q, SessionPager<T>> {
   pub async fn next(&mut self) -> error::Result<Vec<Row>> {
-    let mut params = QueryParamsBuilder::new().page_size(self.pager.page_size);
-    if self.pager_state.cursor.is_some() {
-      params = params.paging_state(self.pager_state.cursor.clone().unwrap());
-    }
-
-    let pinned_session = Pin::new(&mut self.pager.session);
-
-    let body = pinned_session
-      .query_with_params(self.query.to_string(), params.finalize())
+    let params = page_params(&self.params, self.pager.page_size, &self.pager_state);
+    let page = self
+      .pager
+      .session
+      .query_with_params(self.query.to_string(), params)
       .await
-      .and_then(|frame| frame.get_body())?;
-
-    let metadata_res: error::Result<RowsMetadata> = body
-      .as_rows_metadata()
-      .ok_or("Pager query should yield a vector of rows".into());
-    let metadata = metadata_res?;
+      .and_then(read_page)?;
 
-    self.pager_state.has_more_pages =
-      Some(RowsMetadataFlag::has_has_more_pages(metadata.flags.clone()));
-    self.pager_state.cursor = metadata.paging_state.clone();
-    body
-      .into_rows()
-      .ok_or("Pager query should yield a vector of rows".into())
+    self.pager_state = page.state;
+    Ok(page.rows)
   }
 
   pub fn has_more(&self) -> bool {
@@ -106,38 +202,50 @@ impl<'a, Q: ToString, T: CDRSTransport + 'static> QueryPager<'a, Q, SessionPager
   pub fn pager_state(&self) -> PagerState {
     self.pager_state.clone()
   }
+
+  /// Converts the pager into a stream of rows which continues
+  /// from the current state of the pager.
+  pub fn into_stream(self) -> RowStream {
+    let session = self.pager.session.clone();
+    let page_size = self.pager.page_size;
+    let params = self.params;
+    let query = self.query.to_string();
+    let fetch: FetchPage = Box::new(move |state: PagerState| {
+      let session = session.clone();
+      let query = query.clone();
+      let params = page_params(&params, page_size, &state);
+      async move {
+        session
+          .query_with_params(query, params)
+          .await
+          .and_then(read_page)
+      }
+      .boxed()
+    });
+
+    RowStream::new(fetch, self.pager_state, self.pager.prefetch)
+  }
 }
 
 pub struct ExecPager<'a, P: 'a> {
   pager: &'a mut P,
   pager_state: PagerState,
+  params: QueryParams,
   query: PreparedQuery,
 }
 
 impl<'a, T: CDRSTransport + 'static> ExecPager<'a, SessionPager<T>> {
   pub async fn next(&mut self) -> error::Result<Vec<Row>> {
-    let mut params = QueryParamsBuilder::new().page_size(self.pager.page_size);
-    if self.pager_state.cursor.is_some() {
-      params = params.paging_state(self.pager_state.cursor.clone().unwrap());
-    }
-    let pinned_session = Pin::new(&mut self.pager.session);
-
-    let body = pinned_session
-      .exec_with_params(&self.query, params.finalize())
+    let params = page_params(&self.params, self.pager.page_size, &self.pager_state);
+    let page = self
+      .pager
+      .session
+      .exec_with_params(&self.query, params)
       .await
-      .and_then(|frame| frame.get_body())?;
+      .and_then(read_page)?;
 
-    let metadata_res: error::Result<RowsMetadata> = body
-      .as_rows_metadata()
-      .ok_or("Pager query should yield a vector of rows".into());
-    let metadata = metadata_res?;
-
-    self.pager_state.has_more_pages =
-      Some(RowsMetadataFlag::has_has_more_pages(metadata.flags.clone()));
-    self.pager_state.cursor = metadata.paging_state.clone();
-    body
-      .into_rows()
-      .ok_or("Pager query should yield a vector of rows".into())
+    self.pager_state = page.state;
+    Ok(page.rows)
   }
 
   pub fn has_more(&self) -> bool {
@@ -149,6 +257,160 @@ impl<'a, T: CDRSTransport + 'static> ExecPager<'a, SessionPager<T>> {
   pub fn pager_state(&self) -> PagerState {
     self.pager_state.clone()
   }
+
+  /// Converts the pager into a stream of rows which continues
+  /// from the current state of the pager.
+  pub fn into_stream(self) -> RowStream {
+    let session = self.pager.session.clone();
+    let page_size = self.pager.page_size;
+    let params = self.params;
+    let query = self.query;
+    let fetch: FetchPage = Box::new(move |state: PagerState| {
+      let session = session.clone();
+      let query = query.clone();
+      let params = page_params(&params, page_size, &state);
+      async move {
+        session
+          .exec_with_params(&query, params)
+          .await
+          .and_then(read_page)
+      }
+      .boxed()
+    });
+
+    RowStream::new(fetch, self.pager_state, self.pager.prefetch)
+  }
+}
+
+/// Page of rows along with a state the next page is requested with.
+struct Page {
+  rows: Vec<Row>,
+  state: PagerState,
+}
+
+/// Returns parameters of a page request, which are given parameters
+/// of a query with a page size and a paging state of a pager.
+fn page_params(params: &QueryParams, page_size: PageSize, state: &PagerState) -> QueryParams {
+  let mut params = clone_query_params(params);
+  params
+    .flags
+    .retain(|flag| !matches!(flag, QueryFlags::PageSize | QueryFlags::WithPagingState));
+
+  params.flags.push(QueryFlags::PageSize);
+  params.page_size = Some(page_size);
+  params.paging_state = state.cursor.clone();
+  if params.paging_state.is_some() {
+    params.flags.push(QueryFlags::WithPagingState);
+  }
+
+  params
+}
+
+fn read_page(frame: Frame) -> error::Result<Page> {
+  let body = frame.get_body()?;
+  let metadata: RowsMetadata = body
+    .as_rows_metadata()
+    .ok_or_else(|| error::Error::from("Pager query should yield a vector of rows"))?;
+  let state = PagerState {
+    cursor: metadata.paging_state.clone(),
+    has_more_pages: Some(RowsMetadataFlag::has_has_more_pages(metadata.flags)),
+  };
+  let rows = body
+    .into_rows()
+    .ok_or_else(|| error::Error::from("Pager query should yield a vector of rows"))?;
+
+  Ok(Page { rows, state })
+}
+
+type FetchPage = Box<dyn Fn(PagerState) -> BoxFuture<'static, error::Result<Page>> + Send + Sync>;
+
+/// Stream of rows which requests pages one by one as rows are consumed.
+/// If prefetching is enabled, the next page is requested as soon as
+/// the current one arrives. Dropping the stream abandons a request
+/// of a page which has not arrived yet.
+pub struct RowStream {
+  fetch: FetchPage,
+  rows: vec::IntoIter<Row>,
+  state: PagerState,
+  next_page: Option<BoxFuture<'static, error::Result<Page>>>,
+  fetched: Option<error::Result<Page>>,
+  prefetch: bool,
+  is_failed: bool,
+}
+
+impl RowStream {
+  fn new(fetch: FetchPage, state: PagerState, prefetch: bool) -> Self {
+    RowStream {
+      fetch,
+      rows: Vec::new().into_iter(),
+      state,
+      next_page: None,
+      fetched: None,
+      prefetch,
+      is_failed: false,
+    }
+  }
+
+  /// Returns a state of the last received page. Paging which is continued
+  /// from it starts after rows of that page, even if some of them have not
+  /// been consumed from the stream yet.
+  pub fn pager_state(&self) -> PagerState {
+    self.state.clone()
+  }
+
+  fn request_next_page(&mut self) {
+    // a state without a page flag is the one paging has not started with yet
+    let has_more = self.state.has_more_pages.unwrap_or(true);
+    if has_more && self.next_page.is_none() && !self.is_failed {
+      self.next_page = Some((self.fetch)(self.state.clone()));
+    }
+  }
+}
+
+impl Stream for RowStream {
+  type Item = error::Result<Row>;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let stream = &mut *self;
+
+    loop {
+      // a prefetched page is polled while rows are consumed,
+      // so its request is sent and its response is awaited meanwhile
+      if stream.fetched.is_none() {
+        if let Some(next_page) = stream.next_page.as_mut() {
+          if let Poll::Ready(page) = next_page.as_mut().poll(cx) {
+            stream.next_page = None;
+            stream.fetched = Some(page);
+          }
+        }
+      }
+
+      if let Some(row) = stream.rows.next() {
+        return Poll::Ready(Some(Ok(row)));
+      }
+
+      match stream.fetched.take() {
+        Some(Ok(page)) => {
+          stream.rows = page.rows.into_iter();
+          stream.state = page.state;
+          if stream.prefetch {
+            stream.request_next_page();
+          }
+        }
+        Some(Err(err)) => {
+          stream.is_failed = true;
+          return Poll::Ready(Some(Err(err)));
+        }
+        None if stream.next_page.is_some() => return Poll::Pending,
+        None => {
+          stream.request_next_page();
+          if stream.next_page.is_none() {
+            return Poll::Ready(None);
+          }
+        }
+      }
+    }
+  }
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -186,4 +448,303 @@ impl PagerState {
   pub fn get_cursor(&self) -> Option<CBytes> {
     self.cursor.clone()
   }
+
+  /// Encodes the state as a URL-safe string which paging could be resumed
+  /// with later by `from_token`. A client which holds the token can change
+  /// it, so it should be signed by `to_signed_token` if it leaves the application.
+  pub fn to_token(&self) -> String {
+    encode_token(self.token_bytes(0))
+  }
+
+  /// Encodes the state as a URL-safe string which is signed by HMAC-SHA256
+  /// with a given secret key. A signature covers a given context as well,
+  /// e.g. a query and its values, so a token of one query could not be used
+  /// to page another one. It could be decoded only by `from_signed_token`
+  /// with the same key and context.
+  pub fn to_signed_token(&self, key: &[u8], context: &[u8]) -> String {
+    let mut bytes = self.token_bytes(TOKEN_SIGNED);
+    let tag = hmac::sign(
+      &hmac::Key::new(hmac::HMAC_SHA256, key),
+      &signed_bytes(&bytes, context),
+    );
+    bytes.extend_from_slice(tag.as_ref());
+
+    encode_token(bytes)
+  }
+
+  /// Decodes a state from a token which was created by `to_token`.
+  /// Signed tokens are rejected, they should be decoded with a key.
+  pub fn from_token(token: &str) -> error::Result<Self> {
+    let bytes = decode_token(token)?;
+    if bytes[1] & TOKEN_SIGNED != 0 {
+      return Err("Paging token is signed and should be verified with a key".into());
+    }
+
+    PagerState::from_token_bytes(&bytes)
+  }
+
+  /// Decodes a state from a token which was created by `to_signed_token`
+  /// and checks that it was signed with a given key for a given context.
+  pub fn from_signed_token(token: &str, key: &[u8], context: &[u8]) -> error::Result<Self> {
+    let bytes = decode_token(token)?;
+    if bytes[1] & TOKEN_SIGNED == 0 || bytes.len() < TOKEN_HEADER_LEN + TOKEN_TAG_LEN {
+      return Err("Paging token is not signed".into());
+    }
+
+    let (bytes, tag) = bytes.split_at(bytes.len() - TOKEN_TAG_LEN);
+    hmac::verify(
+      &hmac::Key::new(hmac::HMAC_SHA256, key),
+      &signed_bytes(bytes, context),
+      tag,
+    )
+    .map_err(|_| error::Error::from("Paging token has an invalid signature"))?;
+
+    PagerState::from_token_bytes(bytes)
+  }
+
+  fn token_bytes(&self, mut flags: u8) -> Vec<u8> {
+    let cursor = self.cursor.clone().and_then(CBytes::into_plain);
+    if cursor.is_some() {
+      flags |= TOKEN_HAS_CURSOR;
+    }
+    match self.has_more_pages {
+      Some(true) => flags |= TOKEN_HAS_MORE_KNOWN | TOKEN_HAS_MORE,
+      Some(false) => flags |= TOKEN_HAS_MORE_KNOWN,
+      None => {}
+    }
+
+    let mut bytes = vec![TOKEN_VERSION, flags];
+    bytes.extend(cursor.unwrap_or_default());
+    bytes
+  }
+
+  /// Reads a state from a header and a cursor of a token without a signature.
+  fn from_token_bytes(bytes: &[u8]) -> error::Result<Self> {
+    let flags = bytes[1];
+    let cursor = &bytes[TOKEN_HEADER_LEN..];
+    let has_cursor = flags & TOKEN_HAS_CURSOR != 0;
+    if !has_cursor && !cursor.is_empty() {
+      return Err("Paging token is malformed".into());
+    }
+
+    Ok(PagerState {
+      cursor: if has_cursor {
+        Some(CBytes::new(cursor.to_vec()))
+      } else {
+        None
+      },
+      has_more_pages: if flags & TOKEN_HAS_MORE_KNOWN != 0 {
+        Some(flags & TOKEN_HAS_MORE != 0)
+      } else {
+        None
+      },
+    })
+  }
+}
+
+/// A token is URL-safe base64 of a version byte, a flags byte, bytes
+/// of a cursor and HMAC-SHA256 of all of them if the token is signed.
+const TOKEN_VERSION: u8 = 1;
+const TOKEN_HEADER_LEN: usize = 2;
+const TOKEN_TAG_LEN: usize = 32;
+
+const TOKEN_HAS_CURSOR: u8 = 0x01;
+const TOKEN_HAS_MORE_KNOWN: u8 = 0x02;
+const TOKEN_HAS_MORE: u8 = 0x04;
+const TOKEN_SIGNED: u8 = 0x08;
+
+/// Returns bytes a signature of a token is computed of: bytes of the token
+/// followed by SHA-256 of a context, so they could not be confused.
+fn signed_bytes(bytes: &[u8], context: &[u8]) -> Vec<u8> {
+  let mut signed = bytes.to_vec();
+  signed.extend_from_slice(digest::digest(&digest::SHA256, context).as_ref());
+  signed
+}
+
+fn encode_token(bytes: Vec<u8>) -> String {
+  base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Decodes a token and checks its header.
+fn decode_token(token: &str) -> error::Result<Vec<u8>> {
+  let bytes = base64::decode_config(token, base64::URL_SAFE_NO_PAD)
+    .map_err(|err| error::Error::from(format!("Paging token is malformed: {}", err)))?;
+
+  match bytes.first() {
+    Some(&TOKEN_VERSION) if bytes.len() >= TOKEN_HEADER_LEN => Ok(bytes),
+    Some(&TOKEN_VERSION) | None => Err("Paging token is malformed".into()),
+    Some(version) => Err(format!("Paging token version {} is not supported", version).into()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use cassandra_proto::{
+    consistency::Consistency,
+    frame::{frame_result::BodyResResultRows, FromCursor},
+    types::IntoRustByName,
+  };
+  use futures::{executor::block_on, stream::StreamExt};
+  use std::{
+    io::Cursor,
+    sync::{
+      atomic::{AtomicUsize, Ordering},
+      Arc,
+    },
+  };
+
+  /// Rows of `ks.tb` table with a single `id int` column.
+  fn rows(ids: &[i32]) -> Vec<Row> {
+    let mut bytes = vec![0, 0, 0, 1, 0, 0, 0, 1];
+    bytes.extend_from_slice(&[0, 2, b'k', b's', 0, 2, b't', b'b']);
+    bytes.extend_from_slice(&[0, 2, b'i', b'd', 0, 0x09]);
+    bytes.extend_from_slice(&(ids.len() as i32).to_be_bytes());
+    for id in ids {
+      bytes.extend_from_slice(&[0, 0, 0, 4]);
+      bytes.extend_from_slice(&id.to_be_bytes());
+    }
+
+    let body = BodyResResultRows::from_cursor(&mut Cursor::new(&bytes)).unwrap();
+    Row::from_frame_body(body)
+  }
+
+  /// Returns pages of two rows, the page with a given number fails.
+  fn fetch(pages: usize, failing: Option<usize>, calls: Arc<AtomicUsize>) -> FetchPage {
+    Box::new(move |_state| {
+      let page = calls.fetch_add(1, Ordering::SeqCst);
+      let result = if Some(page) == failing {
+        Err("page is unavailable".into())
+      } else {
+        let id = page as i32 * 2;
+        Ok(Page {
+          rows: rows(&[id, id + 1]),
+          state: PagerState {
+            cursor: None,
+            has_more_pages: Some(page + 1 < pages),
+          },
+        })
+      };
+
+      async move { result }.boxed()
+    })
+  }
+
+  fn ids(rows: Vec<error::Result<Row>>) -> Vec<i32> {
+    rows
+      .into_iter()
+      .map(|row| row.unwrap().get_r_by_name("id").unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn test_page_params_keep_query_params() {
+    let params = QueryParamsBuilder::new()
+      .consistency(Consistency::Quorum)
+      .values(QueryValues::SimpleValues(vec![1i32.into()]))
+      .timestamp(7)
+      .page_size(100)
+      .finalize();
+
+    let first = page_params(&params, 10, &PagerState::new());
+    assert_eq!(first.consistency, Consistency::Quorum);
+    assert_eq!(first.timestamp, Some(7));
+    assert_eq!(first.page_size, Some(10));
+    assert!(first.values.is_some());
+    assert!(first.paging_state.is_none());
+
+    let cursor = CBytes::new(vec![1, 2]);
+    let next = page_params(&params, 10, &PagerState::with_cursor(cursor.clone()));
+    assert_eq!(next.paging_state, Some(cursor));
+    let paging_flags = next
+      .flags
+      .iter()
+      .filter(|flag| matches!(flag, QueryFlags::PageSize | QueryFlags::WithPagingState))
+      .count();
+    assert_eq!(paging_flags, 2);
+  }
+
+  #[test]
+  fn test_stream_fetches_all_pages() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let stream = RowStream::new(fetch(3, None, calls.clone()), PagerState::new(), false);
+
+    assert_eq!(ids(block_on(stream.collect())), vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+  }
+
+  #[test]
+  fn test_stream_prefetches_next_page() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut stream = RowStream::new(fetch(3, None, calls.clone()), PagerState::new(), true);
+
+    assert!(block_on(stream.next()).is_some());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert_eq!(ids(block_on(stream.collect())), vec![1, 2, 3, 4, 5]);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+  }
+
+  #[test]
+  fn test_stream_ends_after_error() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut stream = RowStream::new(fetch(3, Some(1), calls.clone()), PagerState::new(), false);
+
+    assert!(block_on(stream.next()).unwrap().is_ok());
+    assert!(block_on(stream.next()).unwrap().is_ok());
+    assert!(block_on(stream.next()).unwrap().is_err());
+    assert!(block_on(stream.next()).is_none());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+  }
+
+  #[test]
+  fn test_token_round_trip() {
+    let states = vec![
+      PagerState::new(),
+      PagerState::with_cursor(CBytes::new(vec![1, 2, 3])),
+      PagerState::with_cursor_and_more_flag(CBytes::new(vec![0xFF; 40]), true),
+      PagerState {
+        cursor: None,
+        has_more_pages: Some(false),
+      },
+    ];
+
+    for state in states {
+      let token = state.to_token();
+      assert!(token
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+      assert_eq!(PagerState::from_token(&token).unwrap(), state);
+    }
+  }
+
+  #[test]
+  fn test_signed_token() {
+    let state = PagerState::with_cursor_and_more_flag(CBytes::new(vec![1, 2, 3]), true);
+    let token = state.to_signed_token(b"secret", b"query");
+
+    assert_eq!(
+      PagerState::from_signed_token(&token, b"secret", b"query").unwrap(),
+      state
+    );
+    assert!(PagerState::from_signed_token(&token, b"other", b"query").is_err());
+    assert!(PagerState::from_token(&token).is_err());
+    assert!(PagerState::from_signed_token(&state.to_token(), b"secret", b"query").is_err());
+    // a cursor of one query could not be replayed for another one
+    assert!(PagerState::from_signed_token(&token, b"secret", b"other query").is_err());
+
+    // a client changes a cursor of a signed token
+    let mut bytes = base64::decode_config(&token, base64::URL_SAFE_NO_PAD).unwrap();
+    bytes[2] = 9;
+    let forged = base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD);
+    assert!(PagerState::from_signed_token(&forged, b"secret", b"query").is_err());
+  }
+
+  #[test]
+  fn test_malformed_token() {
+    assert!(PagerState::from_token("").is_err());
+    assert!(PagerState::from_token("not base64!").is_err());
+    assert!(PagerState::from_token(&encode_token(vec![2, 0])).is_err());
+    assert!(PagerState::from_token(&encode_token(vec![TOKEN_VERSION])).is_err());
+    assert!(PagerState::from_token(&encode_token(vec![TOKEN_VERSION, 0, 1])).is_err());
+  }
 }
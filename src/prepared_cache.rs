@@ -0,0 +1,468 @@
+use std::{
+  collections::HashMap,
+  future::Future,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex, MutexGuard,
+  },
+};
+
+use cassandra_proto::frame::frame_result::BodyResResultPrepared;
+use futures::{
+  channel::oneshot,
+  future::{FutureExt, Shared},
+};
+
+use crate::{
+  error,
+  events::{SchemaChange, SchemaChangeTarget, SchemaChangeType},
+  query::PreparedStatement,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+  keyspace: Option<String>,
+  query: String,
+}
+
+/// Number of prepared queries a cache keeps by default.
+const DEFAULT_CAPACITY: usize = 1024;
+
+enum Slot {
+  /// Prepared query with a time it was used last time.
+  Ready(Arc<PreparedStatement>, u64),
+  /// Query is being prepared by another request, the receiver is cancelled
+  /// if preparation fails.
+  Preparing(Shared<oneshot::Receiver<Arc<PreparedStatement>>>),
+}
+
+/// Cache of prepared queries. Queries are identified by their text
+/// and a keyspace they were prepared in, so the same query is prepared
+/// only once even if it is requested concurrently. When a cache is full,
+/// the least recently used query is evicted.
+///
+/// Queries which are evicted or could be affected by a schema change become
+/// stale: they are prepared again when they are requested, but they are still
+/// found by their ids, so a query a server does not know could be prepared
+/// again from its text.
+pub(crate) struct PreparedCache {
+  entries: Mutex<HashMap<CacheKey, Slot>>,
+  ids: Mutex<HashMap<Vec<u8>, Arc<PreparedStatement>>>,
+  capacity: usize,
+  clock: AtomicU64,
+}
+
+fn lock<V>(value: &Mutex<V>) -> MutexGuard<'_, V> {
+  value
+    .lock()
+    .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl PreparedCache {
+  pub fn new() -> Self {
+    PreparedCache::with_capacity(DEFAULT_CAPACITY)
+  }
+
+  pub fn with_capacity(capacity: usize) -> Self {
+    PreparedCache {
+      entries: Mutex::new(HashMap::new()),
+      ids: Mutex::new(HashMap::new()),
+      capacity,
+      clock: AtomicU64::new(0),
+    }
+  }
+
+  fn tick(&self) -> u64 {
+    self.clock.fetch_add(1, Ordering::Relaxed)
+  }
+
+  /// Returns a cached prepared query or prepares it. If the same query
+  /// is already being prepared, it waits for that preparation instead.
+  pub async fn get_or_prepare<F, R>(
+    &self,
+    keyspace: Option<String>,
+    query: String,
+    prepare: F,
+  ) -> error::Result<Arc<PreparedStatement>>
+  where
+    F: Fn() -> R,
+    R: Future<Output = error::Result<BodyResResultPrepared>>,
+  {
+    let key = CacheKey { keyspace, query };
+
+    loop {
+      let preparing = {
+        let mut entries = lock(&self.entries);
+        match entries.get_mut(&key) {
+          Some(Slot::Ready(entry, last_used)) => {
+            *last_used = self.tick();
+            return Ok(entry.clone());
+          }
+          Some(Slot::Preparing(preparing)) => Some(preparing.clone()),
+          None => None,
+        }
+        .ok_or_else(|| {
+          let (sender, receiver) = oneshot::channel();
+          entries.insert(key.clone(), Slot::Preparing(receiver.shared()));
+          sender
+        })
+      };
+
+      let sender = match preparing {
+        // if preparation fails, a next attempt is made by one of the waiting
+        // requests, so each of them gets an error of its own
+        Ok(preparing) => match preparing.await {
+          Ok(entry) => return Ok(entry),
+          Err(oneshot::Canceled) => continue,
+        },
+        Err(sender) => sender,
+      };
+
+      let guard = PreparingGuard {
+        cache: self,
+        key: &key,
+      };
+      // a ready entry replaces a slot before the guard is dropped, so no
+      // request starts another preparation in between
+      let entry = prepare()
+        .await
+        .map(|prepared| self.insert(key.keyspace.clone(), key.query.clone(), prepared));
+      drop(guard);
+
+      let entry = entry?;
+      let _ = sender.send(entry.clone());
+
+      return Ok(entry);
+    }
+  }
+
+  /// Stores a prepared query replacing a previous one if any.
+  pub fn insert(
+    &self,
+    keyspace: Option<String>,
+    query: String,
+    prepared: BodyResResultPrepared,
+  ) -> Arc<PreparedStatement> {
+    let key = CacheKey {
+      keyspace: keyspace.clone(),
+      query: query.clone(),
+    };
+    let entry = Arc::new(PreparedStatement::new(keyspace, query, prepared));
+
+    let mut entries = lock(&self.entries);
+    let mut ids = lock(&self.ids);
+    if let Some(id) = entry.id().clone().into_plain() {
+      ids.insert(id, entry.clone());
+    }
+    entries.insert(key, Slot::Ready(entry.clone(), self.tick()));
+
+    if entries.len() > self.capacity {
+      let least_recently_used = entries
+        .iter()
+        .filter_map(|(key, slot)| match slot {
+          Slot::Ready(_, last_used) => Some((*last_used, key.clone())),
+          Slot::Preparing(_) => None,
+        })
+        .min_by_key(|(last_used, _)| *last_used);
+      if let Some((_, key)) = least_recently_used {
+        entries.remove(&key);
+      }
+    }
+    // ids of stale queries are kept until there are as many of them
+    // as of cached ones
+    if ids.len() > 2 * self.capacity {
+      ids.retain(|_, entry| is_cached(&entries, entry));
+    }
+
+    entry
+  }
+
+  /// Finds a prepared query by its id. Stale queries are found as well.
+  pub fn get_by_id(&self, id: &[u8]) -> Option<Arc<PreparedStatement>> {
+    let entry = lock(&self.ids).get(id).cloned()?;
+
+    let key = CacheKey {
+      keyspace: entry.keyspace().map(str::to_string),
+      query: entry.query().to_string(),
+    };
+    if let Some(Slot::Ready(cached, last_used)) = lock(&self.entries).get_mut(&key) {
+      if Arc::ptr_eq(cached, &entry) {
+        *last_used = self.tick();
+      }
+    }
+
+    Some(entry)
+  }
+
+  /// Marks queries which could be affected by a schema change as stale ones,
+  /// so they are prepared again against a new schema. A change of a type, a function
+  /// or an aggregate may affect any query of a keyspace.
+  pub fn invalidate(&self, change: &SchemaChange) {
+    if change.change_type == SchemaChangeType::Created {
+      return;
+    }
+
+    match &change.target {
+      SchemaChangeTarget::Table { keyspace, table } => self.remove_table(keyspace, table),
+      target => self.remove_keyspace(target.keyspace()),
+    }
+  }
+
+  /// Marks queries which refer to a given keyspace as stale ones.
+  pub fn remove_keyspace(&self, keyspace: &str) {
+    self.remove_where(|entry| refers_to(entry, keyspace, None));
+  }
+
+  /// Marks queries which refer to a given table or view as stale ones.
+  pub fn remove_table(&self, keyspace: &str, table: &str) {
+    self.remove_where(|entry| refers_to(entry, keyspace, Some(table)));
+  }
+
+  /// Removes prepared queries which match a given predicate, their ids
+  /// are kept. Queries which are being prepared are kept.
+  fn remove_where<P: Fn(&PreparedStatement) -> bool>(&self, predicate: P) {
+    lock(&self.entries).retain(|_, slot| match slot {
+      Slot::Ready(entry, _) => !predicate(entry),
+      Slot::Preparing(_) => true,
+    });
+  }
+}
+
+/// Checks that a prepared query is not a stale one.
+fn is_cached(entries: &HashMap<CacheKey, Slot>, entry: &Arc<PreparedStatement>) -> bool {
+  let key = CacheKey {
+    keyspace: entry.keyspace().map(str::to_string),
+    query: entry.query().to_string(),
+  };
+
+  match entries.get(&key) {
+    Some(Slot::Ready(cached, _)) => Arc::ptr_eq(cached, entry),
+    _ => false,
+  }
+}
+
+/// Checks that a query was prepared in a keyspace or that its bind markers
+/// or result columns belong to a keyspace. A query which columns are unknown
+/// is considered to refer to its keyspace only.
+// `Option::is_none_or` would require Rust 1.82
+#[allow(clippy::unnecessary_map_or)]
+fn refers_to(entry: &PreparedStatement, keyspace: &str, table: Option<&str>) -> bool {
+  let mut columns = entry.bind_columns().iter().chain(entry.result_columns());
+  let refers_to_column = columns.any(|column| {
+    column.keyspace() == Some(keyspace) && table.map_or(true, |table| column.table() == Some(table))
+  });
+
+  refers_to_column || (table.is_none() && entry.keyspace() == Some(keyspace))
+}
+
+/// Releases a slot of a query which preparation has failed
+/// or was abandoned.
+struct PreparingGuard<'a> {
+  cache: &'a PreparedCache,
+  key: &'a CacheKey,
+}
+
+impl<'a> Drop for PreparingGuard<'a> {
+  fn drop(&mut self) {
+    let mut entries = lock(&self.cache.entries);
+    if let Some(Slot::Preparing(_)) = entries.get(self.key) {
+      entries.remove(self.key);
+    }
+  }
+}
+
+/// Returns an id of a prepared query which a server does not know,
+/// e.g. because it was restarted after the query had been prepared.
+pub(crate) fn unprepared_id(err: &error::Error) -> Option<Vec<u8>> {
+  match err {
+    error::Error::Unprepared { id, .. } => Some(id.clone()),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use cassandra_proto::frame::{frame_error::CDRSError, FromCursor};
+  use futures::{executor::block_on, future::join3};
+  use std::{
+    io::Cursor,
+    sync::atomic::{AtomicUsize, Ordering},
+  };
+
+  fn prepared(id: u8) -> BodyResResultPrepared {
+    // id followed by empty metadata of bind markers and of result columns
+    let mut bytes = vec![0, 1, id];
+    bytes.extend_from_slice(&[0; 20]);
+    BodyResResultPrepared::from_cursor(&mut Cursor::new(&bytes)).unwrap()
+  }
+
+  /// Prepared query which has a single bind marker of a given table.
+  fn prepared_table(id: u8, keyspace: &str, table: &str) -> BodyResResultPrepared {
+    let mut bytes = vec![0, 1, id];
+    // global tables spec flag, one column and no partition key
+    bytes.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0]);
+    for name in &[keyspace, table, "c"] {
+      bytes.extend_from_slice(&(name.len() as u16).to_be_bytes());
+      bytes.extend_from_slice(name.as_bytes());
+    }
+    // int column followed by empty metadata of result columns
+    bytes.extend_from_slice(&[0, 9]);
+    bytes.extend_from_slice(&[0; 8]);
+    BodyResResultPrepared::from_cursor(&mut Cursor::new(&bytes)).unwrap()
+  }
+
+  fn is_stale(cache: &PreparedCache, id: u8) -> bool {
+    let entry = cache.get_by_id(&[id]).expect("should find a query by id");
+    !is_cached(&lock(&cache.entries), &entry)
+  }
+
+  fn id_of(entry: &PreparedStatement) -> Vec<u8> {
+    entry.id().clone().into_plain().unwrap()
+  }
+
+  #[test]
+  fn test_prepare_once() {
+    let cache = PreparedCache::new();
+    let calls = AtomicUsize::new(0);
+    let (open, gate) = oneshot::channel::<()>();
+    let gate = gate.shared();
+    let prepare = || {
+      calls.fetch_add(1, Ordering::SeqCst);
+      let gate = gate.clone();
+      async move {
+        let _ = gate.await;
+        Ok(prepared(1))
+      }
+    };
+
+    let (first, second, _) = block_on(join3(
+      cache.get_or_prepare(None, "SELECT".into(), prepare),
+      cache.get_or_prepare(None, "SELECT".into(), prepare),
+      async { open.send(()) },
+    ));
+    assert_eq!(id_of(&first.unwrap()), vec![1]);
+    assert_eq!(id_of(&second.unwrap()), vec![1]);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let other_keyspace =
+      block_on(cache.get_or_prepare(Some("ks".into()), "SELECT".into(), prepare));
+    assert!(other_keyspace.is_ok());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+  }
+
+  #[test]
+  fn test_failed_preparation_is_not_cached() {
+    let cache = PreparedCache::new();
+    let failed = block_on(cache.get_or_prepare(None, "SELECT".into(), || async {
+      Err("cannot prepare".into())
+    }));
+    assert!(failed.is_err());
+
+    let entry =
+      block_on(cache.get_or_prepare(None, "SELECT".into(), || async { Ok(prepared(2)) })).unwrap();
+    assert_eq!(id_of(&entry), vec![2]);
+  }
+
+  #[test]
+  fn test_get_by_id() {
+    let cache = PreparedCache::new();
+    cache.insert(Some("ks".into()), "SELECT".into(), prepared(3));
+
+    let entry = cache.get_by_id(&[3]).unwrap();
+    assert_eq!(entry.query(), "SELECT");
+    assert_eq!(entry.keyspace(), Some("ks"));
+    assert!(cache.get_by_id(&[4]).is_none());
+  }
+
+  #[test]
+  fn test_unprepared_id() {
+    // UNPREPARED error code, message and unknown id
+    let bytes = [0, 0, 0x25, 0, 0, 1, b'?', 0, 1, 5];
+    let body = CDRSError::from_cursor(&mut Cursor::new(&bytes)).unwrap();
+
+    assert_eq!(unprepared_id(&body.into()), Some(vec![5]));
+    assert_eq!(unprepared_id(&"error".into()), None);
+  }
+
+  #[test]
+  fn test_least_recently_used_is_evicted() {
+    let cache = PreparedCache::with_capacity(2);
+    cache.insert(None, "SELECT 1".into(), prepared(1));
+    cache.insert(None, "SELECT 2".into(), prepared(2));
+    assert!(!is_stale(&cache, 1));
+
+    cache.insert(None, "SELECT 3".into(), prepared(3));
+    assert!(!is_stale(&cache, 1));
+    assert!(is_stale(&cache, 2));
+    assert!(!is_stale(&cache, 3));
+  }
+
+  #[test]
+  fn test_remove() {
+    let cache = PreparedCache::new();
+    cache.insert(Some("ks".into()), "SELECT 1".into(), prepared(1));
+    cache.insert(None, "SELECT 2".into(), prepared_table(2, "ks", "a"));
+    cache.insert(None, "SELECT 3".into(), prepared_table(3, "ks", "b"));
+    cache.insert(None, "SELECT 4".into(), prepared_table(4, "other", "a"));
+
+    cache.remove_table("ks", "a");
+    assert!(!is_stale(&cache, 1));
+    assert!(is_stale(&cache, 2));
+    assert!(!is_stale(&cache, 3));
+
+    cache.remove_keyspace("ks");
+    assert!(is_stale(&cache, 1));
+    assert!(is_stale(&cache, 3));
+    assert!(!is_stale(&cache, 4));
+  }
+
+  #[test]
+  fn test_invalidate() {
+    let cache = PreparedCache::new();
+    cache.insert(None, "SELECT 1".into(), prepared_table(1, "ks", "a"));
+    cache.insert(None, "SELECT 2".into(), prepared_table(2, "ks", "b"));
+    let change = |change_type, target| SchemaChange {
+      change_type,
+      target,
+    };
+
+    cache.invalidate(&change(
+      SchemaChangeType::Created,
+      SchemaChangeTarget::Keyspace("ks".into()),
+    ));
+    assert!(!is_stale(&cache, 1));
+
+    cache.invalidate(&change(
+      SchemaChangeType::Updated,
+      SchemaChangeTarget::Table {
+        keyspace: "ks".into(),
+        table: "a".into(),
+      },
+    ));
+    assert!(is_stale(&cache, 1));
+    assert!(!is_stale(&cache, 2));
+    let calls = AtomicUsize::new(0);
+    let entry = block_on(cache.get_or_prepare(None, "SELECT 1".into(), || {
+      calls.fetch_add(1, Ordering::SeqCst);
+      async { Ok(prepared_table(1, "ks", "a")) }
+    }))
+    .unwrap();
+    assert_eq!(
+      calls.load(Ordering::SeqCst),
+      1,
+      "should prepare a stale query again"
+    );
+    assert_eq!(id_of(&entry), vec![1]);
+    assert!(!is_stale(&cache, 1));
+
+    cache.invalidate(&change(
+      SchemaChangeType::Dropped,
+      SchemaChangeTarget::Type {
+        keyspace: "ks".into(),
+        name: "address".into(),
+      },
+    ));
+    assert!(is_stale(&cache, 2));
+  }
+}
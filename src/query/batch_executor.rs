@@ -1,20 +1,34 @@
-use std::pin::Pin;
-
 use async_trait::async_trait;
-use cassandra_proto::{error, frame::Frame, query::QueryBatch};
+use cassandra_proto::{frame::Frame, query::QueryBatch};
+
+use super::QueryOptions;
+use crate::error;
 
 /// Traits that provides methods for sending multiple queries
 /// to a DB server.
 #[async_trait]
-pub trait BatchExecutor: Send {
+pub trait BatchExecutor: Send + Sync {
+  /// Sends a batch with given options of the request, e.g. a timeout
+  /// which overrides one of a session.
+  async fn batch_with_options(
+    &self,
+    batch: QueryBatch,
+    options: QueryOptions,
+  ) -> error::Result<Frame>;
+
   async fn batch_with_params_tw(
-    mut self: Pin<&mut Self>,
+    &self,
     batch: QueryBatch,
     with_tracing: bool,
     with_warnings: bool,
-  ) -> error::Result<Frame>;
+  ) -> error::Result<Frame> {
+    let options = QueryOptions::new()
+      .tracing(with_tracing)
+      .warnings(with_warnings);
+    self.batch_with_options(batch, options).await
+  }
 
-  async fn batch_with_params(mut self: Pin<&mut Self>, batch: QueryBatch) -> error::Result<Frame> {
+  async fn batch_with_params(&self, batch: QueryBatch) -> error::Result<Frame> {
     self.batch_with_params_tw(batch, false, false).await
   }
 }
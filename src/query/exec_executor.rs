@@ -1,29 +1,46 @@
-use std::pin::Pin;
-
 use async_trait::async_trait;
 use cassandra_proto::{
-  error,
   frame::Frame,
   query::{QueryParams, QueryParamsBuilder, QueryValues},
   types::CBytesShort,
 };
 
+use super::{PreparedStatement, QueryOptions};
+
+use crate::error;
+
 /// Prepared query ID.
 pub type PreparedQuery = CBytesShort;
 
 /// Traits that provides methods for prepared query execution.
 #[async_trait]
-pub trait ExecExecutor: Send {
+pub trait ExecExecutor: Send + Sync {
+  /// Executes a prepared query with given parameters and options
+  /// of the request, e.g. a timeout which overrides one of a session.
+  async fn exec_with_options(
+    &self,
+    prepared: &PreparedQuery,
+    query_parameters: QueryParams,
+    options: QueryOptions,
+  ) -> error::Result<Frame>;
+
   async fn exec_with_params_tw(
-    mut self: Pin<&mut Self>,
+    &self,
     prepared: &PreparedQuery,
     query_parameters: QueryParams,
     with_tracing: bool,
     with_warnings: bool,
-  ) -> error::Result<Frame>;
+  ) -> error::Result<Frame> {
+    let options = QueryOptions::new()
+      .tracing(with_tracing)
+      .warnings(with_warnings);
+    self
+      .exec_with_options(prepared, query_parameters, options)
+      .await
+  }
 
   async fn exec_with_params(
-    mut self: Pin<&mut Self>,
+    &self,
     prepared: &PreparedQuery,
     query_parameters: QueryParams,
   ) -> error::Result<Frame> {
@@ -33,7 +50,7 @@ pub trait ExecExecutor: Send {
   }
 
   async fn exec_with_values_tw<V: Into<QueryValues> + Send>(
-    mut self: Pin<&mut Self>,
+    &self,
     prepared: &PreparedQuery,
     values: V,
     with_tracing: bool,
@@ -47,7 +64,7 @@ pub trait ExecExecutor: Send {
   }
 
   async fn exec_with_values<V: Into<QueryValues> + Send>(
-    mut self: Pin<&mut Self>,
+    &self,
     prepared: &PreparedQuery,
     values: V,
   ) -> error::Result<Frame> {
@@ -57,7 +74,7 @@ pub trait ExecExecutor: Send {
   }
 
   async fn exec_tw(
-    mut self: Pin<&mut Self>,
+    &self,
     prepared: &PreparedQuery,
     with_tracing: bool,
     with_warnings: bool,
@@ -68,7 +85,63 @@ pub trait ExecExecutor: Send {
       .await
   }
 
-  async fn exec(mut self: Pin<&mut Self>, prepared: &PreparedQuery) -> error::Result<Frame> {
+  async fn exec(&self, prepared: &PreparedQuery) -> error::Result<Frame> {
     self.exec_tw(prepared, false, false).await
   }
+
+  /// Executes a prepared statement with given options of the request.
+  /// Values of query parameters are checked against bind markers
+  /// of the statement before they are sent. If a server does not know
+  /// the statement any more, it is prepared again from its query.
+  async fn exec_statement_with_options(
+    &self,
+    statement: &PreparedStatement,
+    query_parameters: QueryParams,
+    options: QueryOptions,
+  ) -> error::Result<Frame> {
+    let query_parameters = statement.bind_params(query_parameters)?;
+    self
+      .exec_with_options(statement.id(), query_parameters, options)
+      .await
+  }
+
+  /// Executes a prepared statement. Values of query parameters are checked
+  /// against bind markers of the statement before they are sent.
+  async fn exec_statement_with_params_tw(
+    &self,
+    statement: &PreparedStatement,
+    query_parameters: QueryParams,
+    with_tracing: bool,
+    with_warnings: bool,
+  ) -> error::Result<Frame> {
+    let options = QueryOptions::new()
+      .tracing(with_tracing)
+      .warnings(with_warnings);
+    self
+      .exec_statement_with_options(statement, query_parameters, options)
+      .await
+  }
+
+  async fn exec_statement_with_params(
+    &self,
+    statement: &PreparedStatement,
+    query_parameters: QueryParams,
+  ) -> error::Result<Frame> {
+    self
+      .exec_statement_with_params_tw(statement, query_parameters, false, false)
+      .await
+  }
+
+  /// Executes a prepared statement with values which are checked
+  /// against its bind markers.
+  async fn exec_statement<V: Into<QueryValues> + Send>(
+    &self,
+    statement: &PreparedStatement,
+    values: V,
+  ) -> error::Result<Frame> {
+    let query_params = QueryParamsBuilder::new().values(values.into()).finalize();
+    self
+      .exec_statement_with_params(statement, query_params)
+      .await
+  }
 }
@@ -1,9 +1,11 @@
 mod batch_executor;
 mod exec_executor;
 mod prepare_executor;
+mod prepared_statement;
 mod query;
 mod query_executor;
 mod query_flags;
+mod query_options;
 mod query_params;
 mod query_params_builder;
 mod query_values;
@@ -11,9 +13,11 @@ mod query_values;
 pub use batch_executor::BatchExecutor;
 pub use exec_executor::ExecExecutor;
 pub use prepare_executor::{PrepareExecutor, PreparedQuery};
+pub use prepared_statement::{BatchStatementExt, ColumnSpec, PreparedStatement};
 pub use query::Query;
 pub use query_executor::QueryExecutor;
 pub use query_flags::QueryFlags;
+pub use query_options::QueryOptions;
 pub use query_params::QueryParams;
 pub use query_params_builder::QueryParamsBuilder;
 pub use query_values::QueryValues;
@@ -1,7 +1,8 @@
-use std::pin::Pin;
-
 use async_trait::async_trait;
-use cassandra_proto::{error, types::CBytesShort};
+use cassandra_proto::types::CBytesShort;
+
+use super::PreparedStatement;
+use crate::error;
 
 /// Id of a prepared query. This Id can be used for
 /// query execution and/or query batching.
@@ -10,22 +11,42 @@ pub type PreparedQuery = CBytesShort;
 /// Traits that provides methods for preparing queries
 /// on a DB server.
 #[async_trait]
-pub trait PrepareExecutor {
+pub trait PrepareExecutor: Send + Sync {
   /// It prepares a query for execution, along with query itself
   /// the method takes `with_tracing` and `with_warnings` flags
-  /// to get tracing information and warnings.
-  async fn prepare_tw<Q: ToString + Send>(
-    mut self: Pin<&mut Self>,
+  /// to get tracing information and warnings. Prepared statement keeps
+  /// metadata of bind markers, so values could be checked before execution.
+  async fn prepare_statement_tw<Q: ToString + Send>(
+    &self,
     query: Q,
     with_tracing: bool,
     with_warnings: bool,
-  ) -> error::Result<PreparedQuery>;
+  ) -> error::Result<PreparedStatement>;
 
-  /// It prepares query without additional tracing information and warnings.
-  async fn prepare<Q: ToString + Send>(
-    mut self: Pin<&mut Self>,
+  /// It prepares a statement without additional tracing information and warnings.
+  async fn prepare_statement<Q: ToString + Send>(
+    &self,
+    query: Q,
+  ) -> error::Result<PreparedStatement> {
+    self.prepare_statement_tw(query, false, false).await
+  }
+
+  /// It prepares a query for execution and returns only its id.
+  async fn prepare_tw<Q: ToString + Send>(
+    &self,
     query: Q,
+    with_tracing: bool,
+    with_warnings: bool,
   ) -> error::Result<PreparedQuery> {
+    let statement = self
+      .prepare_statement_tw(query, with_tracing, with_warnings)
+      .await?;
+
+    Ok(statement.id().clone())
+  }
+
+  /// It prepares query without additional tracing information and warnings.
+  async fn prepare<Q: ToString + Send>(&self, query: Q) -> error::Result<PreparedQuery> {
     self.prepare_tw(query, false, false).await
   }
 }
@@ -0,0 +1,577 @@
+use std::collections::HashMap;
+
+use cassandra_proto::{
+  frame::frame_result::{
+    BodyResResultPrepared, ColSpec, ColType, ColTypeOption, ColTypeOptionValue,
+  },
+  query::{BatchQueryBuilder, QueryFlags, QueryParams, QueryValues},
+  types::{
+    value::{Value, ValueType},
+    CString,
+  },
+};
+
+use super::PreparedQuery;
+use crate::{
+  error,
+  load_balancing::RoutingInfo,
+  token::{routing_key, PartitionToken, Partitioner},
+};
+
+/// Column which a bind marker or a result row refers to.
+#[derive(Debug, Clone)]
+pub struct ColumnSpec {
+  keyspace: Option<String>,
+  table: Option<String>,
+  name: String,
+  col_type: ColTypeOption,
+}
+
+impl ColumnSpec {
+  fn new(col_spec: &ColSpec, global_table_spec: Option<(&CString, &CString)>) -> Self {
+    let (keyspace, table) = match (&col_spec.ksname, &col_spec.tablename, global_table_spec) {
+      (Some(keyspace), Some(table), _) | (_, _, Some((keyspace, table))) => (
+        Some(keyspace.as_str().to_string()),
+        Some(table.as_str().to_string()),
+      ),
+      _ => (None, None),
+    };
+
+    ColumnSpec {
+      keyspace,
+      table,
+      name: col_spec.name.as_str().to_string(),
+      col_type: col_spec.col_type.clone(),
+    }
+  }
+
+  pub fn keyspace(&self) -> Option<&str> {
+    self.keyspace.as_deref()
+  }
+
+  pub fn table(&self) -> Option<&str> {
+    self.table.as_deref()
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn col_type(&self) -> &ColTypeOption {
+    &self.col_type
+  }
+}
+
+/// Query which was prepared by a server. Along with an id it keeps metadata
+/// of bind markers, so values could be checked before they are sent.
+#[derive(Debug, Clone)]
+pub struct PreparedStatement {
+  id: PreparedQuery,
+  query: String,
+  keyspace: Option<String>,
+  bind_columns: Vec<ColumnSpec>,
+  pk_indexes: Vec<usize>,
+  result_columns: Vec<ColumnSpec>,
+}
+
+impl PreparedStatement {
+  /// Creates a statement from a response to PREPARE request. A keyspace
+  /// is the one which was used by a connection the query was prepared on.
+  pub(crate) fn new(
+    keyspace: Option<String>,
+    query: String,
+    prepared: BodyResResultPrepared,
+  ) -> Self {
+    let metadata = prepared.metadata;
+    let global_table_spec = metadata
+      .global_table_spec
+      .as_ref()
+      .map(|(keyspace, table)| (keyspace, table));
+    let result_metadata = prepared.result_metadata;
+    let result_global_table_spec = result_metadata
+      .global_table_space
+      .as_ref()
+      .filter(|spec| spec.len() == 2)
+      .map(|spec| (&spec[0], &spec[1]));
+
+    PreparedStatement {
+      id: prepared.id,
+      query,
+      keyspace,
+      bind_columns: metadata
+        .col_specs
+        .iter()
+        .map(|col_spec| ColumnSpec::new(col_spec, global_table_spec))
+        .collect(),
+      pk_indexes: metadata
+        .pk_indexes
+        .iter()
+        .map(|index| *index as usize)
+        .collect(),
+      result_columns: result_metadata
+        .col_specs
+        .iter()
+        .map(|col_spec| ColumnSpec::new(col_spec, result_global_table_spec))
+        .collect(),
+    }
+  }
+
+  /// Id which a server knows the statement by.
+  pub fn id(&self) -> &PreparedQuery {
+    &self.id
+  }
+
+  pub fn query(&self) -> &str {
+    &self.query
+  }
+
+  /// Keyspace which was set for a connection when the statement was prepared.
+  pub fn keyspace(&self) -> Option<&str> {
+    self.keyspace.as_deref()
+  }
+
+  /// Columns of bind markers in the order they appear in a query.
+  pub fn bind_columns(&self) -> &[ColumnSpec] {
+    &self.bind_columns
+  }
+
+  /// Indexes of bind markers which make up a partition key. They are
+  /// known only if all partition key columns are bound.
+  pub fn pk_indexes(&self) -> &[usize] {
+    &self.pk_indexes
+  }
+
+  /// Columns of rows which the statement returns. They may be unknown
+  /// if a server did not send metadata of a result.
+  pub fn result_columns(&self) -> &[ColumnSpec] {
+    &self.result_columns
+  }
+
+  /// Checks values against types of bind markers and returns them in the order
+  /// of bind markers. Named values are bound by names of bind markers,
+  /// the ones which are not provided are left unset.
+  pub fn bind<V: Into<QueryValues>>(&self, values: V) -> error::Result<QueryValues> {
+    let values = match values.into() {
+      QueryValues::SimpleValues(values) => {
+        if values.len() != self.bind_columns.len() {
+          return Err(
+            format!(
+              "Statement expects {} values but {} were provided",
+              self.bind_columns.len(),
+              values.len()
+            )
+            .into(),
+          );
+        }
+
+        values
+      }
+      QueryValues::NamedValues(values) => self.order_values(values)?,
+    };
+
+    for (column, value) in self.bind_columns.iter().zip(values.iter()) {
+      check_value(column, value)?;
+    }
+
+    Ok(QueryValues::SimpleValues(values))
+  }
+
+  /// Binds values of query parameters, see `bind`.
+  pub fn bind_params(&self, mut params: QueryParams) -> error::Result<QueryParams> {
+    if let Some(values) = params.values.take() {
+      params.values = Some(self.bind(values)?);
+      params.with_names = Some(false);
+      params
+        .flags
+        .retain(|flag| !matches!(flag, QueryFlags::WithNamesForValues));
+    }
+
+    Ok(params)
+  }
+
+  /// Serializes values of partition key columns into a routing key.
+  /// Values should be given as they are bound to the statement.
+  pub fn routing_key(&self, values: &QueryValues) -> error::Result<Vec<u8>> {
+    if self.pk_indexes.is_empty() {
+      return Err("Partition key of the statement is unknown".into());
+    }
+
+    let components = self
+      .pk_indexes
+      .iter()
+      .map(|index| {
+        let value = match values {
+          QueryValues::SimpleValues(values) => values.get(*index),
+          QueryValues::NamedValues(values) => self
+            .bind_columns
+            .get(*index)
+            .and_then(|column| values.get(column.name())),
+        };
+
+        value.ok_or_else(|| error::Error::from("Partition key value is not provided"))
+      })
+      .collect::<error::Result<Vec<&Value>>>()?;
+
+    routing_key(components)
+  }
+
+  /// Computes a token of a partition which values of the statement refer to.
+  pub fn token(
+    &self,
+    values: &QueryValues,
+    partitioner: Partitioner,
+  ) -> error::Result<PartitionToken> {
+    self.routing_key(values).map(|key| partitioner.token(&key))
+  }
+
+  /// Returns routing information for token aware load balancing.
+  pub fn routing_info(&self, values: &QueryValues) -> error::Result<RoutingInfo> {
+    self
+      .routing_key(values)
+      .map(|key| RoutingInfo::with_routing_key(&key))
+  }
+
+  fn order_values(&self, mut values: HashMap<String, Value>) -> error::Result<Vec<Value>> {
+    let ordered = self
+      .bind_columns
+      .iter()
+      .map(|column| {
+        values
+          .get(column.name())
+          .cloned()
+          .unwrap_or_else(Value::new_not_set)
+      })
+      .collect();
+
+    values.retain(|name, _| !self.bind_columns.iter().any(|column| column.name() == name));
+    match values.keys().next() {
+      Some(name) => Err(format!("Statement has no bind marker named {}", name).into()),
+      None => Ok(ordered),
+    }
+  }
+}
+
+/// Adds prepared statements to batches.
+pub trait BatchStatementExt: Sized {
+  /// Adds a prepared statement with values which are checked by `bind`.
+  fn add_statement<V: Into<QueryValues>>(
+    self,
+    statement: &PreparedStatement,
+    values: V,
+  ) -> error::Result<Self>;
+}
+
+impl BatchStatementExt for BatchQueryBuilder {
+  fn add_statement<V: Into<QueryValues>>(
+    self,
+    statement: &PreparedStatement,
+    values: V,
+  ) -> error::Result<Self> {
+    let values = statement.bind(values)?;
+
+    Ok(self.add_query_prepared(statement.id().clone(), values))
+  }
+}
+
+/// Checks that a serialized value could be of a column type. Values of types
+/// of fixed size and text encodings are checked, as well as elements
+/// of collections, tuples and user defined types. Blobs, varints
+/// and custom types could be any bytes.
+fn check_value(column: &ColumnSpec, value: &Value) -> error::Result<()> {
+  if let ValueType::Null | ValueType::NotSet = value.value_type {
+    return Ok(());
+  }
+
+  let bytes = &value.body;
+  if is_valid_value(&column.col_type, bytes) {
+    Ok(())
+  } else {
+    Err(
+      format!(
+        "Value of {} bytes does not match type {:?} of bind marker {}",
+        bytes.len(),
+        column.col_type.id,
+        column.name
+      )
+      .into(),
+    )
+  }
+}
+
+/// Checks a serialized value which is not null against a given type.
+fn is_valid_value(col_type: &ColTypeOption, bytes: &[u8]) -> bool {
+  match (&col_type.id, &col_type.value) {
+    (ColType::List, Some(ColTypeOptionValue::CList(element)))
+    | (ColType::Set, Some(ColTypeOptionValue::CSet(element))) => {
+      is_valid_collection(bytes, &[element.as_ref()])
+    }
+    (ColType::Map, Some(ColTypeOptionValue::CMap((key, value)))) => {
+      is_valid_collection(bytes, &[key.as_ref(), value.as_ref()])
+    }
+    (ColType::Tuple, Some(ColTypeOptionValue::TupleType(tuple))) => {
+      is_valid_fields(bytes, tuple.types.iter())
+    }
+    (ColType::Udt, Some(ColTypeOptionValue::UdtType(udt))) => {
+      is_valid_fields(bytes, udt.descriptions.iter().map(|(_, field)| field))
+    }
+    (ColType::Bigint, _)
+    | (ColType::Counter, _)
+    | (ColType::Double, _)
+    | (ColType::Timestamp, _)
+    | (ColType::Time, _) => bytes.len() == 8,
+    (ColType::Int, _) | (ColType::Float, _) | (ColType::Date, _) => bytes.len() == 4,
+    (ColType::Smallint, _) => bytes.len() == 2,
+    (ColType::Tinyint, _) | (ColType::Boolean, _) => bytes.len() == 1,
+    (ColType::Uuid, _) | (ColType::Timeuuid, _) => bytes.len() == 16,
+    (ColType::Inet, _) => bytes.len() == 4 || bytes.len() == 16,
+    // a scale is followed by an unscaled varint
+    (ColType::Decimal, _) => bytes.len() >= 4,
+    (ColType::Ascii, _) => bytes.is_ascii(),
+    (ColType::Varchar, _) => std::str::from_utf8(bytes).is_ok(),
+    _ => true,
+  }
+}
+
+/// Checks a list, a set or a map: a number of elements is followed by
+/// the elements, each of a map is a key followed by a value. Elements
+/// of collections could not be null.
+fn is_valid_collection(mut bytes: &[u8], types: &[&ColTypeOption]) -> bool {
+  let count = match read_int(&mut bytes) {
+    Some(count) if count >= 0 => count as usize,
+    _ => return false,
+  };
+
+  for col_type in types.iter().cycle().take(count.saturating_mul(types.len())) {
+    match read_element(&mut bytes) {
+      Some(Some(element)) if is_valid_value(col_type, element) => {}
+      _ => return false,
+    }
+  }
+
+  bytes.is_empty()
+}
+
+/// Checks fields of a tuple or a user defined type. Fields could be null
+/// and trailing ones could be omitted.
+fn is_valid_fields<'a, I>(mut bytes: &[u8], types: I) -> bool
+where
+  I: Iterator<Item = &'a ColTypeOption>,
+{
+  for col_type in types {
+    if bytes.is_empty() {
+      break;
+    }
+    match read_element(&mut bytes) {
+      Some(Some(element)) if !is_valid_value(col_type, element) => return false,
+      Some(_) => {}
+      None => return false,
+    }
+  }
+
+  bytes.is_empty()
+}
+
+fn read_int(bytes: &mut &[u8]) -> Option<i32> {
+  if bytes.len() < 4 {
+    return None;
+  }
+
+  let (int, rest) = bytes.split_at(4);
+  *bytes = rest;
+  Some(i32::from_be_bytes([int[0], int[1], int[2], int[3]]))
+}
+
+/// Reads an element which is prefixed by its length. Returns `Some(None)`
+/// for a null element and `None` if bytes are truncated.
+fn read_element<'a>(bytes: &mut &'a [u8]) -> Option<Option<&'a [u8]>> {
+  let len = read_int(bytes)?;
+  if len < 0 {
+    return Some(None);
+  }
+
+  let len = len as usize;
+  if bytes.len() < len {
+    return None;
+  }
+  let (element, rest) = bytes.split_at(len);
+  *bytes = rest;
+  Some(Some(element))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::token::Token;
+  use cassandra_proto::{frame::FromCursor, types::value::Bytes};
+  use std::io::Cursor;
+
+  /// Statement `... WHERE id = ? AND name = ?` where `id` is int
+  /// partition key and `name` is text.
+  fn statement() -> PreparedStatement {
+    let mut bytes = vec![0, 1, 7];
+    // global table spec flag, 2 columns, 1 partition key column at index 0
+    bytes.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0]);
+    bytes.extend_from_slice(&[0, 2, b'k', b's', 0, 2, b't', b'b']);
+    bytes.extend_from_slice(&[0, 2, b'i', b'd', 0, 0x09]);
+    bytes.extend_from_slice(&[0, 4, b'n', b'a', b'm', b'e', 0, 0x0D]);
+    // no result metadata
+    bytes.extend_from_slice(&[0, 0, 0, 4, 0, 0, 0, 0]);
+    let prepared = BodyResResultPrepared::from_cursor(&mut Cursor::new(&bytes)).unwrap();
+
+    PreparedStatement::new(None, "SELECT".into(), prepared)
+  }
+
+  fn bodies(values: QueryValues) -> Vec<Vec<u8>> {
+    match values {
+      QueryValues::SimpleValues(values) => values.into_iter().map(|value| value.body).collect(),
+      QueryValues::NamedValues(_) => panic!("values should be positional"),
+    }
+  }
+
+  #[test]
+  fn test_metadata() {
+    let statement = statement();
+    let names: Vec<&str> = statement
+      .bind_columns()
+      .iter()
+      .map(ColumnSpec::name)
+      .collect();
+
+    assert_eq!(names, vec!["id", "name"]);
+    assert_eq!(statement.bind_columns()[0].keyspace(), Some("ks"));
+    assert_eq!(statement.bind_columns()[1].table(), Some("tb"));
+    assert_eq!(statement.pk_indexes(), &[0]);
+    assert!(statement.result_columns().is_empty());
+  }
+
+  #[test]
+  fn test_bind_positional() {
+    let statement = statement();
+    let values: Vec<Value> = vec![1i32.into(), "a".into()];
+    assert_eq!(
+      bodies(statement.bind(values).unwrap()),
+      vec![vec![0, 0, 0, 1], b"a".to_vec()]
+    );
+
+    let values: Vec<Value> = vec![1i32.into()];
+    assert!(statement.bind(values).is_err());
+    let values: Vec<Value> = vec![1i64.into(), "a".into()];
+    assert!(statement.bind(values).is_err());
+  }
+
+  #[test]
+  fn test_routing_key() {
+    let statement = statement();
+    let values = QueryValues::SimpleValues(vec![1i32.into(), "a".into()]);
+    assert_eq!(statement.routing_key(&values).unwrap(), vec![0, 0, 0, 1]);
+    assert_eq!(
+      statement.token(&values, Partitioner::Murmur3).unwrap(),
+      PartitionToken::Murmur3(Token(-4_069_959_284_402_364_209))
+    );
+    assert_eq!(
+      statement
+        .routing_info(&values)
+        .unwrap()
+        .token(Partitioner::Random),
+      Some(Partitioner::Random.token(&[0, 0, 0, 1]))
+    );
+
+    let mut named: HashMap<String, Value> = HashMap::new();
+    named.insert("id".into(), 1i32.into());
+    assert_eq!(
+      statement
+        .routing_key(&QueryValues::NamedValues(named))
+        .unwrap(),
+      vec![0, 0, 0, 1]
+    );
+    assert!(statement
+      .routing_key(&QueryValues::SimpleValues(vec![]))
+      .is_err());
+  }
+
+  #[test]
+  fn test_bind_named() {
+    let statement = statement();
+    let mut values: HashMap<&str, Value> = HashMap::new();
+    values.insert("name", "a".into());
+    let bound = statement.bind(values).unwrap();
+
+    match bound {
+      QueryValues::SimpleValues(values) => {
+        assert!(matches!(values[0].value_type, ValueType::NotSet));
+        assert_eq!(values[1].body, b"a".to_vec());
+      }
+      QueryValues::NamedValues(_) => panic!("values should be positional"),
+    }
+
+    let mut values: HashMap<&str, Value> = HashMap::new();
+    values.insert("unknown", "a".into());
+    assert!(statement.bind(values).is_err());
+  }
+
+  #[test]
+  fn test_check_nested_values() {
+    let simple = |id| ColTypeOption { id, value: None };
+    let column = |col_type| ColumnSpec {
+      keyspace: None,
+      table: None,
+      name: "c".into(),
+      col_type,
+    };
+    let list = column(ColTypeOption {
+      id: ColType::List,
+      value: Some(ColTypeOptionValue::CList(Box::new(simple(ColType::Int)))),
+    });
+    let map = column(ColTypeOption {
+      id: ColType::Map,
+      value: Some(ColTypeOptionValue::CMap((
+        Box::new(simple(ColType::Varchar)),
+        Box::new(simple(ColType::Bigint)),
+      ))),
+    });
+
+    assert!(check_value(&list, &vec![1i32, 2].into()).is_ok());
+    assert!(check_value(&list, &vec![1i64].into()).is_err());
+    assert!(check_value(&list, &Value::new_normal(Bytes::new(vec![0, 0, 0, 1]))).is_err());
+
+    let mut entries: HashMap<String, i64> = HashMap::new();
+    entries.insert("a".into(), 1);
+    assert!(check_value(&map, &entries.into()).is_ok());
+    let mut entries: HashMap<String, i32> = HashMap::new();
+    entries.insert("a".into(), 1);
+    assert!(check_value(&map, &entries.into()).is_err());
+  }
+
+  #[test]
+  fn test_check_tuple_fields() {
+    let tuple = ColumnSpec {
+      keyspace: None,
+      table: None,
+      name: "t".into(),
+      col_type: ColTypeOption {
+        id: ColType::Tuple,
+        value: Some(ColTypeOptionValue::TupleType(
+          cassandra_proto::frame::frame_result::CTuple {
+            types: vec![
+              ColTypeOption {
+                id: ColType::Int,
+                value: None,
+              },
+              ColTypeOption {
+                id: ColType::Varchar,
+                value: None,
+              },
+            ],
+          },
+        )),
+      },
+    };
+    let check = |body: Vec<u8>| check_value(&tuple, &Value::new_normal(Bytes::new(body)));
+
+    assert!(check(vec![0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0, 1, b'a']).is_ok());
+    // a null field and an omitted trailing one
+    assert!(check(vec![0xFF, 0xFF, 0xFF, 0xFF]).is_ok());
+    assert!(check(vec![0, 0, 0, 2, 0, 1]).is_err());
+    assert!(check(vec![0, 0, 0, 4, 0, 0, 0]).is_err());
+  }
+}
@@ -1,33 +1,50 @@
-use std::pin::Pin;
-
 use async_trait::async_trait;
 use cassandra_proto::{
-  error,
   frame::Frame,
   query::{QueryParams, QueryParamsBuilder, QueryValues},
 };
 
+use super::QueryOptions;
+use crate::{
+  error,
+  row::{rows_as, FromRow},
+};
+
 /// Traits that provides methods for immediate query execution.
 #[async_trait]
-pub trait QueryExecutor: Send {
+pub trait QueryExecutor: Send + Sync {
+  /// Executes a query with given parameters and options of the request,
+  /// e.g. a timeout which overrides one of a session.
+  async fn query_with_options<Q: ToString + Send>(
+    &self,
+    query: Q,
+    query_params: QueryParams,
+    options: QueryOptions,
+  ) -> error::Result<Frame>;
+
   async fn query_with_params_tw<Q: ToString + Send>(
-    mut self: Pin<&mut Self>,
+    &self,
     query: Q,
     query_params: QueryParams,
     with_tracing: bool,
     with_warnings: bool,
-  ) -> error::Result<Frame>;
+  ) -> error::Result<Frame> {
+    let options = QueryOptions::new()
+      .tracing(with_tracing)
+      .warnings(with_warnings);
+    self.query_with_options(query, query_params, options).await
+  }
 
   /// Executes a query with default parameters:
   /// * TDB
-  async fn query<Q: ToString + Send>(mut self: Pin<&mut Self>, query: Q) -> error::Result<Frame> {
+  async fn query<Q: ToString + Send>(&self, query: Q) -> error::Result<Frame> {
     self.query_tw(query, false, false).await
   }
 
   /// Executes a query with ability to trace it and see warnings, and default parameters:
   /// * TBD
   async fn query_tw<Q: ToString + Send>(
-    mut self: Pin<&mut Self>,
+    &self,
     query: Q,
     with_tracing: bool,
     with_warnings: bool,
@@ -40,7 +57,7 @@ pub trait QueryExecutor: Send {
 
   /// Executes a query with bounded values (either with or without names).
   async fn query_with_values<Q: ToString + Send, V: Into<QueryValues> + Send>(
-    mut self: Pin<&mut Self>,
+    &self,
     query: Q,
     values: V,
   ) -> error::Result<Frame> {
@@ -50,7 +67,7 @@ pub trait QueryExecutor: Send {
   /// Executes a query with bounded values (either with or without names)
   /// and ability to see warnings, trace a request and default parameters.
   async fn query_with_values_tw<Q: ToString + Send, V: Into<QueryValues> + Send>(
-    mut self: Pin<&mut Self>,
+    &self,
     query: Q,
     values: V,
     with_tracing: bool,
@@ -65,7 +82,7 @@ pub trait QueryExecutor: Send {
 
   /// Executes a query with query params without warnings and tracing.
   async fn query_with_params<Q: ToString + Send>(
-    mut self: Pin<&mut Self>,
+    &self,
     query: Q,
     query_params: QueryParams,
   ) -> error::Result<Frame> {
@@ -73,4 +90,19 @@ pub trait QueryExecutor: Send {
       .query_with_params_tw(query, query_params, false, false)
       .await
   }
+
+  /// Executes a query and converts rows it returns into values of a given type.
+  async fn query_as<T: FromRow, Q: ToString + Send>(&self, query: Q) -> error::Result<Vec<T>> {
+    rows_as(self.query(query).await?)
+  }
+
+  /// Executes a query with bounded values and converts rows it returns
+  /// into values of a given type.
+  async fn query_as_with_values<T: FromRow, Q: ToString + Send, V: Into<QueryValues> + Send>(
+    &self,
+    query: Q,
+    values: V,
+  ) -> error::Result<Vec<T>> {
+    rows_as(self.query_with_values(query, values).await?)
+  }
 }
@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+/// Options of a single request which are not sent to a server. They are
+/// passed along with query parameters, so e.g. one request could wait for
+/// a response longer than others without a separate session handle.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QueryOptions {
+  timeout: Option<Duration>,
+  with_tracing: bool,
+  with_warnings: bool,
+}
+
+impl QueryOptions {
+  /// Creates options of a request which is sent without tracing
+  /// and warnings and waits for a response as long as a session does.
+  pub fn new() -> Self {
+    Default::default()
+  }
+
+  /// Sets a time to wait for a response instead of a request timeout
+  /// of a session or of a cluster session view. Each retry of a request
+  /// waits for the same time.
+  pub fn timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = Some(timeout);
+
+    self
+  }
+
+  /// Asks a server to trace a request.
+  pub fn tracing(mut self, with_tracing: bool) -> Self {
+    self.with_tracing = with_tracing;
+
+    self
+  }
+
+  /// Asks a server to return warnings of a request.
+  pub fn warnings(mut self, with_warnings: bool) -> Self {
+    self.with_warnings = with_warnings;
+
+    self
+  }
+
+  pub fn get_timeout(&self) -> Option<Duration> {
+    self.timeout
+  }
+
+  pub fn with_tracing(&self) -> bool {
+    self.with_tracing
+  }
+
+  pub fn with_warnings(&self) -> bool {
+    self.with_warnings
+  }
+}
@@ -0,0 +1,200 @@
+//! Reconnection policies decide how often a session tries to re-establish
+//! a connection which was lost and what happens to requests meanwhile.
+
+use std::{
+  collections::hash_map::RandomState,
+  hash::{BuildHasher, Hasher},
+  time::Duration,
+};
+
+const DEFAULT_BASE_DELAY: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Reconnection policy is consulted before each attempt to reconnect.
+pub trait ReconnectionPolicy: Send + Sync + 'static {
+  /// Returns a delay before a given attempt, starting from 0, or `None`
+  /// if a session should stop reconnecting. A next request which finds
+  /// a session disconnected starts reconnecting from scratch.
+  fn delay(&self, attempt: usize) -> Option<Duration>;
+
+  /// Whether requests which are made while a session is reconnecting wait
+  /// until it reconnects or gives up. Otherwise they are rejected at once.
+  fn queue_requests(&self) -> bool;
+}
+
+/// Policy which waits the same time before each attempt.
+#[derive(Debug, Clone)]
+pub struct ConstantReconnectionPolicy {
+  delay: Duration,
+  max_attempts: Option<usize>,
+  queue_requests: bool,
+}
+
+impl ConstantReconnectionPolicy {
+  /// Creates a policy which tries to reconnect infinitely
+  /// and rejects requests while reconnecting.
+  pub fn new(delay: Duration) -> Self {
+    ConstantReconnectionPolicy {
+      delay,
+      max_attempts: None,
+      queue_requests: false,
+    }
+  }
+
+  /// Sets a number of attempts after which a session stops reconnecting.
+  pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+    self.max_attempts = Some(max_attempts);
+
+    self
+  }
+
+  /// Makes requests wait while a session is reconnecting.
+  pub fn queue_while_reconnecting(mut self, queue_requests: bool) -> Self {
+    self.queue_requests = queue_requests;
+
+    self
+  }
+}
+
+impl ReconnectionPolicy for ConstantReconnectionPolicy {
+  fn delay(&self, attempt: usize) -> Option<Duration> {
+    match self.max_attempts {
+      Some(max_attempts) if attempt >= max_attempts => None,
+      _ => Some(self.delay),
+    }
+  }
+
+  fn queue_requests(&self) -> bool {
+    self.queue_requests
+  }
+}
+
+/// Policy which doubles a delay after each attempt until it reaches
+/// a maximal one. A random jitter of up to a half of a delay is applied,
+/// so many clients do not reconnect to a restarted node all at once.
+#[derive(Debug, Clone)]
+pub struct ExponentialReconnectionPolicy {
+  base_delay: Duration,
+  max_delay: Duration,
+  max_attempts: Option<usize>,
+  queue_requests: bool,
+}
+
+impl Default for ExponentialReconnectionPolicy {
+  fn default() -> Self {
+    ExponentialReconnectionPolicy::new(DEFAULT_BASE_DELAY, DEFAULT_MAX_DELAY)
+  }
+}
+
+impl ExponentialReconnectionPolicy {
+  /// Creates a policy which tries to reconnect infinitely
+  /// and rejects requests while reconnecting.
+  pub fn new(base_delay: Duration, max_delay: Duration) -> Self {
+    ExponentialReconnectionPolicy {
+      base_delay,
+      max_delay: max_delay.max(base_delay),
+      max_attempts: None,
+      queue_requests: false,
+    }
+  }
+
+  /// Sets a number of attempts after which a session stops reconnecting.
+  pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+    self.max_attempts = Some(max_attempts);
+
+    self
+  }
+
+  /// Makes requests wait while a session is reconnecting.
+  pub fn queue_while_reconnecting(mut self, queue_requests: bool) -> Self {
+    self.queue_requests = queue_requests;
+
+    self
+  }
+
+  /// Returns a delay before a given attempt without a jitter.
+  fn max_delay_of(&self, attempt: usize) -> Duration {
+    let factor = 1u32.checked_shl(attempt as u32).unwrap_or(u32::MAX);
+    self
+      .base_delay
+      .checked_mul(factor)
+      .unwrap_or(self.max_delay)
+      .min(self.max_delay)
+  }
+}
+
+impl ReconnectionPolicy for ExponentialReconnectionPolicy {
+  fn delay(&self, attempt: usize) -> Option<Duration> {
+    if let Some(max_attempts) = self.max_attempts {
+      if attempt >= max_attempts {
+        return None;
+      }
+    }
+
+    let delay = self.max_delay_of(attempt);
+    let jitter = delay.as_millis() as u64 / 2;
+    let jitter = if jitter > 0 { random() % jitter } else { 0 };
+
+    Some(delay - Duration::from_millis(jitter))
+  }
+
+  fn queue_requests(&self) -> bool {
+    self.queue_requests
+  }
+}
+
+/// Returns a random number. It is good enough for a jitter
+/// and does not require a dependency on a random number generator.
+fn random() -> u64 {
+  RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_constant_delay() {
+    let policy = ConstantReconnectionPolicy::new(Duration::from_millis(10)).max_attempts(2);
+
+    assert_eq!(policy.delay(0), Some(Duration::from_millis(10)));
+    assert_eq!(policy.delay(1), Some(Duration::from_millis(10)));
+    assert_eq!(policy.delay(2), None);
+    assert!(!policy.queue_requests());
+  }
+
+  #[test]
+  fn test_exponential_delay_grows() {
+    let policy =
+      ExponentialReconnectionPolicy::new(Duration::from_secs(1), Duration::from_secs(10));
+
+    assert_eq!(policy.max_delay_of(0), Duration::from_secs(1));
+    assert_eq!(policy.max_delay_of(1), Duration::from_secs(2));
+    assert_eq!(policy.max_delay_of(3), Duration::from_secs(8));
+    assert_eq!(policy.max_delay_of(4), Duration::from_secs(10));
+    assert_eq!(policy.max_delay_of(100), Duration::from_secs(10));
+  }
+
+  #[test]
+  fn test_exponential_delay_jitter() {
+    let policy =
+      ExponentialReconnectionPolicy::new(Duration::from_secs(1), Duration::from_secs(10));
+
+    for attempt in 0..10 {
+      let delay = policy.delay(attempt).unwrap();
+      let max_delay = policy.max_delay_of(attempt);
+      assert!(delay <= max_delay && delay >= max_delay / 2);
+    }
+  }
+
+  #[test]
+  fn test_exponential_max_attempts() {
+    let policy = ExponentialReconnectionPolicy::default()
+      .max_attempts(1)
+      .queue_while_reconnecting(true);
+
+    assert!(policy.delay(0).is_some());
+    assert!(policy.delay(1).is_none());
+    assert!(policy.queue_requests());
+  }
+}
@@ -0,0 +1,133 @@
+use cassandra_proto::frame::frame_error::WriteType;
+
+use super::{RequestInfo, RetryDecision, RetryPolicy};
+use crate::error;
+
+/// Policy which retries a request at most once and only if a retry
+/// is likely to succeed:
+/// * a read timed out although enough replicas responded, but data was not
+///   retrieved, so it is retried on the same node,
+/// * a write of a batch log timed out and the batch is idempotent, so it is
+///   retried on the same node,
+/// * a coordinator has not got enough alive replicas, so it is retried
+///   on a next node which may see the cluster differently,
+/// * a node failed or is not able to serve an idempotent request, so it is
+///   retried on a next node.
+///
+/// A consistency of a request is never changed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultRetryPolicy;
+
+impl DefaultRetryPolicy {
+  pub fn new() -> Self {
+    DefaultRetryPolicy
+  }
+}
+
+impl RetryPolicy for DefaultRetryPolicy {
+  fn on_read_timeout(
+    &self,
+    request: &RequestInfo,
+    received: i32,
+    required: i32,
+    data_retrieved: bool,
+  ) -> RetryDecision {
+    if request.retry_count == 0 && received >= required && !data_retrieved {
+      RetryDecision::RetrySameNode(request.consistency)
+    } else {
+      RetryDecision::Rethrow
+    }
+  }
+
+  fn on_write_timeout(
+    &self,
+    request: &RequestInfo,
+    write_type: &WriteType,
+    _received: i32,
+    _required: i32,
+  ) -> RetryDecision {
+    match write_type {
+      WriteType::BatchLog if request.retry_count == 0 && request.is_idempotent => {
+        RetryDecision::RetrySameNode(request.consistency)
+      }
+      _ => RetryDecision::Rethrow,
+    }
+  }
+
+  fn on_unavailable(&self, request: &RequestInfo, _required: i32, _alive: i32) -> RetryDecision {
+    if request.retry_count == 0 {
+      RetryDecision::RetryNextNode(request.consistency)
+    } else {
+      RetryDecision::Rethrow
+    }
+  }
+
+  fn on_request_error(&self, request: &RequestInfo, _error: &error::Error) -> RetryDecision {
+    if request.is_idempotent {
+      RetryDecision::RetryNextNode(request.consistency)
+    } else {
+      RetryDecision::Rethrow
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::retry::request_info;
+  use cassandra_proto::consistency::Consistency;
+
+  #[test]
+  fn test_default_read_timeout() {
+    let policy = DefaultRetryPolicy::new();
+
+    assert_eq!(
+      policy.on_read_timeout(&request_info(0, false), 2, 2, false),
+      RetryDecision::RetrySameNode(Consistency::Quorum)
+    );
+    assert_eq!(
+      policy.on_read_timeout(&request_info(0, false), 2, 2, true),
+      RetryDecision::Rethrow
+    );
+    assert_eq!(
+      policy.on_read_timeout(&request_info(0, false), 1, 2, false),
+      RetryDecision::Rethrow
+    );
+    assert_eq!(
+      policy.on_read_timeout(&request_info(1, false), 2, 2, false),
+      RetryDecision::Rethrow
+    );
+  }
+
+  #[test]
+  fn test_default_write_timeout() {
+    let policy = DefaultRetryPolicy::new();
+
+    assert_eq!(
+      policy.on_write_timeout(&request_info(0, true), &WriteType::BatchLog, 1, 2),
+      RetryDecision::RetrySameNode(Consistency::Quorum)
+    );
+    assert_eq!(
+      policy.on_write_timeout(&request_info(0, false), &WriteType::BatchLog, 1, 2),
+      RetryDecision::Rethrow
+    );
+    assert_eq!(
+      policy.on_write_timeout(&request_info(0, true), &WriteType::Simple, 1, 2),
+      RetryDecision::Rethrow
+    );
+  }
+
+  #[test]
+  fn test_default_unavailable() {
+    let policy = DefaultRetryPolicy::new();
+
+    assert_eq!(
+      policy.on_unavailable(&request_info(0, false), 2, 1),
+      RetryDecision::RetryNextNode(Consistency::Quorum)
+    );
+    assert_eq!(
+      policy.on_unavailable(&request_info(1, false), 2, 1),
+      RetryDecision::Rethrow
+    );
+  }
+}
@@ -0,0 +1,153 @@
+use cassandra_proto::{consistency::Consistency, frame::frame_error::WriteType};
+
+use super::{RequestInfo, RetryDecision, RetryPolicy};
+use crate::error;
+
+/// Policy which retries a request with a lower consistency if there are not
+/// enough replicas to achieve a requested one. It behaves as the default
+/// policy otherwise.
+///
+/// This policy may break consistency guarantees an application relies on,
+/// so it should be used only if a weaker consistency is better than an error.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DowngradingConsistencyRetryPolicy;
+
+impl DowngradingConsistencyRetryPolicy {
+  pub fn new() -> Self {
+    DowngradingConsistencyRetryPolicy
+  }
+}
+
+/// Returns the highest consistency which may be achieved
+/// with a given number of replicas.
+fn max_likely_to_work(replicas: i32) -> Option<Consistency> {
+  match replicas {
+    r if r >= 3 => Some(Consistency::Three),
+    2 => Some(Consistency::Two),
+    1 => Some(Consistency::One),
+    _ => None,
+  }
+}
+
+fn retry_with(replicas: i32) -> RetryDecision {
+  max_likely_to_work(replicas)
+    .map(RetryDecision::RetrySameNode)
+    .unwrap_or(RetryDecision::Rethrow)
+}
+
+impl RetryPolicy for DowngradingConsistencyRetryPolicy {
+  fn on_read_timeout(
+    &self,
+    request: &RequestInfo,
+    received: i32,
+    required: i32,
+    data_retrieved: bool,
+  ) -> RetryDecision {
+    if request.retry_count > 0 {
+      return RetryDecision::Rethrow;
+    }
+
+    if received < required {
+      retry_with(received)
+    } else if !data_retrieved {
+      RetryDecision::RetrySameNode(request.consistency)
+    } else {
+      RetryDecision::Rethrow
+    }
+  }
+
+  fn on_write_timeout(
+    &self,
+    request: &RequestInfo,
+    write_type: &WriteType,
+    received: i32,
+    _required: i32,
+  ) -> RetryDecision {
+    if request.retry_count > 0 || !request.is_idempotent {
+      return RetryDecision::Rethrow;
+    }
+
+    match write_type {
+      // a batch log was not written, so no replica has got a batch
+      WriteType::UnloggedBatch => retry_with(received),
+      WriteType::BatchLog => RetryDecision::RetrySameNode(request.consistency),
+      _ => RetryDecision::Rethrow,
+    }
+  }
+
+  fn on_unavailable(&self, request: &RequestInfo, _required: i32, alive: i32) -> RetryDecision {
+    if request.retry_count > 0 {
+      return RetryDecision::Rethrow;
+    }
+
+    retry_with(alive)
+  }
+
+  fn on_request_error(&self, request: &RequestInfo, _error: &error::Error) -> RetryDecision {
+    if request.is_idempotent {
+      RetryDecision::RetryNextNode(request.consistency)
+    } else {
+      RetryDecision::Rethrow
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::retry::request_info;
+
+  #[test]
+  fn test_downgrading_read_timeout() {
+    let policy = DowngradingConsistencyRetryPolicy::new();
+
+    assert_eq!(
+      policy.on_read_timeout(&request_info(0, false), 1, 2, true),
+      RetryDecision::RetrySameNode(Consistency::One)
+    );
+    assert_eq!(
+      policy.on_read_timeout(&request_info(0, false), 0, 2, false),
+      RetryDecision::Rethrow
+    );
+    assert_eq!(
+      policy.on_read_timeout(&request_info(0, false), 2, 2, false),
+      RetryDecision::RetrySameNode(Consistency::Quorum)
+    );
+    assert_eq!(
+      policy.on_read_timeout(&request_info(1, false), 1, 2, true),
+      RetryDecision::Rethrow
+    );
+  }
+
+  #[test]
+  fn test_downgrading_write_timeout() {
+    let policy = DowngradingConsistencyRetryPolicy::new();
+
+    assert_eq!(
+      policy.on_write_timeout(&request_info(0, true), &WriteType::UnloggedBatch, 2, 3),
+      RetryDecision::RetrySameNode(Consistency::Two)
+    );
+    assert_eq!(
+      policy.on_write_timeout(&request_info(0, false), &WriteType::UnloggedBatch, 2, 3),
+      RetryDecision::Rethrow
+    );
+    assert_eq!(
+      policy.on_write_timeout(&request_info(0, true), &WriteType::Simple, 2, 3),
+      RetryDecision::Rethrow
+    );
+  }
+
+  #[test]
+  fn test_downgrading_unavailable() {
+    let policy = DowngradingConsistencyRetryPolicy::new();
+
+    assert_eq!(
+      policy.on_unavailable(&request_info(0, false), 3, 5),
+      RetryDecision::RetrySameNode(Consistency::Three)
+    );
+    assert_eq!(
+      policy.on_unavailable(&request_info(0, false), 3, 0),
+      RetryDecision::Rethrow
+    );
+  }
+}
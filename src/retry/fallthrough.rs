@@ -0,0 +1,32 @@
+use cassandra_proto::frame::frame_error::WriteType;
+
+use super::{RequestInfo, RetryDecision, RetryPolicy};
+use crate::error;
+
+/// Policy which never retries requests.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FallthroughRetryPolicy;
+
+impl FallthroughRetryPolicy {
+  pub fn new() -> Self {
+    FallthroughRetryPolicy
+  }
+}
+
+impl RetryPolicy for FallthroughRetryPolicy {
+  fn on_read_timeout(&self, _: &RequestInfo, _: i32, _: i32, _: bool) -> RetryDecision {
+    RetryDecision::Rethrow
+  }
+
+  fn on_write_timeout(&self, _: &RequestInfo, _: &WriteType, _: i32, _: i32) -> RetryDecision {
+    RetryDecision::Rethrow
+  }
+
+  fn on_unavailable(&self, _: &RequestInfo, _: i32, _: i32) -> RetryDecision {
+    RetryDecision::Rethrow
+  }
+
+  fn on_request_error(&self, _: &RequestInfo, _: &error::Error) -> RetryDecision {
+    RetryDecision::Rethrow
+  }
+}
@@ -0,0 +1,157 @@
+//! Retry policies decide what to do when a request fails: send it again
+//! to the same node or to a next one in a query plan, possibly with a lower
+//! consistency, or return an error to a caller.
+
+mod default;
+mod downgrading;
+mod fallthrough;
+
+use cassandra_proto::{
+  consistency::Consistency,
+  frame::frame_error::WriteType,
+};
+
+use crate::error;
+
+pub use default::DefaultRetryPolicy;
+pub use downgrading::DowngradingConsistencyRetryPolicy;
+pub use fallthrough::FallthroughRetryPolicy;
+
+/// Information about a failed request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RequestInfo {
+  /// Consistency the request was sent with.
+  pub consistency: Consistency,
+  /// Whether the request can be applied several times with the same effect.
+  /// Non-idempotent requests must not be retried if they could be applied.
+  pub is_idempotent: bool,
+  /// Number of times the request has already been retried.
+  pub retry_count: usize,
+}
+
+/// What should be done with a failed request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RetryDecision {
+  /// Sends a request to the same node again with a given consistency.
+  RetrySameNode(Consistency),
+  /// Sends a request to a next node of a query plan with a given consistency.
+  RetryNextNode(Consistency),
+  /// Returns an error to a caller.
+  Rethrow,
+}
+
+/// Retry policy is consulted each time a request fails with an error
+/// which could be temporary.
+pub trait RetryPolicy: Send + Sync + 'static {
+  /// Called when a coordinator did not receive enough responses from replicas
+  /// in time. `data_retrieved` shows if data was received from any replica.
+  fn on_read_timeout(
+    &self,
+    request: &RequestInfo,
+    received: i32,
+    required: i32,
+    data_retrieved: bool,
+  ) -> RetryDecision;
+
+  /// Called when a coordinator did not receive enough acknowledgements
+  /// from replicas in time.
+  fn on_write_timeout(
+    &self,
+    request: &RequestInfo,
+    write_type: &WriteType,
+    received: i32,
+    required: i32,
+  ) -> RetryDecision;
+
+  /// Called when a coordinator knows that there are not enough alive replicas
+  /// to achieve a requested consistency, so the request was not sent to them.
+  fn on_unavailable(&self, request: &RequestInfo, required: i32, alive: i32) -> RetryDecision;
+
+  /// Called when a node is overloaded or bootstrapping, fails with a server
+  /// error or a connection fails while a request is in flight.
+  fn on_request_error(&self, request: &RequestInfo, error: &error::Error) -> RetryDecision;
+}
+
+/// Asks a policy what to do with a request which failed with a given error.
+/// Errors which could not be fixed by a retry (e.g. syntax ones) are always
+/// returned to a caller.
+pub(crate) fn decide(
+  policy: &dyn RetryPolicy,
+  request: &RequestInfo,
+  error: &error::Error,
+) -> RetryDecision {
+  match error {
+    error::Error::ReadTimeout {
+      received,
+      block_for,
+      data_present,
+      ..
+    } => policy.on_read_timeout(request, *received, *block_for, *data_present),
+    error::Error::WriteTimeout {
+      write_type,
+      received,
+      block_for,
+      ..
+    } => policy.on_write_timeout(request, write_type, *received, *block_for),
+    error::Error::Unavailable {
+      required, alive, ..
+    } => policy.on_unavailable(request, *required, *alive),
+    error::Error::Overloaded { .. }
+    | error::Error::IsBootstrapping { .. }
+    | error::Error::Server { .. }
+    | error::Error::Truncate { .. }
+    | error::Error::ReadFailure { .. }
+    | error::Error::WriteFailure { .. }
+    | error::Error::Timeout(_)
+    | error::Error::ConnectionClosed(_)
+    | error::Error::Io(_) => policy.on_request_error(request, error),
+    _ => RetryDecision::Rethrow,
+  }
+}
+
+#[cfg(test)]
+pub(crate) fn request_info(retry_count: usize, is_idempotent: bool) -> RequestInfo {
+  RequestInfo {
+    consistency: Consistency::Quorum,
+    is_idempotent,
+    retry_count,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{io, time::Duration};
+
+  #[test]
+  fn test_decide_rethrows_general_errors() {
+    let policy = DefaultRetryPolicy::new();
+    let request = request_info(0, true);
+
+    assert_eq!(
+      decide(&policy, &request, &"syntax error".into()),
+      RetryDecision::Rethrow
+    );
+  }
+
+  #[test]
+  fn test_decide_io_error_is_request_error() {
+    let policy = DefaultRetryPolicy::new();
+    let error = error::Error::Io(io::Error::new(io::ErrorKind::BrokenPipe, "broken"));
+
+    assert_eq!(
+      decide(&policy, &request_info(0, true), &error),
+      RetryDecision::RetryNextNode(Consistency::Quorum)
+    );
+    assert_eq!(
+      decide(&policy, &request_info(0, false), &error),
+      RetryDecision::Rethrow
+    );
+
+    let timeout = error::Error::Timeout(Duration::from_secs(1));
+    assert_eq!(
+      decide(&policy, &request_info(0, true), &timeout),
+      RetryDecision::RetryNextNode(Consistency::Quorum)
+    );
+  }
+}
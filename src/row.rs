@@ -0,0 +1,105 @@
+//! Conversion of rows which are returned by a server into Rust types.
+
+pub use crate::error::Result;
+pub use cassandra_proto::types::rows::Row;
+
+use cassandra_proto::frame::Frame;
+
+use crate::error;
+
+/// Type which could be created from a row. It could be derived for structs
+/// with named fields by `#[derive(FromRow)]`, fields could be of any type
+/// which implements `FromCql`.
+pub trait FromRow: Sized {
+  fn from_row(row: &Row) -> Result<Self>;
+}
+
+impl FromRow for Row {
+  fn from_row(row: &Row) -> Result<Self> {
+    Ok(row.clone())
+  }
+}
+
+/// Converts rows of a response into values of a given type.
+pub fn rows_as<T: FromRow>(frame: Frame) -> Result<Vec<T>> {
+  let rows = frame
+    .get_body()?
+    .into_rows()
+    .ok_or_else(|| error::Error::from("Response does not contain rows"))?;
+
+  rows.iter().map(T::from_row).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{types::get_or_default, FromRow};
+  use cassandra_proto::{frame::frame_result::BodyResResultRows, frame::FromCursor};
+  use std::io::Cursor;
+
+  /// Row of `ks.tb` table with `id int` and `name text` columns.
+  fn row(name: Option<&str>) -> Row {
+    // global table spec flag, 2 columns
+    let mut bytes = vec![0, 0, 0, 1, 0, 0, 0, 2];
+    bytes.extend_from_slice(&[0, 2, b'k', b's', 0, 2, b't', b'b']);
+    bytes.extend_from_slice(&[0, 2, b'i', b'd', 0, 0x09]);
+    bytes.extend_from_slice(&[0, 4, b'n', b'a', b'm', b'e', 0, 0x0D]);
+    // 1 row
+    bytes.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 4, 0, 0, 0, 7]);
+    match name {
+      Some(name) => {
+        bytes.extend_from_slice(&[0, 0, 0, name.len() as u8]);
+        bytes.extend_from_slice(name.as_bytes());
+      }
+      None => bytes.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF]),
+    }
+
+    let body = BodyResResultRows::from_cursor(&mut Cursor::new(&bytes)).unwrap();
+    Row::from_frame_body(body).remove(0)
+  }
+
+  #[derive(Debug, PartialEq, FromRow)]
+  struct User {
+    id: i32,
+    #[cdrs(rename = "name")]
+    login: Option<String>,
+    #[cdrs(skip)]
+    is_admin: bool,
+    #[cdrs(default)]
+    email: String,
+  }
+
+  #[derive(Debug, FromRow)]
+  struct StrictUser {
+    #[allow(dead_code)]
+    name: String,
+  }
+
+  #[test]
+  fn test_derive_from_row() {
+    assert_eq!(
+      User::from_row(&row(Some("alex"))).unwrap(),
+      User {
+        id: 7,
+        login: Some("alex".into()),
+        is_admin: false,
+        email: String::new(),
+      }
+    );
+    assert_eq!(User::from_row(&row(None)).unwrap().login, None);
+  }
+
+  #[test]
+  fn test_required_column_is_null() {
+    assert!(StrictUser::from_row(&row(Some("alex"))).is_ok());
+    assert!(StrictUser::from_row(&row(None)).is_err());
+  }
+
+  #[test]
+  fn test_get_or_default() {
+    let row = row(None);
+    assert_eq!(get_or_default::<_, String>(&row, "name").unwrap(), "");
+    assert_eq!(get_or_default::<_, i32>(&row, "missing").unwrap(), 0);
+    assert!(get_or_default::<_, i64>(&row, "id").is_err());
+  }
+}
@@ -0,0 +1,595 @@
+//! Full table scan which reads token ranges of a table in parallel.
+//!
+//! A Murmur3 token ring is split into sub-ranges, each of them is read
+//! by `SELECT ... WHERE token(pk) > ? AND token(pk) <= ?` page by page
+//! and rows of all ranges are merged into a single stream.
+//!
+//! ```ignore
+//! let mut rows = TableScan::new("ks.events", &["id"])
+//!   .splits(64)
+//!   .concurrency(8)
+//!   .run(&session);
+//!
+//! while let Some(row) = rows.next().await {
+//!   // ... store rows.checkpoint() from time to time
+//! }
+//! ```
+
+use std::{
+  collections::VecDeque,
+  fmt,
+  pin::Pin,
+  str::FromStr,
+  task::{Context, Poll},
+  vec,
+};
+
+use cassandra_proto::{
+  consistency::Consistency,
+  query::{QueryParamsBuilder, QueryValues},
+  types::{rows::Row, value::Value},
+};
+use futures::{
+  future::{BoxFuture, FutureExt},
+  stream::{FuturesUnordered, Stream, StreamExt},
+};
+
+use crate::{
+  error,
+  pager::{PageSize, PagerState},
+  session::Session,
+  token::TokenRange,
+  transport::CDRSTransport,
+};
+
+/// Scan of a whole table by token ranges. It is configured by builder
+/// methods and started by `run` or `resume`.
+#[derive(Debug, Clone)]
+pub struct TableScan {
+  table: String,
+  partition_key: Vec<String>,
+  columns: Vec<String>,
+  splits: usize,
+  concurrency: usize,
+  page_size: PageSize,
+  consistency: Consistency,
+}
+
+impl TableScan {
+  /// Creates a scan of a table, which name may be qualified by a keyspace,
+  /// with given partition key columns.
+  pub fn new<S: ToString>(table: S, partition_key: &[&str]) -> Self {
+    TableScan {
+      table: table.to_string(),
+      partition_key: partition_key
+        .iter()
+        .map(|column| column.to_string())
+        .collect(),
+      columns: vec![],
+      splits: 32,
+      concurrency: 4,
+      page_size: 1000,
+      consistency: Consistency::One,
+    }
+  }
+
+  /// Columns to select, all columns are selected by default.
+  pub fn columns(mut self, columns: &[&str]) -> Self {
+    self.columns = columns.iter().map(|column| column.to_string()).collect();
+
+    self
+  }
+
+  /// Number of token ranges the ring is split into.
+  pub fn splits(mut self, splits: usize) -> Self {
+    self.splits = splits.max(1);
+
+    self
+  }
+
+  /// Number of token ranges which are read at the same time.
+  pub fn concurrency(mut self, concurrency: usize) -> Self {
+    self.concurrency = concurrency.max(1);
+
+    self
+  }
+
+  pub fn page_size(mut self, page_size: PageSize) -> Self {
+    self.page_size = page_size;
+
+    self
+  }
+
+  pub fn consistency(mut self, consistency: Consistency) -> Self {
+    self.consistency = consistency;
+
+    self
+  }
+
+  /// Query which selects rows of a single token range.
+  pub fn query(&self) -> String {
+    let columns = if self.columns.is_empty() {
+      "*".to_string()
+    } else {
+      self.columns.join(", ")
+    };
+    let partition_key = self.partition_key.join(", ");
+
+    format!(
+      "SELECT {} FROM {} WHERE token({pk}) > ? AND token({pk}) <= ?",
+      columns,
+      self.table,
+      pk = partition_key
+    )
+  }
+
+  /// Starts scanning a whole ring.
+  pub fn run<T: CDRSTransport + 'static>(&self, session: &Session<T>) -> ScanStream {
+    let ranges = TokenRange::full_ring()
+      .split(self.splits)
+      .into_iter()
+      .map(|range| RangeCheckpoint {
+        range,
+        state: PagerState::new(),
+      })
+      .collect();
+
+    self.resume(session, ScanCheckpoint { ranges })
+  }
+
+  /// Continues a scan from a checkpoint. Ranges which were completed
+  /// before the checkpoint was taken are not read again.
+  pub fn resume<T: CDRSTransport + 'static>(
+    &self,
+    session: &Session<T>,
+    checkpoint: ScanCheckpoint,
+  ) -> ScanStream {
+    let session = session.clone();
+    let query = self.query();
+    let page_size = self.page_size;
+    let consistency = self.consistency;
+    let fetch: FetchRange = Box::new(move |range: TokenRange, state: PagerState| {
+      let session = session.clone();
+      let query = query.clone();
+      async move {
+        let values = vec![Value::from(range.start.0), Value::from(range.end.0)];
+        let params = QueryParamsBuilder::new()
+          .consistency(consistency)
+          .values(QueryValues::SimpleValues(values))
+          .finalize();
+
+        let mut pager = session.into_pager(page_size);
+        let mut range_pager = pager.query_with_params_and_pager_state(query, params, state);
+        let rows = range_pager.next().await?;
+
+        Ok((rows, range_pager.pager_state()))
+      }
+      .boxed()
+    });
+
+    ScanStream::new(fetch, checkpoint, self.concurrency)
+  }
+}
+
+/// Position of a scan which it could be resumed from. It contains
+/// ranges which are not completed yet, along with paging states of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanCheckpoint {
+  ranges: Vec<RangeCheckpoint>,
+}
+
+impl ScanCheckpoint {
+  pub fn new(ranges: Vec<RangeCheckpoint>) -> Self {
+    ScanCheckpoint { ranges }
+  }
+
+  pub fn ranges(&self) -> &[RangeCheckpoint] {
+    &self.ranges
+  }
+
+  /// Checks that all ranges were read.
+  pub fn is_complete(&self) -> bool {
+    self.ranges.is_empty()
+  }
+}
+
+/// A checkpoint is formatted as a list of `start:end:paging-token` entries
+/// separated by commas, so it could be stored and parsed back later.
+impl fmt::Display for ScanCheckpoint {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let ranges: Vec<String> = self
+      .ranges
+      .iter()
+      .map(|checkpoint| {
+        format!(
+          "{}:{}:{}",
+          checkpoint.range.start,
+          checkpoint.range.end,
+          checkpoint.state.to_token()
+        )
+      })
+      .collect();
+
+    write!(f, "{}", ranges.join(","))
+  }
+}
+
+impl FromStr for ScanCheckpoint {
+  type Err = error::Error;
+
+  fn from_str(s: &str) -> error::Result<Self> {
+    let malformed = || error::Error::from(format!("Scan checkpoint {:?} is malformed", s));
+    let ranges = s
+      .split(',')
+      .filter(|range| !range.is_empty())
+      .map(|range| {
+        let parts: Vec<&str> = range.split(':').collect();
+        match parts.as_slice() {
+          [start, end, state] => Ok(RangeCheckpoint {
+            range: TokenRange::new(
+              start.parse().map_err(|_| malformed())?,
+              end.parse().map_err(|_| malformed())?,
+            ),
+            state: PagerState::from_token(state)?,
+          }),
+          _ => Err(malformed()),
+        }
+      })
+      .collect::<error::Result<_>>()?;
+
+    Ok(ScanCheckpoint { ranges })
+  }
+}
+
+/// Token range which is not completed yet and a paging state it should
+/// be continued with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeCheckpoint {
+  pub range: TokenRange,
+  pub state: PagerState,
+}
+
+/// Progress of a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanProgress {
+  /// Number of ranges the scan reads, including completed ones.
+  pub ranges: usize,
+  pub completed_ranges: usize,
+  /// Number of rows which were returned by the scan.
+  pub rows: u64,
+}
+
+type FetchRange = Box<
+  dyn Fn(TokenRange, PagerState) -> BoxFuture<'static, error::Result<(Vec<Row>, PagerState)>>
+    + Send
+    + Sync,
+>;
+
+type FetchedPage = (usize, error::Result<(Vec<Row>, PagerState)>);
+
+struct RangeScan {
+  range: TokenRange,
+  /// State after the last page which rows were all returned.
+  state: PagerState,
+  is_completed: bool,
+}
+
+/// Stream of rows of all ranges of a scan. Ranges are read concurrently
+/// and each of them prefetches its next page while rows of the current
+/// one are consumed, so rows of different ranges are interleaved.
+///
+/// A range is checkpointed after all rows of a page are returned, thus
+/// rows of a page which is being consumed are returned again if a scan
+/// is resumed. The stream ends after the first error, it could be resumed
+/// from a checkpoint which is taken after it.
+pub struct ScanStream {
+  fetch: FetchRange,
+  ranges: Vec<RangeScan>,
+  not_started: VecDeque<usize>,
+  concurrency: usize,
+  active: usize,
+  running: FuturesUnordered<BoxFuture<'static, FetchedPage>>,
+  fetched: VecDeque<(usize, Vec<Row>, PagerState)>,
+  /// Range of rows which are being returned and its state after them.
+  current: Option<(usize, PagerState)>,
+  rows: vec::IntoIter<Row>,
+  returned_rows: u64,
+  is_failed: bool,
+}
+
+impl ScanStream {
+  fn new(fetch: FetchRange, checkpoint: ScanCheckpoint, concurrency: usize) -> Self {
+    let ranges: Vec<RangeScan> = checkpoint
+      .ranges
+      .into_iter()
+      .map(|checkpoint| RangeScan {
+        range: checkpoint.range,
+        state: checkpoint.state,
+        is_completed: false,
+      })
+      .collect();
+
+    ScanStream {
+      fetch,
+      not_started: (0..ranges.len()).collect(),
+      ranges,
+      concurrency: concurrency.max(1),
+      active: 0,
+      running: FuturesUnordered::new(),
+      fetched: VecDeque::new(),
+      current: None,
+      rows: Vec::new().into_iter(),
+      returned_rows: 0,
+      is_failed: false,
+    }
+  }
+
+  /// Returns a checkpoint which the scan could be resumed from.
+  pub fn checkpoint(&self) -> ScanCheckpoint {
+    let ranges = self
+      .ranges
+      .iter()
+      .filter(|range| !range.is_completed)
+      .map(|range| RangeCheckpoint {
+        range: range.range,
+        state: range.state.clone(),
+      })
+      .collect();
+
+    ScanCheckpoint { ranges }
+  }
+
+  pub fn progress(&self) -> ScanProgress {
+    ScanProgress {
+      ranges: self.ranges.len(),
+      completed_ranges: self
+        .ranges
+        .iter()
+        .filter(|range| range.is_completed)
+        .count(),
+      rows: self.returned_rows,
+    }
+  }
+
+  fn start_ranges(&mut self) {
+    while self.active < self.concurrency {
+      let index = match self.not_started.pop_front() {
+        Some(index) => index,
+        None => return,
+      };
+
+      self.active += 1;
+      let state = self.ranges[index].state.clone();
+      self.fetch_page(index, state);
+    }
+  }
+
+  fn fetch_page(&mut self, index: usize, state: PagerState) {
+    let page = (self.fetch)(self.ranges[index].range, state);
+    self
+      .running
+      .push(page.map(move |page| (index, page)).boxed());
+  }
+
+  /// Marks rows of a page as returned.
+  fn complete_page(&mut self, index: usize, state: PagerState) {
+    let range = &mut self.ranges[index];
+    range.is_completed = !state.has_more();
+    range.state = state;
+
+    if range.is_completed {
+      self.active -= 1;
+    }
+  }
+}
+
+impl Stream for ScanStream {
+  type Item = error::Result<Row>;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let scan = &mut *self;
+
+    loop {
+      if scan.is_failed {
+        return Poll::Ready(None);
+      }
+
+      scan.start_ranges();
+      // pages of all ranges are polled while rows are consumed,
+      // so their requests are sent and responses are awaited meanwhile
+      while let Poll::Ready(Some((index, page))) = scan.running.poll_next_unpin(cx) {
+        match page {
+          Ok((rows, state)) => scan.fetched.push_back((index, rows, state)),
+          Err(err) => {
+            scan.is_failed = true;
+            scan.running = FuturesUnordered::new();
+            return Poll::Ready(Some(Err(err)));
+          }
+        }
+      }
+
+      if let Some(row) = scan.rows.next() {
+        scan.returned_rows += 1;
+        return Poll::Ready(Some(Ok(row)));
+      }
+
+      if let Some((index, state)) = scan.current.take() {
+        scan.complete_page(index, state);
+        continue;
+      }
+
+      match scan.fetched.pop_front() {
+        Some((index, rows, state)) => {
+          if state.has_more() {
+            scan.fetch_page(index, state.clone());
+          }
+          scan.rows = rows.into_iter();
+          scan.current = Some((index, state));
+        }
+        None if scan.running.is_empty() => return Poll::Ready(None),
+        None => return Poll::Pending,
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::token::Token;
+  use cassandra_proto::{
+    frame::{frame_result::BodyResResultRows, FromCursor},
+    types::{CBytes, IntoRustByName},
+  };
+  use futures::executor::block_on;
+  use std::{
+    io::Cursor,
+    sync::{Arc, Mutex},
+  };
+
+  /// Rows of `ks.tb` table with a single `id bigint` column.
+  fn rows(ids: &[i64]) -> Vec<Row> {
+    let mut bytes = vec![0, 0, 0, 1, 0, 0, 0, 1];
+    bytes.extend_from_slice(&[0, 2, b'k', b's', 0, 2, b't', b'b']);
+    bytes.extend_from_slice(&[0, 2, b'i', b'd', 0, 0x02]);
+    bytes.extend_from_slice(&(ids.len() as i32).to_be_bytes());
+    for id in ids {
+      bytes.extend_from_slice(&[0, 0, 0, 8]);
+      bytes.extend_from_slice(&id.to_be_bytes());
+    }
+
+    let body = BodyResResultRows::from_cursor(&mut Cursor::new(&bytes)).unwrap();
+    Row::from_frame_body(body)
+  }
+
+  /// Each range has two pages of a single row, which is an end of a range
+  /// and a page number. Requests are recorded.
+  fn fetch(requests: Arc<Mutex<Vec<(TokenRange, PagerState)>>>) -> FetchRange {
+    Box::new(move |range, state| {
+      requests.lock().unwrap().push((range, state.clone()));
+      let page = if state.get_cursor().is_some() { 1 } else { 0 };
+      let next = if page == 0 {
+        PagerState::with_cursor_and_more_flag(CBytes::new(vec![1]), true)
+      } else {
+        PagerState::with_cursor_and_more_flag(CBytes::new(vec![2]), false)
+      };
+
+      let rows = rows(&[range.end.0 / 1000 * 1000 + page]);
+      async move { Ok((rows, next)) }.boxed()
+    })
+  }
+
+  fn ranges(count: i64) -> ScanCheckpoint {
+    ScanCheckpoint::new(
+      (0..count)
+        .map(|i| RangeCheckpoint {
+          range: TokenRange::new(Token(i * 1000), Token((i + 1) * 1000)),
+          state: PagerState::new(),
+        })
+        .collect(),
+    )
+  }
+
+  fn id(row: error::Result<Row>) -> i64 {
+    row.unwrap().get_r_by_name("id").unwrap()
+  }
+
+  #[test]
+  fn test_query() {
+    let scan = TableScan::new("ks.tb", &["a", "b"]).columns(&["a", "c"]);
+
+    assert_eq!(
+      scan.query(),
+      "SELECT a, c FROM ks.tb WHERE token(a, b) > ? AND token(a, b) <= ?"
+    );
+  }
+
+  #[test]
+  fn test_scan_all_ranges() {
+    let requests = Arc::new(Mutex::new(vec![]));
+    let scan = ScanStream::new(fetch(requests.clone()), ranges(3), 2);
+
+    let mut ids: Vec<i64> = block_on(scan.collect::<Vec<_>>())
+      .into_iter()
+      .map(id)
+      .collect();
+    ids.sort();
+    assert_eq!(ids, vec![1000, 1001, 2000, 2001, 3000, 3001]);
+    assert_eq!(requests.lock().unwrap().len(), 6);
+  }
+
+  #[test]
+  fn test_concurrency_is_bounded() {
+    let requests = Arc::new(Mutex::new(vec![]));
+    let mut scan = ScanStream::new(fetch(requests.clone()), ranges(3), 2);
+
+    block_on(scan.next()).unwrap().unwrap();
+    let started: Vec<TokenRange> = requests
+      .lock()
+      .unwrap()
+      .iter()
+      .map(|(range, _)| *range)
+      .collect();
+    assert!(!started.contains(&TokenRange::new(Token(2000), Token(3000))));
+  }
+
+  #[test]
+  fn test_checkpoint_and_progress() {
+    let requests = Arc::new(Mutex::new(vec![]));
+    let mut scan = ScanStream::new(fetch(requests.clone()), ranges(1), 1);
+
+    assert_eq!(id(block_on(scan.next()).unwrap()), 1000);
+    // the first page is not completed until the next row is requested
+    assert_eq!(scan.checkpoint(), ranges(1));
+
+    assert_eq!(id(block_on(scan.next()).unwrap()), 1001);
+    let checkpoint = scan.checkpoint();
+    assert_eq!(
+      checkpoint.ranges()[0].state.get_cursor(),
+      Some(CBytes::new(vec![1]))
+    );
+    assert_eq!(
+      scan.progress(),
+      ScanProgress {
+        ranges: 1,
+        completed_ranges: 0,
+        rows: 2,
+      }
+    );
+
+    assert!(block_on(scan.next()).is_none());
+    assert!(scan.checkpoint().is_complete());
+    assert_eq!(scan.progress().completed_ranges, 1);
+
+    // resuming continues the range from its second page
+    let requests = Arc::new(Mutex::new(vec![]));
+    let resumed = ScanStream::new(fetch(requests.clone()), checkpoint, 1);
+    let ids: Vec<i64> = block_on(resumed.collect::<Vec<_>>())
+      .into_iter()
+      .map(id)
+      .collect();
+    assert_eq!(ids, vec![1001]);
+  }
+
+  #[test]
+  fn test_checkpoint_from_str() {
+    let mut checkpoint = ranges(2);
+    checkpoint.ranges[1].state = PagerState::with_cursor_and_more_flag(CBytes::new(vec![7]), true);
+    let text = checkpoint.to_string();
+
+    assert_eq!(text.parse::<ScanCheckpoint>().unwrap(), checkpoint);
+    assert!("".parse::<ScanCheckpoint>().unwrap().is_complete());
+    assert!("1:2".parse::<ScanCheckpoint>().is_err());
+    assert!("a:2:AQA".parse::<ScanCheckpoint>().is_err());
+  }
+
+  #[test]
+  fn test_scan_ends_after_error() {
+    let fetch: FetchRange = Box::new(|_, _| async { Err("range is unavailable".into()) }.boxed());
+    let mut scan = ScanStream::new(fetch, ranges(2), 2);
+
+    assert!(block_on(scan.next()).unwrap().is_err());
+    assert!(block_on(scan.next()).is_none());
+    assert_eq!(scan.checkpoint(), ranges(2));
+  }
+}
@@ -0,0 +1,172 @@
+use std::{
+  future::Future,
+  io::Cursor,
+  time::{Duration, Instant},
+};
+
+use async_std::task;
+use cassandra_proto::{
+  frame::{events as proto, frame_result::ResultKind, Frame, FromCursor, IntoBytes, Opcode},
+  types::{rows::Row, IntoRustByName},
+};
+use uuid::Uuid;
+
+use crate::{error, events::SchemaChange};
+
+/// Time between two checks of schema versions.
+const SCHEMA_AGREEMENT_INTERVAL: Duration = Duration::from_millis(200);
+
+pub(crate) const SELECT_LOCAL_SCHEMA_QUERY: &str =
+  "SELECT host_id, schema_version FROM system.local WHERE key = 'local'";
+pub(crate) const SELECT_PEERS_SCHEMA_QUERY: &str =
+  "SELECT host_id, schema_version FROM system.peers";
+
+/// Schema version which a node reports.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SchemaVersion {
+  pub host_id: Option<Uuid>,
+  pub version: Option<Uuid>,
+}
+
+pub(crate) fn read_versions(rows: &[Row]) -> error::Result<Vec<SchemaVersion>> {
+  rows
+    .iter()
+    .map(|row| {
+      Ok(SchemaVersion {
+        host_id: row.get_by_name("host_id")?,
+        version: row.get_by_name("schema_version")?,
+      })
+    })
+    .collect()
+}
+
+/// Checks that all live nodes report the same schema version. Nodes which
+/// do not report a version, e.g. ones which are joining a cluster, are skipped.
+pub(crate) fn is_agreement<F: Fn(Option<Uuid>) -> bool>(
+  versions: &[SchemaVersion],
+  is_live: F,
+) -> bool {
+  let mut live = versions
+    .iter()
+    .filter(|version| is_live(version.host_id))
+    .filter_map(|version| version.version);
+
+  match live.next() {
+    Some(first) => live.all(|version| version == first),
+    None => true,
+  }
+}
+
+/// Checks schema agreement until it is reached or a given time passes.
+/// Agreement is checked at least once.
+pub(crate) async fn wait_for_agreement<F, R>(max_wait: Duration, check: F) -> error::Result<bool>
+where
+  F: Fn() -> R,
+  R: Future<Output = error::Result<bool>>,
+{
+  let started = Instant::now();
+
+  loop {
+    if check().await? {
+      return Ok(true);
+    }
+    if started.elapsed() + SCHEMA_AGREEMENT_INTERVAL > max_wait {
+      return Ok(false);
+    }
+
+    task::sleep(SCHEMA_AGREEMENT_INTERVAL).await;
+  }
+}
+
+/// Checks that a response is a result of a query which changed a schema.
+pub(crate) fn is_schema_change(frame: &Frame) -> bool {
+  frame.opcode == Opcode::Result
+    && frame
+      .body
+      .starts_with(&ResultKind::SchemaChange.into_cbytes())
+}
+
+/// Returns a change a query made to a schema.
+pub(crate) fn schema_change(frame: &Frame) -> Option<SchemaChange> {
+  if !is_schema_change(frame) {
+    return None;
+  }
+
+  // a kind of a result is followed by a change
+  let body = &frame.body[ResultKind::SchemaChange.into_cbytes().len()..];
+  proto::SchemaChange::from_cursor(&mut Cursor::new(body))
+    .ok()
+    .map(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::events::{SchemaChangeTarget, SchemaChangeType};
+  use futures::executor::block_on;
+  use std::cell::Cell;
+
+  fn version(host: u128, version: Option<u128>) -> SchemaVersion {
+    SchemaVersion {
+      host_id: Some(Uuid::from_u128(host)),
+      version: version.map(Uuid::from_u128),
+    }
+  }
+
+  #[test]
+  fn test_is_agreement() {
+    let agreed = vec![version(1, Some(10)), version(2, Some(10)), version(3, None)];
+    assert!(is_agreement(&agreed, |_| true));
+
+    let disagreed = vec![version(1, Some(10)), version(2, Some(11))];
+    assert!(!is_agreement(&disagreed, |_| true));
+    assert!(is_agreement(&disagreed, |host_id| {
+      host_id != Some(Uuid::from_u128(2))
+    }));
+    assert!(is_agreement(&[], |_| true));
+  }
+
+  #[test]
+  fn test_wait_for_agreement() {
+    let checks = Cell::new(0);
+    let check = || {
+      checks.set(checks.get() + 1);
+      futures::future::ready(Ok(checks.get() == 2))
+    };
+
+    assert!(block_on(wait_for_agreement(Duration::from_secs(1), check)).unwrap());
+    assert_eq!(checks.get(), 2);
+
+    checks.set(0);
+    assert!(!block_on(wait_for_agreement(Duration::from_millis(0), check)).unwrap());
+    assert_eq!(checks.get(), 1);
+  }
+
+  #[test]
+  fn test_schema_change() {
+    let mut body = ResultKind::SchemaChange.into_cbytes();
+    for value in &["DROPPED", "TABLE", "ks", "events"] {
+      body.extend_from_slice(&(value.len() as u16).to_be_bytes());
+      body.extend_from_slice(value.as_bytes());
+    }
+    let frame = Frame {
+      version: cassandra_proto::frame::Version::Response,
+      flags: vec![],
+      opcode: Opcode::Result,
+      stream: 0,
+      body,
+      tracing_id: None,
+      warnings: vec![],
+    };
+
+    let change = schema_change(&frame).unwrap();
+    assert_eq!(change.change_type, SchemaChangeType::Dropped);
+    assert_eq!(
+      change.target,
+      SchemaChangeTarget::Table {
+        keyspace: "ks".into(),
+        table: "events".into()
+      }
+    );
+  }
+}
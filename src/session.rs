@@ -1,61 +1,137 @@
 use std::{
-  collections::HashMap,
-  io,
-  pin::Pin,
-  task::{Context, Poll},
+  future::Future,
+  io::{self, Cursor},
+  sync::{Arc, Mutex, MutexGuard, Weak},
+  time::{Duration, Instant},
 };
 
-use async_std::{future::poll_fn, prelude::*};
+use async_std::{
+  future,
+  task::{self, JoinHandle},
+};
 use async_tls::TlsConnector;
 use cassandra_proto::{
-  error,
-  frame::{parser_async::convert_frame_into_result, Frame, IntoBytes, Opcode},
-  query::{Query, QueryBatch, QueryParams},
+  consistency::Consistency,
+  frame::{
+    frame_result::{BodyResResultPrepared, ResultKind},
+    Frame, FromCursor, IntoBytes, Opcode,
+  },
+  query::{Query, QueryBatch, QueryParams, QueryParamsBuilder},
+  types::{rows::Row, CBytes},
+};
+use futures::{
+  channel::mpsc,
+  future::{BoxFuture, FutureExt, Shared},
+  stream::StreamExt,
 };
-use futures::stream::Stream;
+use log::{debug, info, warn};
 
 use crate::{
   async_trait::async_trait,
-  authenticators::Authenticator,
+  authenticators::{Authenticator, SaslAuthenticator},
   compressor::Compression,
-  frame_channel::FrameChannel,
+  connection::Connection,
+  error,
+  events::{EventStream, EventType, ServerEvent},
   pager::{PageSize, SessionPager},
-  query::{BatchExecutor, ExecExecutor, PrepareExecutor, PreparedQuery, QueryExecutor},
+  prepared_cache::{unprepared_id, PreparedCache},
+  query::{
+    BatchExecutor, ExecExecutor, PrepareExecutor, PreparedQuery, PreparedStatement, QueryExecutor,
+    QueryOptions,
+  },
+  retry::{self, RequestInfo, RetryDecision, RetryPolicy},
+  schema_agreement::{
+    is_agreement, read_versions, schema_change, wait_for_agreement, SchemaVersion,
+    SELECT_LOCAL_SCHEMA_QUERY, SELECT_PEERS_SCHEMA_QUERY,
+  },
+  session_config::SessionConfig,
   transport::CDRSTransport,
-  utils::prepare_flags,
+  utils::{clone_query_params, prepare_flags},
   TransportTcp, TransportTls,
 };
 
-type StreamId = u16;
+/// Opens a new transport within an optional timeout when a session reconnects.
+type Connector<T> =
+  Arc<dyn Fn(Option<Duration>) -> BoxFuture<'static, io::Result<T>> + Send + Sync>;
+
+/// Reconnection which is in progress. Its result is shared by all requests
+/// which wait for it. A reason of a failure is shared as a message, requests
+/// fail with `Error::ConnectionClosed`, so they could be retried elsewhere.
+type Reconnection<T> = Shared<JoinHandle<Result<Arc<Connection<T>>, String>>>;
 
 /// Session structure which allows clients making requests to a server.
+///
+/// Session multiplexes requests over a single connection, so it can be used
+/// concurrently by many tasks. Cloning a session is cheap, all clones share
+/// the same connection.
+///
+/// If a connection is lost, requests which are in flight fail and a session
+/// reconnects as a reconnection policy says. A keyspace which was set
+/// by `USE` query is set again for a new connection.
+///
+/// Requests which are not answered within a request timeout fail with
+/// `Error::Timeout`. A timeout of a single request could be set via
+/// `QueryOptions`.
+///
+/// After a query which changed a schema a session waits until all nodes
+/// agree on a schema version, but no longer than a configured time.
+/// Sessions of a cluster session leave it to the cluster session, which
+/// knows which nodes are down.
+///
+/// Queries, executions of prepared queries and batches which fail are sent
+/// again as a retry policy of a session configuration decides. A session
+/// does not know whether a request is idempotent, so it is considered
+/// a non-idempotent one.
+///
+/// Prepared queries are cached, so a query is prepared only once. If a server
+/// does not know a prepared query any more, e.g. because it was restarted,
+/// the query is prepared again and its execution is retried. Queries which
+/// could be affected by a schema change a session made are prepared again.
+///
+/// Server events a session registered for are registered for again
+/// on each new connection.
 pub struct Session<T> {
-  channel: FrameChannel<T>,
-  responses: HashMap<StreamId, Frame>,
+  inner: Arc<Inner<T>>,
+  request_timeout: Option<Duration>,
+  prepared_cache: Arc<PreparedCache>,
+  retry_policy: Arc<dyn RetryPolicy>,
+  waits_for_schema_agreement: bool,
+}
+
+struct Inner<T> {
+  connection: Mutex<Arc<Connection<T>>>,
+  connector: Connector<T>,
+  compressor: Compression,
   authenticator: Authenticator,
+  keyspace: Mutex<Option<String>>,
+  config: SessionConfig,
+  reconnection: Mutex<Option<Reconnection<T>>>,
+  events: Mutex<Registrations>,
 }
 
-macro_rules! receive_frame {
-  ($this: expr, $stream_id: expr) => {
-    poll_fn(|cx: &mut Context| {
-      if let Some(response) = $this.responses.remove(&$stream_id) {
-        return Poll::Ready(convert_frame_into_result(response));
-      }
+/// Event types a session registered for and streams which receive them.
+#[derive(Default)]
+struct Registrations {
+  event_types: Vec<EventType>,
+  listeners: Vec<mpsc::UnboundedSender<ServerEvent>>,
+}
 
-      match Pin::new(&mut $this.channel).poll_next(cx) {
-        Poll::Ready(Some(frame)) => {
-          if frame.stream == $stream_id {
-            return Poll::Ready(convert_frame_into_result(frame));
-          } else {
-            $this.responses.insert(frame.stream, frame);
-            return Poll::Pending;
-          }
-        }
-        Poll::Ready(None) => Poll::Ready(Err("stream was terminated".into())),
-        Poll::Pending => Poll::Pending,
-      }
-    })
-  };
+fn lock<V>(value: &Mutex<V>) -> MutexGuard<'_, V> {
+  value
+    .lock()
+    .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl<T> Clone for Session<T> {
+  fn clone(&self) -> Self {
+    Session {
+      inner: self.inner.clone(),
+      request_timeout: self.request_timeout,
+      prepared_cache: self.prepared_cache.clone(),
+      retry_policy: self.retry_policy.clone(),
+      waits_for_schema_agreement: self.waits_for_schema_agreement,
+    }
+  }
 }
 
 impl Session<TransportTcp> {
@@ -64,205 +140,940 @@ impl Session<TransportTcp> {
     compressor: Compression,
     authenticator: Authenticator,
   ) -> error::Result<Self> {
-    let transport = TransportTcp::new(&addr.to_string()).await?;
-    let channel = FrameChannel::new(transport, compressor);
-    let responses = HashMap::new();
-
-    let mut session = Session {
-      channel,
-      responses,
-      authenticator,
-    };
-
-    session.startup().await?;
-
-    Ok(session)
+    Session::connect_with_config(addr, compressor, authenticator, SessionConfig::default()).await
   }
 
-  /// Converts `Session` into `SessionPager`
-  pub fn into_pager(self, page_size: PageSize) -> SessionPager<TransportTcp> {
-    SessionPager::new(self, page_size)
+  /// Creates a session which connects, reconnects and waits for responses
+  /// as a given configuration says.
+  pub async fn connect_with_config<Addr: ToString>(
+    addr: Addr,
+    compressor: Compression,
+    authenticator: Authenticator,
+    config: SessionConfig,
+  ) -> error::Result<Self> {
+    let addr = addr.to_string();
+    let connector: Connector<TransportTcp> = Arc::new(move |timeout| {
+      let addr = addr.clone();
+      async move {
+        match timeout {
+          Some(timeout) => TransportTcp::with_timeout(&addr, timeout).await,
+          None => TransportTcp::new(&addr).await,
+        }
+      }
+      .boxed()
+    });
+
+    Session::from_connector(connector, compressor, authenticator, config).await
   }
 }
 
 impl Session<TransportTls> {
   pub async fn connect_tls<Addr: ToString>(
+    addr_connector: (Addr, TlsConnector),
+    compressor: Compression,
+    authenticator: Authenticator,
+  ) -> error::Result<Self> {
+    Session::connect_tls_with_config(
+      addr_connector,
+      compressor,
+      authenticator,
+      SessionConfig::default(),
+    )
+    .await
+  }
+
+  /// Creates a TLS session which connects, reconnects and waits for
+  /// responses as a given configuration says.
+  pub async fn connect_tls_with_config<Addr: ToString>(
     (addr, connector): (Addr, TlsConnector),
     compressor: Compression,
     authenticator: Authenticator,
+    config: SessionConfig,
   ) -> error::Result<Self> {
-    let transport = TransportTls::new(&addr.to_string(), connector).await?;
-    let channel = FrameChannel::new(transport, compressor);
-    let responses = HashMap::new();
+    let addr = addr.to_string();
+    let connector: Connector<TransportTls> = Arc::new(move |timeout| {
+      let addr = addr.clone();
+      let connector = connector.clone();
+      async move {
+        match timeout {
+          Some(timeout) => TransportTls::with_timeout(&addr, connector, timeout).await,
+          None => TransportTls::new(&addr, connector).await,
+        }
+      }
+      .boxed()
+    });
 
-    let mut session = Session {
-      channel,
-      responses,
-      authenticator,
+    Session::from_connector(connector, compressor, authenticator, config).await
+  }
+}
+
+impl<T: CDRSTransport + 'static> Session<T> {
+  /// Creates a new session which uses a given connector to open
+  /// transports and performs a handshake with a server.
+  async fn from_connector(
+    connector: Connector<T>,
+    compressor: Compression,
+    authenticator: Authenticator,
+    config: SessionConfig,
+  ) -> error::Result<Self> {
+    let connection = open_connection(&connector, compressor, &authenticator, &config, None).await?;
+
+    Ok(Session {
+      request_timeout: config.get_request_timeout(),
+      prepared_cache: Arc::new(PreparedCache::new()),
+      retry_policy: config.get_retry_policy(),
+      waits_for_schema_agreement: true,
+      inner: Arc::new(Inner {
+        connection: Mutex::new(connection),
+        connector,
+        compressor,
+        authenticator,
+        keyspace: Mutex::new(None),
+        config,
+        reconnection: Mutex::new(None),
+        events: Mutex::default(),
+      }),
+    })
+  }
+
+  /// Converts `Session` into `SessionPager`
+  pub fn into_pager(self, page_size: PageSize) -> SessionPager<T> {
+    SessionPager::new(self, page_size)
+  }
+
+  /// Returns a handle to the same session which waits for responses
+  /// for a given time instead of a configured request timeout.
+  /// `None` means the handle waits without a timeout.
+  pub fn with_request_timeout(&self, request_timeout: Option<Duration>) -> Self {
+    Session {
+      inner: self.inner.clone(),
+      request_timeout,
+      prepared_cache: self.prepared_cache.clone(),
+      retry_policy: self.retry_policy.clone(),
+      waits_for_schema_agreement: self.waits_for_schema_agreement,
+    }
+  }
+
+  /// Makes the session share a cache of prepared queries with other sessions
+  /// which are connected to the same node.
+  pub(crate) fn with_prepared_cache(mut self, prepared_cache: Arc<PreparedCache>) -> Self {
+    self.prepared_cache = prepared_cache;
+
+    self
+  }
+
+  /// Makes the session consult a given retry policy instead of a configured one.
+  pub(crate) fn with_retry_policy(mut self, retry_policy: Arc<dyn RetryPolicy>) -> Self {
+    self.retry_policy = retry_policy;
+
+    self
+  }
+
+  /// Makes the session return responses to schema changes at once instead
+  /// of waiting for schema agreement, so a caller could wait for it itself.
+  pub(crate) fn without_schema_agreement_wait(mut self) -> Self {
+    self.waits_for_schema_agreement = false;
+
+    self
+  }
+
+  /// Time the session waits for a response to a request.
+  pub fn request_timeout(&self) -> Option<Duration> {
+    self.request_timeout
+  }
+
+  /// Checks that a connection of the session is still alive.
+  pub fn is_alive(&self) -> bool {
+    lock(&self.inner.connection).is_alive()
+  }
+
+  /// Returns a number of requests which were sent via the session
+  /// and are waiting for responses.
+  pub fn in_flight(&self) -> usize {
+    lock(&self.inner.connection).in_flight()
+  }
+
+  /// Registers for server events of given types and returns a stream of them.
+  /// If a connection is lost, the session reconnects and registers for them
+  /// again, so the stream ends only when the session is dropped or gives up
+  /// reconnecting. Events which were sent while reconnecting are missed.
+  pub async fn register(&self, event_types: &[EventType]) -> error::Result<EventStream> {
+    let connection = self.connection(self.request_timeout).await?;
+    let (sender, receiver) = mpsc::unbounded();
+    let (registered, is_first) = {
+      let mut events = lock(&self.inner.events);
+      let is_first = events.event_types.is_empty();
+      for event_type in event_types {
+        if !events.event_types.contains(event_type) {
+          events.event_types.push(*event_type);
+        }
+      }
+      events.listeners.push(sender);
+      (events.event_types.clone(), is_first)
     };
 
-    session.startup().await?;
+    // listen before registering, so no event which follows READY is lost
+    if is_first {
+      forward_events(&self.inner, &connection);
+    }
+    send_register(&connection, &registered, self.request_timeout).await?;
 
-    Ok(session)
+    Ok(EventStream::new(event_types.to_vec(), receiver))
   }
-}
 
-impl<T: CDRSTransport> Session<T> {
-  async fn startup(&mut self) -> error::Result<()> {
-    let ref mut compression = Compression::None;
-    let startup_frame = Frame::new_req_startup(compression.as_str());
-    let stream = startup_frame.stream;
+  /// Registers for server events of given types via a current connection
+  /// only. The stream ends when the connection is closed.
+  pub(crate) async fn register_connection(
+    &self,
+    event_types: &[EventType],
+  ) -> error::Result<EventStream> {
+    let connection = self.connection(self.request_timeout).await?;
+    // listen before registering, so no event which follows READY is lost
+    let events = connection.listen();
+    send_register(&connection, event_types, self.request_timeout).await?;
+
+    Ok(EventStream::new(event_types.to_vec(), events))
+  }
 
-    self.channel.write(&startup_frame.into_cbytes()).await?;
-    let start_response = receive_frame!(self, stream).await?;
+  /// Sends a request via an alive connection and waits for a response
+  /// no longer than a given time. Time which is spent waiting
+  /// for reconnection counts towards it.
+  async fn send(&self, frame: Frame, timeout: Option<Duration>) -> error::Result<Frame> {
+    let started = Instant::now();
+    let connection = self.connection(timeout).await?;
+    let timeout = timeout.map(|timeout| timeout.checked_sub(started.elapsed()).unwrap_or_default());
 
-    if start_response.opcode == Opcode::Ready {
-      return Ok(());
+    let response = connection.send(frame, timeout).await?;
+    self.track_keyspace(&response);
+    if let Some(change) = schema_change(&response) {
+      self.prepared_cache.invalidate(&change);
+      if self.waits_for_schema_agreement {
+        self.wait_for_schema_change().await;
+      }
     }
 
-    if start_response.opcode == Opcode::Authenticate {
-      let body = start_response.get_body()?;
-      let authenticator = body.get_authenticator().expect(
-        "Cassandra Server did communicate that it neededs
-                authentication but the auth schema was missing in the body response",
+    Ok(response)
+  }
+
+  /// Sends a request with a given consistency until it succeeds or a retry
+  /// policy returns an error. A request is built for each attempt, since
+  /// a policy may lower its consistency. Each attempt waits for a response
+  /// as long as given options say.
+  async fn send_with_retries<F>(
+    &self,
+    consistency: Consistency,
+    options: &QueryOptions,
+    request: F,
+  ) -> error::Result<Frame>
+  where
+    F: Fn(Consistency) -> Frame + Send + Sync,
+  {
+    let timeout = options.get_timeout().or(self.request_timeout);
+    let mut consistency = consistency;
+    let mut retry_count = 0;
+
+    loop {
+      let err = match self.send(request(consistency), timeout).await {
+        Ok(frame) => return Ok(frame),
+        Err(err) => err,
+      };
+
+      let info = RequestInfo {
+        consistency,
+        is_idempotent: false,
+        retry_count,
+      };
+      consistency = match retry::decide(self.retry_policy.as_ref(), &info, &err) {
+        // the session is connected to a single node only
+        RetryDecision::RetrySameNode(consistency) | RetryDecision::RetryNextNode(consistency) => {
+          consistency
+        }
+        RetryDecision::Rethrow => return Err(err),
+      };
+
+      debug!(
+        "CDRS session: retrying a request failed with {:?}, attempt {}",
+        err,
+        retry_count + 1
       );
+      retry_count += 1;
+    }
+  }
+
+  /// Checks that the node the session is connected to and all its peers
+  /// use the same schema version. The session does not know which peers
+  /// are down, so all of them are checked.
+  pub async fn check_schema_agreement(&self) -> error::Result<bool> {
+    Ok(is_agreement(&self.schema_versions().await?, |_| true))
+  }
+
+  /// Waits until nodes agree on a schema version, but no longer than
+  /// a configured maximum time. Returns whether agreement was reached.
+  pub async fn await_schema_agreement(&self) -> error::Result<bool> {
+    wait_for_agreement(self.max_schema_agreement_wait(), || {
+      self.check_schema_agreement()
+    })
+    .await
+  }
+
+  pub(crate) fn max_schema_agreement_wait(&self) -> Duration {
+    self.inner.config.get_max_schema_agreement_wait()
+  }
+
+  /// Reads schema versions of the node the session is connected to
+  /// and of its peers.
+  pub(crate) async fn schema_versions(&self) -> error::Result<Vec<SchemaVersion>> {
+    let mut versions = read_versions(&self.select(SELECT_LOCAL_SCHEMA_QUERY).await?)?;
+    versions.extend(read_versions(
+      &self.select(SELECT_PEERS_SCHEMA_QUERY).await?,
+    )?);
 
-      // This creates a new scope; avoiding a clone
-      // and we check whether
-      // 1. any authenticators has been passed in by client and if not send error back
-      // 2. authenticator is provided by the client and `auth_scheme` presented by
-      //      the server and client are same if not send error back
-      // 3. if it falls through it means the preliminary conditions are true
-
-      let auth_check = self
-        .authenticator
-        .get_cassandra_name()
-        .ok_or(error::Error::General(
-          "No authenticator was provided".to_string(),
-        ))
-        .map(|auth| {
-          if authenticator != auth {
-            let io_err = io::Error::new(
-              io::ErrorKind::NotFound,
-              format!(
-                "Unsupported type of authenticator. {:?} got,
-                             but {} is supported.",
-                authenticator, auth
-              ),
-            );
-            return Err(error::Error::Io(io_err));
-          }
-          Ok(())
-        });
-
-      if let Err(err) = auth_check {
-        return Err(err);
+    Ok(versions)
+  }
+
+  /// Selects rows via a connection directly, so a response is not inspected
+  /// the way responses to other requests are.
+  async fn select(&self, query: &str) -> error::Result<Vec<Row>> {
+    let query = Query {
+      query: query.to_string(),
+      params: QueryParamsBuilder::new().finalize(),
+    };
+    let frame = Frame::new_query(query, prepare_flags(false, false));
+
+    let connection = self.connection(self.request_timeout).await?;
+    Ok(
+      connection
+        .send(frame, self.request_timeout)
+        .await?
+        .get_body()?
+        .into_rows()
+        .unwrap_or_default(),
+    )
+  }
+
+  /// Waits for schema agreement after a schema change. A schema change
+  /// itself succeeded, so it does not fail if agreement is not reached.
+  async fn wait_for_schema_change(&self) {
+    if self.max_schema_agreement_wait() == Duration::from_secs(0) {
+      return;
+    }
+
+    match self.await_schema_agreement().await {
+      Ok(true) => {}
+      Ok(false) => warn!(
+        "CDRS session: schema agreement was not reached within {:?}",
+        self.max_schema_agreement_wait()
+      ),
+      Err(err) => warn!("CDRS session: cannot check schema agreement: {:?}", err),
+    }
+  }
+
+  /// Prepares a query on a server bypassing a cache.
+  async fn send_prepare(
+    &self,
+    query: &str,
+    with_tracing: bool,
+    with_warnings: bool,
+  ) -> error::Result<BodyResResultPrepared> {
+    let flags = prepare_flags(with_tracing, with_warnings);
+    let query_frame = Frame::new_req_prepare(query.to_string(), flags);
+
+    into_prepared(self.send(query_frame, self.request_timeout).await?)
+  }
+
+  /// Prepares a query in a given keyspace bypassing a cache. If the session
+  /// uses another keyspace now, a query is prepared via a separate connection,
+  /// so names which are not qualified by a keyspace refer to the same tables.
+  /// Prepared queries are known to a whole node, not only to a connection.
+  async fn send_prepare_in(
+    &self,
+    keyspace: Option<&str>,
+    query: &str,
+    with_tracing: bool,
+    with_warnings: bool,
+  ) -> error::Result<BodyResResultPrepared> {
+    let current = lock(&self.inner.keyspace).clone();
+    let keyspace = match keyspace {
+      Some(keyspace) if current.as_deref() != Some(keyspace) => keyspace,
+      _ => return self.send_prepare(query, with_tracing, with_warnings).await,
+    };
+
+    let connection = open_connection(
+      &self.inner.connector,
+      self.inner.compressor,
+      &self.inner.authenticator,
+      &self.inner.config,
+      Some(keyspace),
+    )
+    .await?;
+    let flags = prepare_flags(with_tracing, with_warnings);
+    let query_frame = Frame::new_req_prepare(query.to_string(), flags);
+
+    into_prepared(connection.send(query_frame, self.request_timeout).await?)
+  }
+
+  /// Executes a prepared query. If a server does not know it, e.g. because
+  /// it was restarted or a schema changed, the query is prepared again
+  /// from a cache or from a given statement and its execution is retried.
+  async fn execute(
+    &self,
+    prepared: &PreparedQuery,
+    statement: Option<&PreparedStatement>,
+    query_parameters: QueryParams,
+    options: QueryOptions,
+  ) -> error::Result<Frame> {
+    let (with_tracing, with_warnings) = (options.with_tracing(), options.with_warnings());
+    let consistency = query_parameters.consistency;
+    let execute = |id: &PreparedQuery, consistency| {
+      let mut params = clone_query_params(&query_parameters);
+      params.consistency = consistency;
+
+      Frame::new_req_execute(id, params, prepare_flags(with_tracing, with_warnings))
+    };
+
+    let err = match self
+      .send_with_retries(consistency, &options, |consistency| {
+        execute(prepared, consistency)
+      })
+      .await
+    {
+      Ok(frame) => return Ok(frame),
+      Err(err) => err,
+    };
+    let cached = match unprepared_id(&err) {
+      Some(id) => self.prepared_cache.get_by_id(&id),
+      None => return Err(err),
+    };
+    let (keyspace, query) = match (&cached, statement) {
+      (Some(unprepared), _) => (unprepared.keyspace(), unprepared.query()),
+      (None, Some(unprepared)) => (unprepared.keyspace(), unprepared.query()),
+      (None, None) => return Err(err),
+    };
+
+    debug!("CDRS session: preparing query {:?} again", query);
+    let reprepared = self
+      .send_prepare_in(keyspace, query, with_tracing, with_warnings)
+      .await?;
+    let reprepared =
+      self
+        .prepared_cache
+        .insert(keyspace.map(str::to_string), query.to_string(), reprepared);
+
+    self
+      .send_with_retries(consistency, &options, |consistency| {
+        execute(reprepared.id(), consistency)
+      })
+      .await
+  }
+
+  /// Returns an alive connection. If a connection is lost, reconnection
+  /// is started and a request either waits for it or is rejected.
+  /// A request waits for reconnection no longer than a given timeout.
+  async fn connection(&self, timeout: Option<Duration>) -> error::Result<Arc<Connection<T>>> {
+    let connection = lock(&self.inner.connection).clone();
+    if connection.is_alive() {
+      return Ok(connection);
+    }
+
+    let reconnection = self.reconnect();
+    if !self.inner.config.get_reconnection_policy().queue_requests() {
+      return Err(error::Error::ConnectionClosed(
+        "Connection was lost, session is reconnecting".to_string(),
+      ));
+    }
+
+    match timeout {
+      Some(timeout) => future::timeout(timeout, reconnection)
+        .await
+        .map_err(|_| error::Error::Timeout(timeout))?
+        .map_err(error::Error::ConnectionClosed),
+      None => reconnection.await.map_err(error::Error::ConnectionClosed),
+    }
+  }
+
+  /// Returns a reconnection which is in progress or starts a new one.
+  fn reconnect(&self) -> Reconnection<T> {
+    start_reconnection(&self.inner)
+  }
+
+  /// Remembers a keyspace which was set by `USE` query,
+  /// so it could be set again after reconnection.
+  fn track_keyspace(&self, frame: &Frame) {
+    let set_keyspace = ResultKind::SetKeyspace.into_cbytes();
+    if frame.opcode != Opcode::Result || !frame.body.starts_with(&set_keyspace) {
+      return;
+    }
+
+    if let Some(keyspace) = frame
+      .get_body()
+      .ok()
+      .and_then(|body| body.into_set_keyspace())
+    {
+      *lock(&self.inner.keyspace) = Some(keyspace.body.into_plain());
+    }
+  }
+}
+
+/// Returns a reconnection of a session which is in progress or starts a new one.
+fn start_reconnection<T: CDRSTransport + 'static>(inner: &Arc<Inner<T>>) -> Reconnection<T> {
+  let mut reconnection = lock(&inner.reconnection);
+
+  match &*reconnection {
+    Some(in_progress) if in_progress.peek().is_none() => in_progress.clone(),
+    _ => {
+      let started = task::spawn(reconnect(Arc::downgrade(inner))).shared();
+      *reconnection = Some(started.clone());
+      started
+    }
+  }
+}
+
+/// Sends events a connection receives to all event streams of a session.
+/// When the connection is closed, a session which still has event streams
+/// reconnects at once, so events are registered for again.
+fn forward_events<T: CDRSTransport + 'static>(inner: &Arc<Inner<T>>, connection: &Connection<T>) {
+  let mut events = connection.listen();
+  let inner = Arc::downgrade(inner);
+
+  task::spawn(async move {
+    while let Some(event) = events.next().await {
+      let inner = match inner.upgrade() {
+        Some(inner) => inner,
+        None => return,
+      };
+      lock(&inner.events)
+        .listeners
+        .retain(|listener| listener.unbounded_send(event.clone()).is_ok());
+    }
+
+    if let Some(inner) = inner.upgrade() {
+      let is_listened = {
+        let mut events = lock(&inner.events);
+        events.listeners.retain(|listener| !listener.is_closed());
+        !events.listeners.is_empty()
+      };
+      if is_listened && !lock(&inner.connection).is_alive() {
+        // a reconnection runs in its own task, nothing has to wait for it
+        drop(start_reconnection(&inner));
       }
+    }
+  });
+}
+
+/// Registers for events of given types via a connection.
+async fn send_register<T: CDRSTransport + 'static>(
+  connection: &Connection<T>,
+  event_types: &[EventType],
+  timeout: Option<Duration>,
+) -> error::Result<()> {
+  let register_frame =
+    Frame::new_req_register(event_types.iter().cloned().map(Into::into).collect());
+
+  let response = connection.send(register_frame, timeout).await?;
+  if response.opcode != Opcode::Ready {
+    return Err(unexpected_response("REGISTER", &response));
+  }
+
+  Ok(())
+}
+
+/// Tries to reconnect until it succeeds or a reconnection policy gives up.
+/// It stops as soon as all clones of a session are dropped. Events a session
+/// registered for are registered for again via a new connection. If a policy
+/// gives up, event streams of a session end.
+async fn reconnect<T: CDRSTransport + 'static>(
+  inner: Weak<Inner<T>>,
+) -> Result<Arc<Connection<T>>, String> {
+  const SESSION_DROPPED: &str = "Session was dropped";
+  let mut last_error = String::from("Connection was lost");
+  let mut attempt = 0usize;
+
+  loop {
+    let delay = {
+      let inner = inner.upgrade().ok_or(SESSION_DROPPED)?;
+      inner.config.get_reconnection_policy().delay(attempt)
+    };
+    let delay = match delay {
+      Some(delay) => delay,
+      None => {
+        if let Some(inner) = inner.upgrade() {
+          lock(&inner.events).listeners.clear();
+        }
+        return Err(format!("Cannot reconnect: {}", last_error));
+      }
+    };
+
+    task::sleep(delay).await;
+
+    let inner = inner.upgrade().ok_or(SESSION_DROPPED)?;
+    let keyspace = lock(&inner.keyspace).clone();
+    let result = open_connection(
+      &inner.connector,
+      inner.compressor,
+      &inner.authenticator,
+      &inner.config,
+      keyspace.as_deref(),
+    )
+    .await;
+    let registered = lock(&inner.events).event_types.clone();
+    let result = match result {
+      Ok(connection) if !registered.is_empty() => {
+        forward_events(&inner, &connection);
+        let timeout = inner.config.get_request_timeout();
+        send_register(&connection, &registered, timeout)
+          .await
+          .map(|_| connection)
+      }
+      result => result,
+    };
+
+    match result {
+      Ok(connection) => {
+        info!(
+          "CDRS session: reconnected after {} attempts",
+          attempt.saturating_add(1)
+        );
+        *lock(&inner.connection) = connection.clone();
+        return Ok(connection);
+      }
+      Err(err) => {
+        warn!("CDRS session: reconnection attempt failed: {:?}", err);
+        last_error = format!("{:?}", err);
+      }
+    }
+
+    attempt = attempt.saturating_add(1);
+  }
+}
+
+/// Opens a new connection, performs a handshake and sets a keyspace.
+async fn open_connection<T: CDRSTransport + 'static>(
+  connector: &Connector<T>,
+  compressor: Compression,
+  authenticator: &Authenticator,
+  config: &SessionConfig,
+  keyspace: Option<&str>,
+) -> error::Result<Arc<Connection<T>>> {
+  let connect_timeout = config.get_connect_timeout();
+  let transport = connector(connect_timeout)
+    .await
+    .map_err(|err| match connect_timeout {
+      Some(timeout) if err.kind() == io::ErrorKind::TimedOut => error::Error::Timeout(timeout),
+      _ => err.into(),
+    })?;
+  let connection = Arc::new(Connection::new(transport, compressor));
+  let timeout = config.get_request_timeout();
+
+  startup(&connection, authenticator, timeout).await?;
+
+  if let Some(keyspace) = keyspace {
+    let use_query = Query {
+      query: format!("USE \"{}\"", keyspace.replace('"', "\"\"")),
+      params: QueryParams::default(),
+    };
+    connection
+      .send(Frame::new_query(use_query, vec![]), timeout)
+      .await?;
+  }
+
+  Ok(connection)
+}
+
+/// Performs a handshake: sends STARTUP request and authenticates
+/// if a server requires it. Errors a server responds with are returned
+/// as they are, so a caller sees a reason, e.g. an unsupported protocol version.
+async fn startup<T: CDRSTransport + 'static>(
+  connection: &Connection<T>,
+  client_authenticator: &Authenticator,
+  timeout: Option<Duration>,
+) -> error::Result<()> {
+  let ref mut compression = Compression::None;
+  let startup_frame = Frame::new_req_startup(compression.as_str());
+
+  let start_response = connection.send(startup_frame, timeout).await?;
+  let authenticator = match required_authenticator(&start_response)? {
+    Some(authenticator) => authenticator,
+    None => return Ok(()),
+  };
+
+  let mut sasl = client_authenticator.sasl();
+  let client_authenticator_name = sasl.cassandra_name().ok_or_else(|| {
+    error::Error::Auth(format!(
+      "Server requires {} authenticator, but no authenticator was provided",
+      authenticator
+    ))
+  })?;
+  if authenticator != client_authenticator_name {
+    return Err(error::Error::Auth(format!(
+      "Unsupported type of authenticator. {} got, but {} is supported.",
+      authenticator, client_authenticator_name
+    )));
+  }
 
-      let auth_token_bytes =
-        self
-          .authenticator
-          .get_auth_token()
-          .into_plain()
-          .ok_or(error::Error::from(
-            "Authentication error: cannot get auth token",
-          ))?;
-      let auth_response = Frame::new_req_auth_response(auth_token_bytes);
-      let response_stream = auth_response.stream;
-
-      self.channel.write(&auth_response.into_cbytes()).await?;
-      receive_frame!(self, response_stream).await?;
-
-      return Ok(());
+  authenticate(sasl.as_mut(), |auth_response| {
+    connection.send(auth_response, timeout)
+  })
+  .await
+}
+
+/// Sends AUTH_RESPONSE requests until a server accepts authentication.
+/// Each challenge of a server is answered by a given authenticator.
+async fn authenticate<F, R>(sasl: &mut dyn SaslAuthenticator, send: F) -> error::Result<()>
+where
+  F: Fn(Frame) -> R,
+  R: Future<Output = error::Result<Frame>>,
+{
+  let mut token = sasl.initial_response().await?;
+  loop {
+    let response = send(Frame::new_req_auth_response(token)).await?;
+    match response.opcode {
+      Opcode::AuthChallenge => token = sasl.evaluate_challenge(auth_token(&response)?).await?,
+      Opcode::AuthSuccess => return sasl.on_success(auth_token(&response)?).await,
+      _ => return Err(unexpected_response("AUTH_RESPONSE", &response)),
     }
+  }
+}
 
-    unreachable!();
+/// Reads a token of AUTH_CHALLENGE or AUTH_SUCCESS response.
+fn auth_token(response: &Frame) -> error::Result<Option<Vec<u8>>> {
+  if response.body.is_empty() {
+    return Ok(None);
+  }
+
+  Ok(CBytes::from_cursor(&mut Cursor::new(response.body.as_slice()))?.into_plain())
+}
+
+/// Checks a response to STARTUP request and returns a class of an
+/// authenticator a server requires or `None` if a connection is ready.
+fn required_authenticator(response: &Frame) -> error::Result<Option<String>> {
+  match response.opcode {
+    Opcode::Ready => Ok(None),
+    Opcode::Authenticate => response
+      .get_body()?
+      .get_authenticator()
+      .map(|authenticator| Some(authenticator.to_string()))
+      .ok_or_else(|| {
+        error::Error::ProtocolViolation(
+          "AUTHENTICATE response has no authenticator class".to_string(),
+        )
+      }),
+    _ => Err(unexpected_response("STARTUP", response)),
   }
 }
 
+/// Reads a prepared query from a response to a PREPARE request.
+fn into_prepared(response: Frame) -> error::Result<BodyResResultPrepared> {
+  response
+    .get_body()?
+    .into_prepared()
+    .ok_or_else(|| unexpected_response("PREPARE", &response))
+}
+
+fn unexpected_response(request: &str, response: &Frame) -> error::Error {
+  error::Error::ProtocolViolation(format!(
+    "Unexpected response to {} request: {:?}",
+    request, response.opcode
+  ))
+}
+
 #[async_trait]
-impl<T: CDRSTransport> QueryExecutor for Session<T> {
-  async fn query_with_params_tw<Q: ToString + Send>(
-    mut self: Pin<&mut Self>,
+impl<T: CDRSTransport + 'static> QueryExecutor for Session<T> {
+  async fn query_with_options<Q: ToString + Send>(
+    &self,
     query: Q,
     query_params: QueryParams,
-    with_tracing: bool,
-    with_warnings: bool,
+    options: QueryOptions,
   ) -> error::Result<Frame> {
-    let query = Query {
-      query: query.to_string(),
-      params: query_params,
-    };
+    let query = query.to_string();
+    let flags = || prepare_flags(options.with_tracing(), options.with_warnings());
 
-    let flags = prepare_flags(with_tracing, with_warnings);
-    let query_frame = Frame::new_query(query, flags);
-    let stream = query_frame.stream;
+    self
+      .send_with_retries(query_params.consistency, &options, |consistency| {
+        let mut params = clone_query_params(&query_params);
+        params.consistency = consistency;
+        let query = Query {
+          query: query.clone(),
+          params,
+        };
 
-    // send frame
-    self.channel.write(&query_frame.into_cbytes()).await?;
-    receive_frame!(self, stream).await
+        Frame::new_query(query, flags())
+      })
+      .await
   }
 }
 
 #[async_trait]
-impl<T: CDRSTransport> PrepareExecutor for Session<T> {
-  async fn prepare_tw<Q: ToString + Send>(
-    mut self: Pin<&mut Self>,
+impl<T: CDRSTransport + 'static> PrepareExecutor for Session<T> {
+  async fn prepare_statement_tw<Q: ToString + Send>(
+    &self,
     query: Q,
     with_tracing: bool,
     with_warnings: bool,
-  ) -> error::Result<PreparedQuery> {
-    let flags = prepare_flags(with_tracing, with_warnings);
-
-    let query_frame = Frame::new_req_prepare(query.to_string(), flags);
-    let stream = query_frame.stream;
-
-    self.channel.write(&query_frame.into_cbytes()).await?;
+  ) -> error::Result<PreparedStatement> {
+    let query = query.to_string();
+    let keyspace = lock(&self.inner.keyspace).clone();
 
-    let prepared_id = receive_frame!(self, stream)
-      .await?
-      .get_body()?
-      .into_prepared()
-      .ok_or(error::Error::from(
-        "Cannot get prepared query ID from a response",
-      ))?
-      .id;
+    let prepared = self
+      .prepared_cache
+      .get_or_prepare(keyspace, query.clone(), || {
+        self.send_prepare(&query, with_tracing, with_warnings)
+      })
+      .await?;
 
-    Ok(prepared_id)
+    Ok(prepared.as_ref().clone())
   }
 }
 
 #[async_trait]
-impl<T: CDRSTransport> ExecExecutor for Session<T> {
-  async fn exec_with_params_tw(
-    mut self: Pin<&mut Self>,
+impl<T: CDRSTransport + 'static> ExecExecutor for Session<T> {
+  async fn exec_with_options(
+    &self,
     prepared: &PreparedQuery,
     query_parameters: QueryParams,
-    with_tracing: bool,
-    with_warnings: bool,
+    options: QueryOptions,
   ) -> error::Result<Frame> {
-    let flags = prepare_flags(with_tracing, with_warnings);
-    let executor_frame = Frame::new_req_execute(prepared, query_parameters, flags);
-    let stream = executor_frame.stream;
+    self
+      .execute(prepared, None, query_parameters, options)
+      .await
+  }
 
-    self.channel.write(&executor_frame.into_cbytes()).await?;
-    receive_frame!(self, stream).await
+  async fn exec_statement_with_options(
+    &self,
+    statement: &PreparedStatement,
+    query_parameters: QueryParams,
+    options: QueryOptions,
+  ) -> error::Result<Frame> {
+    let query_parameters = statement.bind_params(query_parameters)?;
+    self
+      .execute(statement.id(), Some(statement), query_parameters, options)
+      .await
   }
 }
 
 #[async_trait]
-impl<T: CDRSTransport> BatchExecutor for Session<T> {
-  async fn batch_with_params_tw(
-    mut self: Pin<&mut Self>,
+impl<T: CDRSTransport + 'static> BatchExecutor for Session<T> {
+  async fn batch_with_options(
+    &self,
     batch: QueryBatch,
-    with_tracing: bool,
-    with_warnings: bool,
+    options: QueryOptions,
   ) -> error::Result<Frame> {
-    let flags = prepare_flags(with_tracing, with_warnings);
-    let batch_frame = Frame::new_req_batch(batch, flags);
-    let stream = batch_frame.stream;
+    self
+      .send_with_retries(batch.consistency, &options, |consistency| {
+        let mut batch = batch.clone();
+        batch.consistency = consistency;
+
+        Frame::new_req_batch(
+          batch,
+          prepare_flags(options.with_tracing(), options.with_warnings()),
+        )
+      })
+      .await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use cassandra_proto::frame::Version;
+  use futures::executor::block_on;
+  use std::cell::RefCell;
+
+  fn response(opcode: Opcode, body: Vec<u8>) -> Frame {
+    Frame {
+      version: Version::Response,
+      flags: vec![],
+      opcode,
+      stream: 0,
+      body,
+      tracing_id: None,
+      warnings: vec![],
+    }
+  }
+
+  #[test]
+  fn test_required_authenticator() {
+    let ready = response(Opcode::Ready, vec![]);
+    assert_eq!(required_authenticator(&ready).unwrap(), None);
+
+    let authenticate = response(Opcode::Authenticate, vec![0, 4, b'A', b'u', b't', b'h']);
+    assert_eq!(
+      required_authenticator(&authenticate).unwrap(),
+      Some("Auth".to_string())
+    );
+  }
+
+  #[test]
+  fn test_required_authenticator_unexpected_response() {
+    let supported = response(Opcode::Supported, vec![0, 0]);
+    match required_authenticator(&supported) {
+      Err(error::Error::ProtocolViolation(reason)) => {
+        assert_eq!(reason, "Unexpected response to STARTUP request: Supported")
+      }
+      result => panic!("unexpected result {:?}", result),
+    }
+
+    let authenticate = response(Opcode::Authenticate, vec![]);
+    assert!(required_authenticator(&authenticate).is_err());
+  }
+
+  /// Authenticator which answers each challenge with the challenge itself.
+  struct EchoAuthenticator {
+    success: Option<Option<Vec<u8>>>,
+  }
+
+  #[async_trait]
+  impl SaslAuthenticator for EchoAuthenticator {
+    fn cassandra_name(&self) -> Option<&str> {
+      Some("Echo")
+    }
+
+    async fn initial_response(&mut self) -> error::Result<Vec<u8>> {
+      Ok(vec![0])
+    }
+
+    async fn evaluate_challenge(&mut self, challenge: Option<Vec<u8>>) -> error::Result<Vec<u8>> {
+      Ok(challenge.unwrap_or_default())
+    }
+
+    async fn on_success(&mut self, token: Option<Vec<u8>>) -> error::Result<()> {
+      self.success = Some(token);
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn test_authenticate_challenges() {
+    let responses = RefCell::new(vec![
+      response(Opcode::AuthSuccess, vec![0, 0, 0, 1, 9]),
+      response(Opcode::AuthChallenge, vec![0, 0, 0, 2, 1, 2]),
+      response(Opcode::AuthChallenge, vec![0, 0, 0, 1, 1]),
+    ]);
+    let sent = RefCell::new(vec![]);
+    let send = |request: Frame| {
+      sent.borrow_mut().push(request.body);
+      futures::future::ready(Ok(responses.borrow_mut().pop().unwrap()))
+    };
+
+    let mut sasl = EchoAuthenticator { success: None };
+    block_on(authenticate(&mut sasl, send)).unwrap();
+
+    assert_eq!(
+      sent.into_inner(),
+      vec![
+        vec![0, 0, 0, 1, 0],
+        vec![0, 0, 0, 1, 1],
+        vec![0, 0, 0, 2, 1, 2]
+      ]
+    );
+    assert_eq!(sasl.success, Some(Some(vec![9])));
+  }
+
+  #[test]
+  fn test_authenticate_unexpected_response() {
+    let send = |_| futures::future::ready(Ok(response(Opcode::Ready, vec![])));
 
-    self.channel.write(&batch_frame.into_cbytes()).await?;
-    receive_frame!(self, stream).await
+    let mut sasl = EchoAuthenticator { success: None };
+    assert!(block_on(authenticate(&mut sasl, send)).is_err());
+    assert_eq!(sasl.success, None);
   }
 }
@@ -0,0 +1,140 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use crate::{
+  reconnection::{ExponentialReconnectionPolicy, ReconnectionPolicy},
+  retry::{DefaultRetryPolicy, RetryPolicy},
+};
+
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(12);
+const DEFAULT_MAX_SCHEMA_AGREEMENT_WAIT: Duration = Duration::from_secs(10);
+
+/// Configuration of a session which describes how it connects
+/// and how long it waits for responses.
+#[derive(Clone)]
+pub struct SessionConfig {
+  connect_timeout: Option<Duration>,
+  request_timeout: Option<Duration>,
+  max_schema_agreement_wait: Duration,
+  reconnection_policy: Arc<dyn ReconnectionPolicy>,
+  retry_policy: Arc<dyn RetryPolicy>,
+}
+
+impl Default for SessionConfig {
+  fn default() -> Self {
+    SessionConfig {
+      connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+      request_timeout: Some(DEFAULT_REQUEST_TIMEOUT),
+      max_schema_agreement_wait: DEFAULT_MAX_SCHEMA_AGREEMENT_WAIT,
+      reconnection_policy: Arc::new(ExponentialReconnectionPolicy::default()),
+      retry_policy: Arc::new(DefaultRetryPolicy::new()),
+    }
+  }
+}
+
+impl fmt::Debug for SessionConfig {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("SessionConfig")
+      .field("connect_timeout", &self.connect_timeout)
+      .field("request_timeout", &self.request_timeout)
+      .field("max_schema_agreement_wait", &self.max_schema_agreement_wait)
+      .finish()
+  }
+}
+
+impl SessionConfig {
+  /// Creates a session configuration with default values:
+  /// * 5 seconds to establish a connection,
+  /// * 12 seconds to wait for a response,
+  /// * 10 seconds to wait for schema agreement,
+  /// * exponential reconnection policy,
+  /// * default retry policy.
+  pub fn new() -> Self {
+    Default::default()
+  }
+
+  /// Sets a time to establish a connection. `None` means no timeout.
+  pub fn connect_timeout(mut self, connect_timeout: Option<Duration>) -> Self {
+    self.connect_timeout = connect_timeout;
+
+    self
+  }
+
+  /// Sets a default time to wait for a response to a request.
+  /// `None` means no timeout.
+  pub fn request_timeout(mut self, request_timeout: Option<Duration>) -> Self {
+    self.request_timeout = request_timeout;
+
+    self
+  }
+
+  /// Sets a maximum time to wait until all nodes agree on a schema version
+  /// after a query which changed a schema. Zero disables waiting.
+  pub fn max_schema_agreement_wait(mut self, max_schema_agreement_wait: Duration) -> Self {
+    self.max_schema_agreement_wait = max_schema_agreement_wait;
+
+    self
+  }
+
+  /// Sets a policy which decides how a session reconnects
+  /// if a connection is lost.
+  pub fn reconnection_policy<R: ReconnectionPolicy>(mut self, reconnection_policy: R) -> Self {
+    self.reconnection_policy = Arc::new(reconnection_policy);
+
+    self
+  }
+
+  /// Sets a policy which decides whether a failed request is sent again.
+  /// A session is connected to a single node, so requests are retried
+  /// on that node even if a policy asks for a next one.
+  pub fn retry_policy<R: RetryPolicy>(mut self, retry_policy: R) -> Self {
+    self.retry_policy = Arc::new(retry_policy);
+
+    self
+  }
+
+  pub fn get_connect_timeout(&self) -> Option<Duration> {
+    self.connect_timeout
+  }
+
+  pub fn get_request_timeout(&self) -> Option<Duration> {
+    self.request_timeout
+  }
+
+  pub fn get_max_schema_agreement_wait(&self) -> Duration {
+    self.max_schema_agreement_wait
+  }
+
+  pub(crate) fn get_reconnection_policy(&self) -> &dyn ReconnectionPolicy {
+    self.reconnection_policy.as_ref()
+  }
+
+  pub(crate) fn get_retry_policy(&self) -> Arc<dyn RetryPolicy> {
+    self.retry_policy.clone()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_session_config_default() {
+    let config = SessionConfig::new();
+    assert_eq!(config.get_connect_timeout(), Some(DEFAULT_CONNECT_TIMEOUT));
+    assert_eq!(config.get_request_timeout(), Some(DEFAULT_REQUEST_TIMEOUT));
+    assert_eq!(
+      config.get_max_schema_agreement_wait(),
+      DEFAULT_MAX_SCHEMA_AGREEMENT_WAIT
+    );
+  }
+
+  #[test]
+  fn test_session_config_disable_timeouts() {
+    let config = SessionConfig::new()
+      .connect_timeout(None)
+      .request_timeout(None);
+    assert_eq!(config.get_connect_timeout(), None);
+    assert_eq!(config.get_request_timeout(), None);
+  }
+}
@@ -0,0 +1,223 @@
+//! Tokens define positions of nodes and partitions on a cluster ring.
+
+mod partitioner;
+mod routing_key;
+
+use std::{fmt, num::ParseIntError, str::FromStr};
+
+pub use partitioner::{PartitionToken, Partitioner};
+pub use routing_key::routing_key;
+
+/// Token of Murmur3 partitioner which is default for Cassandra and Scylla.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Token(pub i64);
+
+impl Token {
+  /// Computes a token of a partition with a given serialized routing key
+  /// exactly as `Murmur3Partitioner` does.
+  pub fn from_routing_key(key: &[u8]) -> Token {
+    let hash = murmur3_x64_128(key);
+
+    // i64::MIN is not a valid token
+    if hash == i64::MIN {
+      Token(i64::MAX)
+    } else {
+      Token(hash)
+    }
+  }
+}
+
+impl FromStr for Token {
+  type Err = ParseIntError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    s.trim().parse().map(Token)
+  }
+}
+
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+/// Range of tokens which starts right after `start` and ends with `end`.
+/// A range which `end` is less than `start` wraps around the ring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TokenRange {
+  pub start: Token,
+  pub end: Token,
+}
+
+impl TokenRange {
+  pub fn new(start: Token, end: Token) -> Self {
+    TokenRange { start, end }
+  }
+
+  /// Range of all tokens of Murmur3 partitioner.
+  pub fn full_ring() -> Self {
+    TokenRange::new(Token(i64::MIN), Token(i64::MAX))
+  }
+
+  pub fn is_wrapping(&self) -> bool {
+    self.end < self.start
+  }
+
+  pub fn contains(&self, token: Token) -> bool {
+    if self.is_wrapping() {
+      token > self.start || token <= self.end
+    } else {
+      token > self.start && token <= self.end
+    }
+  }
+
+  /// Splits the range into a given number of adjacent ranges of almost
+  /// equal width. Fewer ranges are returned if the range is too narrow.
+  pub fn split(&self, count: usize) -> Vec<TokenRange> {
+    let width = u128::from(self.end.0.wrapping_sub(self.start.0) as u64);
+    let count = count.max(1) as u128;
+    let boundary = |i: u128| Token(self.start.0.wrapping_add((width * i / count) as u64 as i64));
+
+    let mut ranges: Vec<TokenRange> = (0..count)
+      .map(|i| TokenRange::new(boundary(i), boundary(i + 1)))
+      .filter(|range| range.start != range.end)
+      .collect();
+    if ranges.is_empty() {
+      ranges.push(*self);
+    }
+
+    ranges
+  }
+}
+
+const C1: i64 = 0x87c3_7b91_1142_53d5_u64 as i64;
+const C2: i64 = 0x4cf5_ad43_2745_937f_u64 as i64;
+
+fn rotl(v: i64, r: u32) -> i64 {
+  (v as u64).rotate_left(r) as i64
+}
+
+fn fmix(mut k: i64) -> i64 {
+  k ^= ((k as u64) >> 33) as i64;
+  k = k.wrapping_mul(0xff51_afd7_ed55_8ccd_u64 as i64);
+  k ^= ((k as u64) >> 33) as i64;
+  k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53_u64 as i64);
+  k ^= ((k as u64) >> 33) as i64;
+  k
+}
+
+fn block(key: &[u8], offset: usize) -> i64 {
+  let mut bytes = [0u8; 8];
+  bytes.copy_from_slice(&key[offset..offset + 8]);
+  i64::from_le_bytes(bytes)
+}
+
+/// First half of 128-bit MurmurHash3 with zero seed as it is implemented by
+/// Cassandra. Cassandra treats bytes of a tail as signed ones, so it gives
+/// different results than the reference implementation for some keys.
+fn murmur3_x64_128(key: &[u8]) -> i64 {
+  let length = key.len();
+  let blocks = length / 16;
+  let mut h1: i64 = 0;
+  let mut h2: i64 = 0;
+
+  for i in 0..blocks {
+    let mut k1 = block(key, i * 16);
+    let mut k2 = block(key, i * 16 + 8);
+
+    k1 = rotl(k1.wrapping_mul(C1), 31).wrapping_mul(C2);
+    h1 ^= k1;
+    h1 = rotl(h1, 27).wrapping_add(h2);
+    h1 = h1.wrapping_mul(5).wrapping_add(0x52dc_e729);
+
+    k2 = rotl(k2.wrapping_mul(C2), 33).wrapping_mul(C1);
+    h2 ^= k2;
+    h2 = rotl(h2, 31).wrapping_add(h1);
+    h2 = h2.wrapping_mul(5).wrapping_add(0x3849_5ab5);
+  }
+
+  let tail = &key[blocks * 16..];
+  let signed = |i: usize| i64::from(tail[i] as i8);
+  let mut k1: i64 = 0;
+  let mut k2: i64 = 0;
+
+  for i in (8..tail.len()).rev() {
+    k2 ^= signed(i) << ((i - 8) * 8);
+  }
+  if tail.len() > 8 {
+    k2 = rotl(k2.wrapping_mul(C2), 33).wrapping_mul(C1);
+    h2 ^= k2;
+  }
+
+  for i in (0..tail.len().min(8)).rev() {
+    k1 ^= signed(i) << (i * 8);
+  }
+  if !tail.is_empty() {
+    k1 = rotl(k1.wrapping_mul(C1), 31).wrapping_mul(C2);
+    h1 ^= k1;
+  }
+
+  h1 ^= length as i64;
+  h2 ^= length as i64;
+
+  h1 = h1.wrapping_add(h2);
+  h2 = h2.wrapping_add(h1);
+
+  h1 = fmix(h1);
+  h2 = fmix(h2);
+
+  h1.wrapping_add(h2)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_token_from_int_routing_key() {
+    assert_eq!(
+      Token::from_routing_key(&1_i32.to_be_bytes()),
+      Token(-4_069_959_284_402_364_209)
+    );
+    assert_eq!(
+      Token::from_routing_key(&2_i32.to_be_bytes()),
+      Token(-3_248_873_570_005_575_792)
+    );
+  }
+
+  #[test]
+  fn test_split_full_ring() {
+    let ranges = TokenRange::full_ring().split(4);
+
+    assert_eq!(ranges.len(), 4);
+    assert_eq!(ranges[0].start, Token(i64::MIN));
+    assert_eq!(ranges[3].end, Token(i64::MAX));
+    assert!(ranges.windows(2).all(|pair| pair[0].end == pair[1].start));
+    assert!(ranges.iter().all(|range| !range.is_wrapping()));
+    assert_eq!(ranges[2].start, Token(-1));
+  }
+
+  #[test]
+  fn test_split_wrapping_range() {
+    let range = TokenRange::new(Token(i64::MAX - 1), Token(i64::MIN + 2));
+    let ranges = range.split(2);
+
+    assert_eq!(
+      ranges,
+      vec![
+        TokenRange::new(Token(i64::MAX - 1), Token(i64::MIN)),
+        TokenRange::new(Token(i64::MIN), Token(i64::MIN + 2)),
+      ]
+    );
+    assert!(range.contains(Token(i64::MAX)));
+    assert!(range.contains(Token(i64::MIN + 2)));
+    assert!(!range.contains(Token(0)));
+    assert_eq!(TokenRange::new(Token(0), Token(2)).split(8).len(), 2);
+  }
+
+  #[test]
+  fn test_token_from_str() {
+    assert_eq!("-42".parse::<Token>(), Ok(Token(-42)));
+    assert!("abc".parse::<Token>().is_err());
+  }
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+
+use super::Token;
+use crate::error;
+
+/// Partitioner which distributes partitions of a cluster over its nodes.
+/// It is reported by `partitioner` column of `system.local` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Partitioner {
+  /// `Murmur3Partitioner` which is default since Cassandra 1.2.
+  #[default]
+  Murmur3,
+  /// `RandomPartitioner` which hashes keys with MD5.
+  Random,
+  /// `ByteOrderedPartitioner` which orders partitions by raw bytes of keys.
+  ByteOrdered,
+}
+
+impl Partitioner {
+  /// Returns a partitioner by a name of its class, which may be qualified
+  /// by a package, e.g. `org.apache.cassandra.dht.Murmur3Partitioner`.
+  pub fn from_class_name(name: &str) -> Option<Self> {
+    match name.rsplit('.').next() {
+      Some("Murmur3Partitioner") => Some(Partitioner::Murmur3),
+      Some("RandomPartitioner") => Some(Partitioner::Random),
+      Some("ByteOrderedPartitioner") => Some(Partitioner::ByteOrdered),
+      _ => None,
+    }
+  }
+
+  /// Computes a token of a partition with a given serialized routing key.
+  pub fn token(&self, routing_key: &[u8]) -> PartitionToken {
+    match self {
+      Partitioner::Murmur3 => PartitionToken::Murmur3(Token::from_routing_key(routing_key)),
+      // a digest is a signed big-endian integer which absolute value is a token
+      Partitioner::Random => {
+        PartitionToken::Random(i128::from_be_bytes(md5::compute(routing_key).0).unsigned_abs())
+      }
+      Partitioner::ByteOrdered => PartitionToken::ByteOrdered(routing_key.to_vec()),
+    }
+  }
+
+  /// Parses a token as it is stored in system tables.
+  pub fn parse_token(&self, token: &str) -> error::Result<PartitionToken> {
+    let token = token.trim();
+    let invalid = || error::Error::from(format!("Invalid {:?} token {:?}", self, token));
+
+    match self {
+      Partitioner::Murmur3 => token
+        .parse()
+        .map(PartitionToken::Murmur3)
+        .map_err(|_| invalid()),
+      Partitioner::Random => token
+        .parse()
+        .map(PartitionToken::Random)
+        .map_err(|_| invalid()),
+      Partitioner::ByteOrdered => token
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+          std::str::from_utf8(pair)
+            .ok()
+            .filter(|pair| pair.len() == 2)
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            .ok_or_else(invalid)
+        })
+        .collect::<error::Result<_>>()
+        .map(PartitionToken::ByteOrdered),
+    }
+  }
+}
+
+/// Token which is computed by any of partitioners. Only tokens
+/// of the same partitioner could be compared.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PartitionToken {
+  Murmur3(Token),
+  Random(u128),
+  ByteOrdered(Vec<u8>),
+}
+
+impl PartitionToken {
+  /// Returns a partitioner which computes tokens of this kind.
+  pub fn partitioner(&self) -> Partitioner {
+    match self {
+      PartitionToken::Murmur3(_) => Partitioner::Murmur3,
+      PartitionToken::Random(_) => Partitioner::Random,
+      PartitionToken::ByteOrdered(_) => Partitioner::ByteOrdered,
+    }
+  }
+}
+
+impl From<Token> for PartitionToken {
+  fn from(token: Token) -> Self {
+    PartitionToken::Murmur3(token)
+  }
+}
+
+impl fmt::Display for PartitionToken {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PartitionToken::Murmur3(token) => write!(f, "{}", token),
+      PartitionToken::Random(token) => write!(f, "{}", token),
+      PartitionToken::ByteOrdered(bytes) => {
+        bytes.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_from_class_name() {
+    assert_eq!(
+      Partitioner::from_class_name("org.apache.cassandra.dht.Murmur3Partitioner"),
+      Some(Partitioner::Murmur3)
+    );
+    assert_eq!(
+      Partitioner::from_class_name("RandomPartitioner"),
+      Some(Partitioner::Random)
+    );
+    assert_eq!(
+      Partitioner::from_class_name("org.apache.cassandra.dht.OrderPreservingPartitioner"),
+      None
+    );
+  }
+
+  #[test]
+  fn test_random_token() {
+    assert_eq!(
+      Partitioner::Random.token(b""),
+      PartitionToken::Random(58_332_598_431_525_814_501_020_785_164_969_033_090)
+    );
+    assert_eq!(
+      Partitioner::Random.token(&1_i32.to_be_bytes()),
+      PartitionToken::Random(19_580_090_105_725_936_846_312_850_328_329_299_579)
+    );
+  }
+
+  #[test]
+  fn test_parse_and_display_tokens() {
+    for (partitioner, token) in &[
+      (Partitioner::Murmur3, "-4069959284402364209"),
+      (
+        Partitioner::Random,
+        "19580090105725936846312850328329299579",
+      ),
+      (Partitioner::ByteOrdered, "00ff10"),
+    ] {
+      let parsed = partitioner.parse_token(token).unwrap();
+      assert_eq!(parsed.to_string(), *token);
+    }
+
+    assert_eq!(
+      Partitioner::ByteOrdered.parse_token("00ff10").unwrap(),
+      Partitioner::ByteOrdered.token(&[0, 0xFF, 0x10])
+    );
+    assert!(Partitioner::ByteOrdered.parse_token("0").is_err());
+    assert!(Partitioner::ByteOrdered.parse_token("zz").is_err());
+    assert!(Partitioner::Random.parse_token("-1").is_err());
+  }
+}
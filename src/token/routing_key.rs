@@ -0,0 +1,70 @@
+use cassandra_proto::types::value::{Value, ValueType};
+
+use crate::error;
+
+/// Serializes values of partition key columns into a routing key which
+/// a token of a partition is computed from. A key of a single column is
+/// its value, a composite key consists of values which are prefixed by
+/// their lengths and followed by zero bytes.
+pub fn routing_key<'a, I>(components: I) -> error::Result<Vec<u8>>
+where
+  I: IntoIterator<Item = &'a Value>,
+{
+  let components = components
+    .into_iter()
+    .map(|value| match value.value_type {
+      ValueType::Normal(_) => Ok(value.body.as_slice()),
+      ValueType::Null | ValueType::NotSet => {
+        Err(error::Error::from("Partition key value is null or not set"))
+      }
+    })
+    .collect::<error::Result<Vec<&[u8]>>>()?;
+
+  match components.as_slice() {
+    [] => Err("Partition key has no values".into()),
+    [single] => Ok(single.to_vec()),
+    composite => {
+      let mut key = vec![];
+      for component in composite {
+        if component.len() > u16::MAX as usize {
+          return Err("Partition key value is too long".into());
+        }
+
+        key.extend_from_slice(&(component.len() as u16).to_be_bytes());
+        key.extend_from_slice(component);
+        key.push(0);
+      }
+
+      Ok(key)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_single_routing_key() {
+    let values = vec![Value::from(1i32)];
+
+    assert_eq!(routing_key(&values).unwrap(), vec![0, 0, 0, 1]);
+  }
+
+  #[test]
+  fn test_composite_routing_key() {
+    let values = vec![Value::from(1i32), Value::from("ab")];
+
+    assert_eq!(
+      routing_key(&values).unwrap(),
+      vec![0, 4, 0, 0, 0, 1, 0, 0, 2, b'a', b'b', 0]
+    );
+  }
+
+  #[test]
+  fn test_routing_key_without_values() {
+    assert!(routing_key(&[]).is_err());
+    assert!(routing_key(&[Value::new_null()]).is_err());
+    assert!(routing_key(&[Value::from(1i32), Value::new_not_set()]).is_err());
+  }
+}
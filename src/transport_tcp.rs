@@ -3,6 +3,7 @@ use std::{
   marker::Unpin,
   pin::Pin,
   task::{Context, Poll},
+  time::Duration,
 };
 
 use async_std::{
@@ -30,6 +31,12 @@ impl TransportTcp {
         _addr: addr.to_string(),
       })
   }
+
+  /// Constructs a new `TransportTcp`. It fails with `TimedOut` error
+  /// if a connection is not established in a given time.
+  pub async fn with_timeout(addr: &str, timeout: Duration) -> io::Result<TransportTcp> {
+    io::timeout(timeout, TransportTcp::new(addr)).await
+  }
 }
 
 impl Unpin for TransportTcp {}
@@ -3,6 +3,7 @@ use std::{
   marker::Unpin,
   pin::Pin,
   task::{Context, Poll},
+  time::Duration,
 };
 
 use async_std::{
@@ -28,12 +29,24 @@ impl TransportTls {
   pub async fn new(addr: &str, connector: TlsConnector) -> io::Result<TransportTls> {
     let tcp_stream = net::TcpStream::connect(addr).await?;
     let domain = addr.split(':').next();
-    let stream = connector.connect(domain.unwrap_or(addr), tcp_stream)?.await?;
+    let stream = connector
+      .connect(domain.unwrap_or(addr), tcp_stream)?
+      .await?;
     Ok(TransportTls {
       stream,
       _addr: addr.to_string(),
     })
   }
+
+  /// Constructs a new `TransportTls`. It fails with `TimedOut` error
+  /// if a connection and TLS handshake take longer than a given time.
+  pub async fn with_timeout(
+    addr: &str,
+    connector: TlsConnector,
+    timeout: Duration,
+  ) -> io::Result<TransportTls> {
+    io::timeout(timeout, TransportTls::new(addr, connector)).await
+  }
 }
 
 impl Unpin for TransportTls {}
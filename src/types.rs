@@ -0,0 +1,344 @@
+//! Conversion of CQL values into Rust types and back. Besides primitive
+//! types and collections, user defined types are mapped onto structs
+//! by `#[derive(FromUdt)]` and `#[derive(IntoUdt)]`, and tuples are mapped
+//! onto Rust tuples.
+
+pub use cassandra_proto::types::{
+  blob::Blob, decimal::Decimal, list::List, map::Map, tuple::Tuple, udt::UDT,
+};
+
+use std::{collections::HashMap, hash::Hash, net::IpAddr};
+
+use cassandra_proto::{
+  error::{self as proto, column_is_empty_err},
+  frame::IntoBytes,
+  types::{
+    value::{Bytes, Value, ValueType},
+    AsRustType, IntoRustByIndex, IntoRustByName,
+  },
+};
+use time::Timespec;
+use uuid::Uuid;
+
+use crate::error::Result;
+
+/// Type which a CQL value could be converted into. A value is decoded
+/// by the protocol into a raw type first, e.g. a UDT is decoded into `UDT`,
+/// and then it is converted into the type itself.
+///
+/// It is implemented for primitive types, `Vec` and `HashMap` of such types,
+/// tuples of up to 8 elements and `Option`. It could be derived for structs
+/// which represent user defined types by `#[derive(FromUdt)]`.
+pub trait FromCql: Sized {
+  /// Type which the protocol decodes a value into.
+  type Raw;
+
+  fn from_raw(raw: Self::Raw) -> Result<Self>;
+
+  /// Returns a value which null is converted into. Null is not allowed
+  /// unless a type says otherwise.
+  fn from_null(name: &str) -> Result<Self> {
+    Err(column_is_empty_err(name).into())
+  }
+}
+
+macro_rules! raw_from_cql {
+  ($($ty:ty),+) => {
+    $(
+      impl FromCql for $ty {
+        type Raw = $ty;
+
+        fn from_raw(raw: Self::Raw) -> Result<Self> {
+          Ok(raw)
+        }
+      }
+    )+
+  };
+}
+
+raw_from_cql!(
+  Blob, String, bool, i64, i32, i16, i8, f64, f32, IpAddr, Uuid, Timespec, Decimal, List, Map, UDT,
+  Tuple
+);
+
+impl<T: FromCql> FromCql for Option<T> {
+  type Raw = T::Raw;
+
+  fn from_raw(raw: Self::Raw) -> Result<Self> {
+    T::from_raw(raw).map(Some)
+  }
+
+  fn from_null(_name: &str) -> Result<Self> {
+    Ok(None)
+  }
+}
+
+/// Empty collections are returned by a server as null.
+impl<T: FromCql> FromCql for Vec<T>
+where
+  List: AsRustType<Vec<T::Raw>>,
+{
+  type Raw = List;
+
+  fn from_raw(list: List) -> Result<Self> {
+    list.as_r_type()?.into_iter().map(T::from_raw).collect()
+  }
+
+  fn from_null(_name: &str) -> Result<Self> {
+    Ok(Vec::new())
+  }
+}
+
+/// Keys are not converted, so they should be of one of primitive types.
+/// Empty maps are returned by a server as null.
+impl<K, V> FromCql for HashMap<K, V>
+where
+  K: Eq + Hash,
+  V: FromCql,
+  Map: AsRustType<HashMap<K, V::Raw>>,
+{
+  type Raw = Map;
+
+  fn from_raw(map: Map) -> Result<Self> {
+    map
+      .as_r_type()?
+      .into_iter()
+      .map(|(key, value)| V::from_raw(value).map(|value| (key, value)))
+      .collect()
+  }
+
+  fn from_null(_name: &str) -> Result<Self> {
+    Ok(HashMap::new())
+  }
+}
+
+macro_rules! tuple_from_cql {
+  ($($name:ident: $index:tt),+) => {
+    impl<$($name: FromCql),+> FromCql for ($($name,)+)
+    where
+      $(Tuple: IntoRustByIndex<<$name as FromCql>::Raw>),+
+    {
+      type Raw = Tuple;
+
+      fn from_raw(tuple: Tuple) -> Result<Self> {
+        Ok(($(get_by_index::<$name>(&tuple, $index)?,)+))
+      }
+    }
+  };
+}
+
+tuple_from_cql!(A: 0);
+tuple_from_cql!(A: 0, B: 1);
+tuple_from_cql!(A: 0, B: 1, C: 2);
+tuple_from_cql!(A: 0, B: 1, C: 2, D: 3);
+tuple_from_cql!(A: 0, B: 1, C: 2, D: 3, E: 4);
+tuple_from_cql!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+tuple_from_cql!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+tuple_from_cql!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+/// Reads an element of a tuple.
+fn get_by_index<T: FromCql>(tuple: &Tuple, index: usize) -> Result<T>
+where
+  Tuple: IntoRustByIndex<T::Raw>,
+{
+  match tuple.get_by_index(index)? {
+    Some(raw) => T::from_raw(raw),
+    None => T::from_null(&index.to_string()),
+  }
+}
+
+/// Reads a column of a row or a field of a UDT.
+#[doc(hidden)]
+pub fn get_required<C, T>(container: &C, name: &str) -> Result<T>
+where
+  C: IntoRustByName<T::Raw>,
+  T: FromCql,
+{
+  match container.get_by_name(name)? {
+    Some(raw) => T::from_raw(raw),
+    None => T::from_null(name),
+  }
+}
+
+/// Reads a column of a row or a field of a UDT which is replaced
+/// by a default value if it is missing or null.
+#[doc(hidden)]
+pub fn get_or_default<C, T>(container: &C, name: &str) -> Result<T>
+where
+  C: IntoRustByName<T::Raw>,
+  T: FromCql + Default,
+{
+  match container.get_by_name(name) {
+    Ok(Some(raw)) => T::from_raw(raw),
+    Ok(None) => Ok(T::default()),
+    Err(ref err) if is_missing(err, name) => Ok(T::default()),
+    Err(err) => Err(err.into()),
+  }
+}
+
+/// A row reports a missing column the same way as a null one,
+/// the error could be distinguished only by its message.
+fn is_missing(err: &proto::Error, name: &str) -> bool {
+  err.to_string() == column_is_empty_err(name).to_string()
+}
+
+/// Wrapper which serializes a Rust tuple as a CQL tuple. Elements could be
+/// of any type which converts into `Value`, `None` elements are null.
+///
+/// ```ignore
+/// let location: Value = CqlTuple((52.37f64, 4.89f64)).into();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CqlTuple<T>(pub T);
+
+macro_rules! tuple_into_bytes {
+  ($($name:ident: $index:tt),+) => {
+    impl<$($name: Into<Value>),+> From<CqlTuple<($($name,)+)>> for Bytes {
+      fn from(tuple: CqlTuple<($($name,)+)>) -> Bytes {
+        let mut bytes = vec![];
+        $(push_field(&mut bytes, (tuple.0).$index);)+
+        Bytes::new(bytes)
+      }
+    }
+  };
+}
+
+tuple_into_bytes!(A: 0);
+tuple_into_bytes!(A: 0, B: 1);
+tuple_into_bytes!(A: 0, B: 1, C: 2);
+tuple_into_bytes!(A: 0, B: 1, C: 2, D: 3);
+tuple_into_bytes!(A: 0, B: 1, C: 2, D: 3, E: 4);
+tuple_into_bytes!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+tuple_into_bytes!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+tuple_into_bytes!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+/// Appends a serialized field of a UDT or an element of a tuple.
+#[doc(hidden)]
+pub fn push_field<T: Into<Value>>(bytes: &mut Vec<u8>, value: T) {
+  let value = value.into();
+  match value.value_type {
+    ValueType::Normal(_) => bytes.extend_from_slice(&value.into_cbytes()),
+    // fields and elements could not be unset, they are null instead
+    ValueType::Null | ValueType::NotSet => bytes.extend_from_slice(&(-1i32).to_be_bytes()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{FromRow, FromUdt, IntoUdt};
+  use cassandra_proto::{
+    frame::{frame_result::BodyResResultRows, FromCursor},
+    types::rows::Row,
+  };
+  use std::io::Cursor;
+
+  #[derive(Debug, Clone, PartialEq, FromUdt, IntoUdt)]
+  struct Address {
+    street: String,
+    #[cdrs(rename = "zip")]
+    zip_code: Option<i32>,
+  }
+
+  #[derive(Debug, PartialEq, FromRow)]
+  struct User {
+    address: Address,
+    addresses: Vec<Address>,
+    location: (String, Option<i32>),
+  }
+
+  /// Metadata of `address` UDT with `street text` and `zip int` fields.
+  fn address_type(bytes: &mut Vec<u8>) {
+    bytes.extend_from_slice(&[0, 0x30, 0, 2, b'k', b's', 0, 7]);
+    bytes.extend_from_slice(b"address");
+    bytes.extend_from_slice(&[0, 2, 0, 6]);
+    bytes.extend_from_slice(b"street");
+    bytes.extend_from_slice(&[0, 0x0D, 0, 3, b'z', b'i', b'p', 0, 0x09]);
+  }
+
+  fn cell(bytes: &mut Vec<u8>, value: &[u8]) {
+    bytes.extend_from_slice(&(value.len() as i32).to_be_bytes());
+    bytes.extend_from_slice(value);
+  }
+
+  /// Row with `address frozen<address>`, `addresses list<frozen<address>>`
+  /// and `location tuple<text, int>` columns.
+  fn row(address: &[u8], location: &[u8]) -> Row {
+    // global table spec flag, 3 columns
+    let mut bytes = vec![0, 0, 0, 1, 0, 0, 0, 3];
+    bytes.extend_from_slice(&[0, 2, b'k', b's', 0, 2, b't', b'b']);
+    bytes.extend_from_slice(&[0, 7]);
+    bytes.extend_from_slice(b"address");
+    address_type(&mut bytes);
+    bytes.extend_from_slice(&[0, 9]);
+    bytes.extend_from_slice(b"addresses");
+    bytes.extend_from_slice(&[0, 0x20]);
+    address_type(&mut bytes);
+    bytes.extend_from_slice(&[0, 8]);
+    bytes.extend_from_slice(b"location");
+    bytes.extend_from_slice(&[0, 0x31, 0, 2, 0, 0x0D, 0, 0x09]);
+    // 1 row
+    bytes.extend_from_slice(&[0, 0, 0, 1]);
+    cell(&mut bytes, address);
+    let mut list = vec![0, 0, 0, 2];
+    cell(&mut list, address);
+    cell(&mut list, address);
+    cell(&mut bytes, &list);
+    cell(&mut bytes, location);
+
+    let body = BodyResResultRows::from_cursor(&mut Cursor::new(&bytes)).unwrap();
+    Row::from_frame_body(body).remove(0)
+  }
+
+  fn bytes_of<T: Into<Value>>(value: T) -> Vec<u8> {
+    value.into().body
+  }
+
+  #[test]
+  fn test_udt_round_trip() {
+    let address = Address {
+      street: "Main".into(),
+      zip_code: None,
+    };
+    let encoded = bytes_of(address.clone());
+    assert_eq!(
+      encoded,
+      vec![0, 0, 0, 4, b'M', b'a', b'i', b'n', 0xFF, 0xFF, 0xFF, 0xFF]
+    );
+
+    let location = bytes_of(CqlTuple(("home", Some(7i32))));
+    let user = User::from_row(&row(&encoded, &location)).unwrap();
+    assert_eq!(
+      user,
+      User {
+        address: address.clone(),
+        addresses: vec![address.clone(), address],
+        location: ("home".into(), Some(7)),
+      }
+    );
+  }
+
+  #[test]
+  fn test_null_tuple_element() {
+    let address = bytes_of(Address {
+      street: "Main".into(),
+      zip_code: Some(1),
+    });
+    let location = bytes_of(CqlTuple((None::<String>, None::<i32>)));
+
+    assert!(User::from_row(&row(&address, &location)).is_err());
+  }
+
+  #[test]
+  fn test_list_of_udts() {
+    let address = Address {
+      street: "a".into(),
+      zip_code: Some(1),
+    };
+    let list = bytes_of(vec![address.clone()]);
+    let mut expected = vec![0, 0, 0, 1];
+    cell(&mut expected, &bytes_of(address));
+
+    assert_eq!(list, expected);
+  }
+}
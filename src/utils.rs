@@ -1,4 +1,4 @@
-use cassandra_proto::frame::Flag;
+use cassandra_proto::{frame::Flag, query::QueryParams};
 
 pub fn prepare_flags(with_tracing: bool, with_warnings: bool) -> Vec<Flag> {
   let mut flags = vec![];
@@ -13,3 +13,17 @@ pub fn prepare_flags(with_tracing: bool, with_warnings: bool) -> Vec<Flag> {
 
   flags
 }
+
+/// Copies query parameters, so a request could be sent several times.
+pub fn clone_query_params(params: &QueryParams) -> QueryParams {
+  QueryParams {
+    consistency: params.consistency,
+    flags: params.flags.clone(),
+    with_names: params.with_names,
+    values: params.values.clone(),
+    page_size: params.page_size,
+    paging_state: params.paging_state.clone(),
+    serial_consistency: params.serial_consistency,
+    timestamp: params.timestamp,
+  }
+}
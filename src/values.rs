@@ -0,0 +1,83 @@
+//! Conversion of Rust types into values of bind markers. Structs with
+//! named fields could be converted by `#[derive(IntoQueryValues)]`,
+//! the ones which represent UDTs by `#[derive(IntoUdt)]`.
+
+pub use cassandra_proto::{
+  query::QueryValues,
+  types::value::{Bytes, Value},
+};
+
+/// Converts an optional value into a value which is not set if there is
+/// no value, so a column it is bound to is left intact.
+#[doc(hidden)]
+pub fn unset_if_none<T: Into<Value>>(value: Option<T>) -> Value {
+  match value {
+    Some(value) => value.into(),
+    None => Value::new_not_set(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::IntoQueryValues;
+  use cassandra_proto::types::value::ValueType;
+
+  #[derive(IntoQueryValues)]
+  struct User {
+    id: i32,
+    #[cdrs(rename = "name")]
+    login: Option<String>,
+    #[cdrs(unset_if_none)]
+    email: Option<String>,
+    #[cdrs(skip)]
+    #[allow(dead_code)]
+    is_admin: bool,
+  }
+
+  #[derive(IntoQueryValues)]
+  #[cdrs(values = "simple")]
+  struct Key {
+    id: i32,
+    name: Option<String>,
+  }
+
+  fn user() -> User {
+    User {
+      id: 7,
+      login: None,
+      email: None,
+      is_admin: true,
+    }
+  }
+
+  #[test]
+  fn test_named_values() {
+    let values = match QueryValues::from(user()) {
+      QueryValues::NamedValues(values) => values,
+      QueryValues::SimpleValues(_) => panic!("values should be named"),
+    };
+
+    let mut names: Vec<&str> = values.keys().map(String::as_str).collect();
+    names.sort();
+    assert_eq!(names, vec!["email", "id", "name"]);
+    assert_eq!(values["id"].body, vec![0, 0, 0, 7]);
+    assert!(matches!(values["name"].value_type, ValueType::Null));
+    assert!(matches!(values["email"].value_type, ValueType::NotSet));
+  }
+
+  #[test]
+  fn test_simple_values() {
+    let key = Key {
+      id: 1,
+      name: Some("a".into()),
+    };
+    let values = match QueryValues::from(key) {
+      QueryValues::SimpleValues(values) => values,
+      QueryValues::NamedValues(_) => panic!("values should be positional"),
+    };
+
+    let bodies: Vec<Vec<u8>> = values.into_iter().map(|value| value.body).collect();
+    assert_eq!(bodies, vec![vec![0, 0, 0, 1], b"a".to_vec()]);
+  }
+}
@@ -0,0 +1,192 @@
+#[cfg(test)]
+extern crate speculate;
+#[cfg(test)]
+use speculate::speculate;
+
+mod utils_bootstrap;
+
+use async_std::task;
+
+use cdrs_async::{
+  authenticators::NoneAuthenticator,
+  cluster::{ClusterConfig, ClusterSession, PoolConfig, RequestOptions, TcpConnectionManager},
+  load_balancing::{DcAwareRoundRobin, RoundRobin, RoutingInfo, TokenAware},
+  query::{ExecExecutor, PrepareExecutor, QueryExecutor},
+  retry::FallthroughRetryPolicy,
+  Compression,
+};
+
+speculate! {
+  describe "cluster session" {
+    const NODE_ADDR: &str = "127.0.0.1:9042";
+    const SELECT_RELEASE_VERSION_QUERY: &str = r#"
+      SELECT release_version FROM system.local;
+    "#;
+
+    before {
+      utils_bootstrap::bootstrap();
+    }
+
+    it "should open core connections to each node" {
+      task::block_on(async {
+        let connection_manager =
+          TcpConnectionManager::new(Compression::None, NoneAuthenticator {}.into());
+        let config = ClusterConfig::new(vec![NODE_ADDR], connection_manager)
+          .pool(PoolConfig::new().core_connections(2));
+        let session = ClusterSession::connect(config)
+          .await
+          .expect("cluster session connect");
+
+        assert_eq!(session.pool_size(NODE_ADDR), Some(2), "should open core connections");
+      });
+    }
+
+    it "should skip unreachable contact points" {
+      task::block_on(async {
+        let connection_manager =
+          TcpConnectionManager::new(Compression::None, NoneAuthenticator {}.into());
+        let config = ClusterConfig::new(vec!["127.0.0.1:1", NODE_ADDR], connection_manager);
+        let session = ClusterSession::connect(config)
+          .await
+          .expect("cluster session connect");
+
+        for _ in 0..4 {
+          session
+            .query(SELECT_RELEASE_VERSION_QUERY)
+            .await
+            .expect("could not select release version");
+        }
+      });
+    }
+
+    it "should prepare and execute queries" {
+      task::block_on(async {
+        let connection_manager =
+          TcpConnectionManager::new(Compression::None, NoneAuthenticator {}.into());
+        let config = ClusterConfig::new(vec![NODE_ADDR], connection_manager);
+        let session = ClusterSession::connect(config)
+          .await
+          .expect("cluster session connect");
+
+        let prepared = session
+          .prepare(SELECT_RELEASE_VERSION_QUERY)
+          .await
+          .expect("could not prepare a query");
+        let rows = session
+          .exec(&prepared)
+          .await
+          .expect("could not execute a query")
+          .get_body()
+          .expect("could not obtain body from a response")
+          .into_rows()
+          .expect("could not get rows from a response");
+
+        assert_eq!(rows.len(), 1, "should select a local node");
+      });
+    }
+
+    it "should read node locations" {
+      task::block_on(async {
+        let connection_manager =
+          TcpConnectionManager::new(Compression::None, NoneAuthenticator {}.into());
+        let config = ClusterConfig::new(vec![NODE_ADDR], connection_manager);
+        let session = ClusterSession::connect(config)
+          .await
+          .expect("cluster session connect");
+
+        let node = &session.nodes()[0];
+        assert!(node.host_id().is_some(), "should read a host id");
+        assert!(node.datacenter().is_some(), "should read a data center");
+        assert!(node.release_version().is_some(), "should read a release version");
+        assert!(!node.tokens().is_empty(), "should read tokens");
+      });
+    }
+
+    it "should route requests with token aware policy" {
+      task::block_on(async {
+        let connection_manager =
+          TcpConnectionManager::new(Compression::None, NoneAuthenticator {}.into());
+        let config = ClusterConfig::new(vec![NODE_ADDR], connection_manager)
+          .load_balancing(TokenAware::new(RoundRobin::new()));
+        let session = ClusterSession::connect(config)
+          .await
+          .expect("cluster session connect");
+
+        session
+          .with_routing(RoutingInfo::with_routing_key(&1_i32.to_be_bytes()))
+          .query(SELECT_RELEASE_VERSION_QUERY)
+          .await
+          .expect("could not select release version");
+      });
+    }
+
+    it "should not use remote data centers by default" {
+      task::block_on(async {
+        let connection_manager =
+          TcpConnectionManager::new(Compression::None, NoneAuthenticator {}.into());
+        let config = ClusterConfig::new(vec![NODE_ADDR], connection_manager)
+          .load_balancing(DcAwareRoundRobin::new("unknown_dc"));
+        let session = ClusterSession::connect(config)
+          .await
+          .expect("cluster session connect");
+
+        assert!(
+          session.query(SELECT_RELEASE_VERSION_QUERY).await.is_err(),
+          "should not find local nodes"
+        );
+      });
+    }
+
+    it "should not duplicate discovered contact points" {
+      task::block_on(async {
+        let connection_manager =
+          TcpConnectionManager::new(Compression::None, NoneAuthenticator {}.into());
+        let config = ClusterConfig::new(vec![NODE_ADDR], connection_manager);
+        let session = ClusterSession::connect(config)
+          .await
+          .expect("cluster session connect");
+
+        let mut host_ids: Vec<_> = session.nodes().iter().map(|node| node.host_id()).collect();
+        host_ids.sort();
+        host_ids.dedup();
+        assert_eq!(host_ids.len(), session.nodes().len(), "should keep unique nodes");
+      });
+    }
+
+    it "should connect to contact points only without peer discovery" {
+      task::block_on(async {
+        let connection_manager =
+          TcpConnectionManager::new(Compression::None, NoneAuthenticator {}.into());
+        let config = ClusterConfig::new(vec![NODE_ADDR], connection_manager)
+          .peer_discovery(false);
+        let session = ClusterSession::connect(config)
+          .await
+          .expect("cluster session connect");
+
+        assert_eq!(session.nodes().len(), 1, "should know contact points only");
+      });
+    }
+
+    it "should send idempotent requests with a custom retry policy" {
+      task::block_on(async {
+        let connection_manager =
+          TcpConnectionManager::new(Compression::None, NoneAuthenticator {}.into());
+        let config = ClusterConfig::new(vec![NODE_ADDR], connection_manager)
+          .retry_policy(FallthroughRetryPolicy::new());
+        let session = ClusterSession::connect(config)
+          .await
+          .expect("cluster session connect");
+
+        session
+          .with_options(RequestOptions::new().idempotent(true))
+          .query(SELECT_RELEASE_VERSION_QUERY)
+          .await
+          .expect("could not select release version");
+        assert!(
+          session.query("SELECT * FROM unknown_table").await.is_err(),
+          "should not retry invalid queries"
+        );
+      });
+    }
+  }
+}
@@ -0,0 +1,95 @@
+#[cfg(test)]
+extern crate speculate;
+#[cfg(test)]
+use speculate::speculate;
+
+mod utils_bootstrap;
+mod utils_session;
+
+use std::time::Duration;
+
+use async_std::task;
+use futures::stream::StreamExt;
+
+use cdrs_async::{
+  events::{EventType, SchemaChangeTarget, SchemaChangeType, ServerEvent},
+  metadata::Metadata,
+  query::QueryExecutor,
+};
+
+speculate! {
+  describe "server events" {
+    const CREATE_KEYSPACE_QUERY: &str = r#"
+      CREATE KEYSPACE IF NOT EXISTS cdrs_async_events
+        WITH REPLICATION = { 'class' : 'SimpleStrategy', 'replication_factor' : 1 };
+    "#;
+    const DROP_KEYSPACE_QUERY: &str = "DROP KEYSPACE IF EXISTS cdrs_async_events;";
+
+    before {
+      utils_bootstrap::bootstrap();
+    }
+
+    it "should receive schema change events" {
+      task::block_on(async {
+        let session = utils_session::connect_tcp().await;
+        session
+          .query(DROP_KEYSPACE_QUERY)
+          .await
+          .expect("could not drop keyspace");
+
+        let mut events = session
+          .register(&[EventType::SchemaChange])
+          .await
+          .expect("could not register for events");
+
+        session
+          .query(CREATE_KEYSPACE_QUERY)
+          .await
+          .expect("could not create keyspace");
+
+        match events.next().await {
+          Some(ServerEvent::SchemaChange(change)) => {
+            assert_eq!(change.change_type, SchemaChangeType::Created);
+            assert_eq!(
+              change.target,
+              SchemaChangeTarget::Keyspace("cdrs_async_events".into())
+            );
+          }
+          event => panic!("unexpected event {:?}", event),
+        }
+      });
+    }
+
+    it "should refresh metadata on schema changes" {
+      task::block_on(async {
+        let session = utils_session::connect_tcp().await;
+        session
+          .query(DROP_KEYSPACE_QUERY)
+          .await
+          .expect("could not drop keyspace");
+
+        let events = session
+          .register(&[EventType::SchemaChange])
+          .await
+          .expect("could not register for events");
+        let metadata = Metadata::watch(session.clone(), events)
+          .await
+          .expect("could not load metadata");
+        assert!(metadata.keyspace("cdrs_async_events").is_none());
+
+        session
+          .query(CREATE_KEYSPACE_QUERY)
+          .await
+          .expect("could not create keyspace");
+
+        for _ in 0..50 {
+          if metadata.keyspace("cdrs_async_events").is_some() {
+            return;
+          }
+          task::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("should load a created keyspace");
+      });
+    }
+  }
+}
@@ -7,8 +7,6 @@ mod utils_bootstrap;
 mod utils_keyspace;
 mod utils_session;
 
-use std::pin::Pin;
-
 use async_std::task;
 
 use cdrs_async::query::QueryExecutor;
@@ -28,11 +26,11 @@ speculate! {
 
     it "should create a new keyspace" {
       task::block_on(async {
-        let mut session = connect_tcp().await;
+        let session = connect_tcp().await;
         // create a new keyspace
-        utils_keyspace::create_keyspace(Pin::new(&mut session)).await;
+        utils_keyspace::create_keyspace(&session).await;
         // select all existing keyspaces
-        let keyspaces = Pin::new(&mut session)
+        let keyspaces = session
           .query(SELECT_KS_NAMES_QUERY)
           .await
           .expect("could not select keyspaces")
@@ -45,13 +43,13 @@ speculate! {
 
     it "should remove a keyspace" {
       task::block_on(async {
-        let mut session = connect_tcp().await;
+        let session = connect_tcp().await;
 
         // create a new keyspace
-        utils_keyspace::create_keyspace(Pin::new(&mut session)).await;
+        utils_keyspace::create_keyspace(&session).await;
 
         // select all existing keyspaces
-        let keyspaces = Pin::new(&mut session)
+        let keyspaces = session
           .query(SELECT_KS_NAMES_QUERY)
           .await
           .expect("could not select keyspaces")
@@ -63,10 +61,10 @@ speculate! {
         assert_eq!(keyspaces.len(), 1, "should create a keyspace");
 
         // drop the keyspace
-        utils_keyspace::drop_keyspace(Pin::new(&mut session)).await;
+        utils_keyspace::drop_keyspace(&session).await;
 
         // select all existing keyspaces
-        let keyspaces = Pin::new(&mut session)
+        let keyspaces = session
           .query(SELECT_KS_NAMES_QUERY)
           .await
           .expect("could not select keyspaces")
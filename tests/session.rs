@@ -0,0 +1,269 @@
+#[cfg(test)]
+extern crate speculate;
+#[cfg(test)]
+use speculate::speculate;
+
+mod utils_bootstrap;
+mod utils_session;
+
+use std::time::Duration;
+
+use async_std::task;
+use futures::{future::join_all, stream::StreamExt};
+
+use cdrs_async::{
+  error::Error,
+  query::{ExecExecutor, PrepareExecutor, QueryExecutor, QueryOptions},
+  reconnection::ConstantReconnectionPolicy,
+  scan::TableScan,
+  FromRow, IntoQueryValues, SessionConfig,
+};
+
+speculate! {
+  describe "session" {
+    const SELECT_RELEASE_VERSION_QUERY: &str = r#"
+      SELECT release_version FROM system.local;
+    "#;
+
+    before {
+      utils_bootstrap::bootstrap();
+    }
+
+    it "should run concurrent queries over a single connection" {
+      task::block_on(async {
+        let session = utils_session::connect_tcp().await;
+
+        let handles = (0..100).map(|_| {
+          let session = session.clone();
+          task::spawn(async move {
+            session
+              .query(SELECT_RELEASE_VERSION_QUERY)
+              .await
+              .expect("could not select release version")
+              .get_body()
+              .expect("could not obtain body from a response")
+              .into_rows()
+              .expect("could not get rows from a response")
+          })
+        });
+
+        for rows in join_all(handles).await {
+          assert_eq!(rows.len(), 1, "should select a local node");
+        }
+        assert_eq!(session.in_flight(), 0, "should not leave pending requests");
+      });
+    }
+
+    it "should be shareable by reference" {
+      task::block_on(async {
+        let session = utils_session::connect_tcp().await;
+
+        let (first, second) = futures::join!(
+          session.query(SELECT_RELEASE_VERSION_QUERY),
+          session.query(SELECT_RELEASE_VERSION_QUERY)
+        );
+
+        assert!(first.is_ok(), "should run the first query");
+        assert!(second.is_ok(), "should run the second query");
+      });
+    }
+
+    it "should run queries in a keyspace which was set by USE" {
+      task::block_on(async {
+        let config = SessionConfig::new().reconnection_policy(
+          ConstantReconnectionPolicy::new(Duration::from_millis(100))
+            .max_attempts(3)
+            .queue_while_reconnecting(true),
+        );
+        let session = utils_session::connect_tcp_with_config(config).await;
+
+        session.query("USE system;").await.expect("could not use keyspace");
+        session
+          .query("SELECT release_version FROM local;")
+          .await
+          .expect("could not select from a keyspace table");
+      });
+    }
+
+    it "should fail a request which is not answered in time" {
+      task::block_on(async {
+        let session = utils_session::connect_tcp()
+          .await
+          .with_request_timeout(Some(Duration::from_nanos(1)));
+
+        let err = session
+          .query(SELECT_RELEASE_VERSION_QUERY)
+          .await
+          .expect_err("should time out");
+        match err {
+          Error::Timeout(_) => {}
+          err => panic!("unexpected error {:?}", err),
+        }
+      });
+    }
+
+    it "should fail a single request which is not answered in time" {
+      task::block_on(async {
+        let session = utils_session::connect_tcp().await;
+        let options = QueryOptions::new().timeout(Duration::from_nanos(1));
+
+        let err = session
+          .query_with_options(SELECT_RELEASE_VERSION_QUERY, Default::default(), options)
+          .await
+          .expect_err("should time out");
+        match err {
+          Error::Timeout(_) => {}
+          err => panic!("unexpected error {:?}", err),
+        }
+        session
+          .query(SELECT_RELEASE_VERSION_QUERY)
+          .await
+          .expect("should wait as long as the session does");
+      });
+    }
+
+    it "should prepare the same query once" {
+      task::block_on(async {
+        let session = utils_session::connect_tcp().await;
+
+        let prepared = join_all(vec![
+          session.prepare(SELECT_RELEASE_VERSION_QUERY),
+          session.prepare(SELECT_RELEASE_VERSION_QUERY),
+        ])
+        .await;
+        let ids: Vec<_> = prepared
+          .into_iter()
+          .map(|prepared| prepared.expect("should prepare").into_plain())
+          .collect();
+        assert_eq!(ids[0], ids[1]);
+
+        let prepared = session
+          .prepare(SELECT_RELEASE_VERSION_QUERY)
+          .await
+          .expect("should prepare");
+        session.exec(&prepared).await.expect("should execute");
+      });
+    }
+
+    it "should check values of a prepared statement" {
+      task::block_on(async {
+        let session = utils_session::connect_tcp().await;
+
+        let statement = session
+          .prepare_statement("SELECT release_version FROM system.local WHERE key = ?")
+          .await
+          .expect("should prepare");
+        assert_eq!(statement.bind_columns()[0].name(), "key");
+
+        session
+          .exec_statement(&statement, vec!["local"])
+          .await
+          .expect("should execute");
+        session
+          .exec_statement(&statement, vec![1i32, 2i32])
+          .await
+          .expect_err("should reject values");
+      });
+    }
+
+    it "should convert rows into structs" {
+      task::block_on(async {
+        let session = utils_session::connect_tcp().await;
+
+        let locals: Vec<Local> = session
+          .query_as(SELECT_RELEASE_VERSION_QUERY)
+          .await
+          .expect("should select local node");
+        assert_eq!(locals.len(), 1);
+        assert!(!locals[0].version.is_empty());
+      });
+    }
+
+    it "should stream rows page by page" {
+      task::block_on(async {
+        let session = utils_session::connect_tcp().await;
+        let mut pager = session.clone().into_pager(2).prefetch(true);
+
+        let keyspaces: Vec<_> = pager
+          .query_stream("SELECT keyspace_name FROM system_schema.keyspaces;")
+          .collect()
+          .await;
+        assert!(keyspaces.len() > 2, "should fetch several pages");
+        assert!(keyspaces.iter().all(Result::is_ok));
+
+        let rows: Vec<_> = pager
+          .query_with_values(
+            "SELECT keyspace_name FROM system_schema.keyspaces WHERE keyspace_name = ?;",
+            vec!["system"],
+          )
+          .into_stream()
+          .collect()
+          .await;
+        assert_eq!(rows.len(), 1, "should bind values to each page request");
+
+        let mut stream = pager.query_stream("SELECT keyspace_name FROM system_schema.keyspaces;");
+        stream.next().await.expect("should fetch a row").expect("should fetch a page");
+        drop(stream);
+        assert_eq!(session.in_flight(), 0, "should not wait for abandoned pages");
+      });
+    }
+
+    it "should scan a table by token ranges" {
+      task::block_on(async {
+        let session = utils_session::connect_tcp().await;
+        let keyspaces = session
+          .query("SELECT keyspace_name FROM system_schema.keyspaces;")
+          .await
+          .expect("should select keyspaces")
+          .get_body()
+          .expect("should get a body")
+          .into_rows()
+          .expect("should get rows");
+
+        let mut scan = TableScan::new("system_schema.keyspaces", &["keyspace_name"])
+          .splits(8)
+          .concurrency(3)
+          .page_size(2)
+          .run(&session);
+        let mut rows = 0;
+        while let Some(row) = scan.next().await {
+          row.expect("should scan a range");
+          rows += 1;
+        }
+
+        assert_eq!(rows, keyspaces.len());
+        assert_eq!(scan.progress().completed_ranges, 8);
+        assert!(scan.checkpoint().is_complete());
+      });
+    }
+
+    it "should bind struct fields by names" {
+      task::block_on(async {
+        let session = utils_session::connect_tcp().await;
+        let key = LocalKey {
+          key: "local".into(),
+        };
+
+        let locals: Vec<Local> = session
+          .query_as_with_values(
+            "SELECT release_version FROM system.local WHERE key = :key",
+            key,
+          )
+          .await
+          .expect("should select local node");
+        assert_eq!(locals.len(), 1);
+      });
+    }
+  }
+}
+
+#[derive(FromRow)]
+struct Local {
+  #[cdrs(rename = "release_version")]
+  version: String,
+}
+
+#[derive(IntoQueryValues)]
+struct LocalKey {
+  key: String,
+}
@@ -8,9 +8,10 @@ mod utils_keyspace;
 mod utils_session;
 
 use async_std::task;
-use std::pin::Pin;
-
-use cdrs_async::query::QueryExecutor;
+use cdrs_async::{
+  metadata::{ColumnKind, Metadata},
+  query::QueryExecutor,
+};
 
 speculate! {
   describe "table" {
@@ -18,12 +19,6 @@ speculate! {
       CREATE TABLE test_keyspace.test_table (key blob PRIMARY KEY, value blob);
     "#;
 
-    const GET_TABLE_INFO_QUERY: &'static str = r#"
-      SELECT * from system_schema.tables
-        WHERE keyspace_name = 'test_keyspace'
-        AND  table_name = 'test_table';
-    "#;
-
     const DROP_TABLE_QUERY: &'static str = r#"
       DROP TABLE test_keyspace.test_table;
     "#; 
@@ -34,43 +29,50 @@ speculate! {
 
     it "should create and remove a table" {
       task::block_on(async {
-        let mut session = utils_session::connect_tcp().await;
+        let session = utils_session::connect_tcp().await;
         // create a new keyspace
-        utils_keyspace::create_keyspace(Pin::new(&mut session)).await;
+        utils_keyspace::create_keyspace(&session).await;
 
         // create a new table
-        Pin::new(&mut session)
+        session
           .query(CREATE_TABLE_QUERY)
           .await
           .expect("could not create a table");
+        assert!(
+          session
+            .check_schema_agreement()
+            .await
+            .expect("could not check schema agreement"),
+          "should wait for schema agreement"
+        );
 
         // select an info about a table
-        let keyspaces = Pin::new(&mut session)
-          .query(GET_TABLE_INFO_QUERY)
+        let metadata = Metadata::load(&session)
           .await
-          .expect("could not select table info")
-          .get_body()
-          .expect("could not obtain body from a response")
-          .into_rows()
-          .expect("could not get rows from a response");
-        assert_eq!(keyspaces.len(), 1, "should create a table");
+          .expect("could not load metadata");
+        let keyspace = metadata
+          .keyspace("test_keyspace")
+          .expect("should load a keyspace");
+        let table = keyspace.table("test_table").expect("should create a table");
+        assert_eq!(table.partition_key()[0].name, "key");
+        assert_eq!(table.column("value").map(|c| c.kind), Some(ColumnKind::Regular));
+        assert!(table.to_cql().starts_with("CREATE TABLE test_keyspace.test_table ("));
 
         // drop a table
-        Pin::new(&mut session)
+        session
           .query(DROP_TABLE_QUERY)
           .await
           .expect("could not drop a table");
 
-          // select an info about a table
-        let keyspaces = Pin::new(&mut session)
-          .query(GET_TABLE_INFO_QUERY)
+        // select an info about a table
+        metadata
+          .refresh_keyspace(&session, "test_keyspace")
           .await
-          .expect("could not select table info")
-          .get_body()
-          .expect("could not obtain body from a response")
-          .into_rows()
-          .expect("could not get rows from a response");
-        assert_eq!(keyspaces.len(), 0, "should drop a table");
+          .expect("could not refresh metadata");
+        let keyspace = metadata
+          .keyspace("test_keyspace")
+          .expect("should load a keyspace");
+        assert!(keyspace.table("test_table").is_none(), "should drop a table");
       });
     }
   }
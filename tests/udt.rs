@@ -0,0 +1,97 @@
+#[cfg(test)]
+extern crate speculate;
+#[cfg(test)]
+use speculate::speculate;
+
+mod utils_bootstrap;
+mod utils_keyspace;
+mod utils_session;
+
+use std::collections::HashMap;
+
+use async_std::task;
+use cdrs_async::{query::QueryExecutor, FromRow, FromUdt, IntoQueryValues, IntoUdt};
+
+speculate! {
+  describe "udt" {
+    const CREATE_TYPE_QUERY: &str = r#"
+      CREATE TYPE IF NOT EXISTS test_keyspace.address (street text, zip int);
+    "#;
+
+    const CREATE_TABLE_QUERY: &str = r#"
+      CREATE TABLE IF NOT EXISTS test_keyspace.users (
+        id int PRIMARY KEY,
+        address frozen<address>,
+        previous list<frozen<address>>,
+        by_kind map<text, frozen<address>>,
+        location tuple<double, double>
+      );
+    "#;
+
+    const INSERT_QUERY: &str = r#"
+      INSERT INTO test_keyspace.users (id, address, previous, by_kind, location)
+        VALUES (:id, :address, :previous, :by_kind, :location);
+    "#;
+
+    before {
+      utils_bootstrap::bootstrap();
+    }
+
+    it "should write and read UDTs and tuples" {
+      task::block_on(async {
+        let session = utils_session::connect_tcp().await;
+        utils_keyspace::create_keyspace(&session).await;
+        session
+          .query(CREATE_TYPE_QUERY)
+          .await
+          .expect("could not create a type");
+        session
+          .query(CREATE_TABLE_QUERY)
+          .await
+          .expect("could not create a table");
+
+        let home = Address {
+          street: "Main".into(),
+          zip: Some(1000),
+        };
+        let mut by_kind = HashMap::new();
+        by_kind.insert("work".to_string(), Address {
+          street: "Second".into(),
+          zip: None,
+        });
+        let user = User {
+          id: 1,
+          address: home.clone(),
+          previous: vec![home],
+          by_kind,
+          location: (52.37, 4.89),
+        };
+
+        session
+          .query_with_values(INSERT_QUERY, user.clone())
+          .await
+          .expect("could not insert a user");
+        let users: Vec<User> = session
+          .query_as("SELECT * FROM test_keyspace.users WHERE id = 1;")
+          .await
+          .expect("could not select a user");
+        assert_eq!(users, vec![user]);
+      });
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, FromUdt, IntoUdt)]
+struct Address {
+  street: String,
+  zip: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, FromRow, IntoQueryValues)]
+struct User {
+  id: i32,
+  address: Address,
+  previous: Vec<Address>,
+  by_kind: HashMap<String, Address>,
+  location: (f64, f64),
+}
@@ -1,5 +1,3 @@
-use std::pin::Pin;
-
 use cdrs_async::query::QueryExecutor;
 
 pub const CREATE_KS_QUERY: &'static str = r#"
@@ -14,14 +12,14 @@ pub const DROP_KS_QUERY: &'static str = r#"
   DROP KEYSPACE IF EXISTS test_keyspace;
   "#;
 
-pub async fn create_keyspace(executor: Pin<&mut impl QueryExecutor>) {
+pub async fn create_keyspace(executor: &impl QueryExecutor) {
   executor
     .query(CREATE_KS_QUERY)
     .await
     .expect("should create test_keyspace");
 }
 
-pub async fn drop_keyspace(executor: Pin<&mut impl QueryExecutor>) {
+pub async fn drop_keyspace(executor: &impl QueryExecutor) {
   executor
     .query(DROP_KS_QUERY)
     .await
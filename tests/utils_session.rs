@@ -1,4 +1,6 @@
-use cdrs_async::{authenticators::NoneAuthenticator, Compression, Session, TransportTcp};
+use cdrs_async::{
+  authenticators::NoneAuthenticator, Compression, Session, SessionConfig, TransportTcp,
+};
 
 pub async fn connect_tcp() -> Session<TransportTcp> {
   let authenticator_strategy = NoneAuthenticator {};
@@ -10,3 +12,16 @@ pub async fn connect_tcp() -> Session<TransportTcp> {
   .await
   .expect("session connect")
 }
+
+#[allow(dead_code)]
+pub async fn connect_tcp_with_config(config: SessionConfig) -> Session<TransportTcp> {
+  let authenticator_strategy = NoneAuthenticator {};
+  Session::connect_with_config(
+    "127.0.0.1:9042",
+    Compression::None,
+    authenticator_strategy.into(),
+    config,
+  )
+  .await
+  .expect("session connect")
+}